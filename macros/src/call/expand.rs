@@ -1,16 +1,20 @@
 use super::parse::CallDef;
-use quote::quote;
+use quote::{format_ident, quote};
 
 /// See the `fn call` docs at the `lib.rs` of this crate for a high level definition.
 pub fn expand_call(def: CallDef) -> proc_macro2::TokenStream {
-	let CallDef { pallet_struct, methods } = def;
+	let CallDef { pallet_struct, where_clause, methods } = def;
+
+	// The bounds of the `impl` block, which the runtime must satisfy to dispatch the calls.
+	let where_predicates = where_clause.map(|clause| clause.predicates).into_iter().flatten();
 
 	// This is a vector of all the callable function names.
 	let fn_name = methods.iter().map(|method| &method.name).collect::<Vec<_>>();
 
 	// This is a nested vector of all the arguments for each of the functions in `fn_name`. It does
 	// not include the `self` or `caller: T::AccountId` parameter, which we always assume are the
-	// first two parameters to these calls.
+	// first two parameters to these calls, nor `origin: T::RuntimeOrigin` which can take the place
+	// of `caller`.
 	let args_name = methods
 		.iter()
 		.map(|method| method.args.iter().map(|(name, _)| name.clone()).collect::<Vec<_>>())
@@ -23,8 +27,61 @@ pub fn expand_call(def: CallDef) -> proc_macro2::TokenStream {
 		.map(|method| method.args.iter().map(|(_, type_)| type_.clone()).collect::<Vec<_>>())
 		.collect::<Vec<_>>();
 
+	// The weight expression of each of the callable functions.
+	let weight = methods.iter().map(|method| &method.weight).collect::<Vec<_>>();
+
+	// The name of each of the callable functions, as shown in the `Call` enum.
+	let fn_name_str = fn_name.iter().map(|name| name.to_string()).collect::<Vec<_>>();
+
+	// The index of each of the callable functions, which identifies the call in its encoding.
+	let call_indices = (0..methods.len()).map(|i| i as u8).collect::<Vec<_>>();
+
+	// The names of the arguments as strings, and two sets of bindings for them used to compare
+	// two calls with each other.
+	let args_name_str = args_name
+		.iter()
+		.map(|names| names.iter().map(|name| name.to_string()).collect::<Vec<_>>())
+		.collect::<Vec<_>>();
+	let args_left = args_name
+		.iter()
+		.map(|names| names.iter().map(|name| format_ident!("left_{}", name)).collect::<Vec<_>>())
+		.collect::<Vec<_>>();
+	let args_right = args_name
+		.iter()
+		.map(|names| names.iter().map(|name| format_ident!("right_{}", name)).collect::<Vec<_>>())
+		.collect::<Vec<_>>();
+
+	// The expression calling each of the callable functions on the runtime: either as a method of
+	// the pallet, found in the runtime, or as a function given the whole runtime.
+	let fn_call = methods
+		.iter()
+		.map(|method| {
+			let name = &method.name;
+			let args = method.args.iter().map(|(name, _)| name);
+			// Note that we assume the first argument of every call after the runtime or `self` is
+			// the `caller`, or the `origin`. A `caller` must come from a signed origin.
+			let (ensure_signed, origin) = if method.takes_origin {
+				(quote!(), quote!(origin))
+			} else {
+				(quote!(let caller = crate::system::ensure_signed::<T>(origin)?;), quote!(caller))
+			};
+			let call = if method.takes_runtime {
+				quote!(#pallet_struct::<T>::#name(runtime, #origin, #( #args ),*))
+			} else {
+				quote! {
+					crate::support::HasPallet::<#pallet_struct<T>>::pallet_mut(runtime)
+						.#name(#origin, #( #args ),*)
+				}
+			};
+			quote!({
+				#ensure_signed
+				#call
+			})
+		})
+		.collect::<Vec<_>>();
+
 	// This quote block creates an `enum Call` which contains all the calls exposed by our pallet,
-	// and the `Dispatch` trait logic to route a `caller` to access those functions.
+	// and the dispatch logic to route a `caller` to access those functions.
 	let dispatch_impl = quote! {
 		// The callable functions exposed by this pallet.
 		//
@@ -36,25 +93,116 @@ pub fn expand_call(def: CallDef) -> proc_macro2::TokenStream {
 			)*
 		}
 
-		// Dispatch logic at the pallet level, mapping each of the items in the `Call` enum to the
-		// appropriate function call with all arguments, including the `caller`.
-		impl<T: Config> crate::support::Dispatch for #pallet_struct<T> {
-			type Caller = T::AccountId;
-			type Call = Call<T>;
+		impl<T: Config> Call<T> {
+			// The name of the function this call dispatches to.
+			pub fn call_name(&self) -> &'static str {
+				match self {
+					#( Call::#fn_name { .. } => #fn_name_str, )*
+				}
+			}
+
+			// The weight of this call, as declared with `#[weight(..)]`. The caller pays for it
+			// before the call is dispatched.
+			#[allow(unused_variables)]
+			pub fn weight(&self) -> crate::support::Weight {
+				match self {
+					#( Call::#fn_name { #( #args_name ),* } => #weight, )*
+				}
+			}
+		}
+
+		// We implement these traits by hand rather than deriving them, since a derive would
+		// require `T` itself to implement them, while only the argument types need to.
+		impl<T: Config> Clone for Call<T> {
+			fn clone(&self) -> Self {
+				match self {
+					#(
+						Call::#fn_name { #( #args_name ),* } => {
+							Call::#fn_name { #( #args_name: #args_name.clone() ),* }
+						},
+					)*
+				}
+			}
+		}
+
+		impl<T: Config> core::fmt::Debug for Call<T> {
+			fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+				match self {
+					#(
+						Call::#fn_name { #( #args_name ),* } => {
+							f.debug_struct(#fn_name_str)
+								#( .field(#args_name_str, #args_name) )*
+								.finish()
+						},
+					)*
+				}
+			}
+		}
+
+		impl<T: Config> PartialEq for Call<T> {
+			fn eq(&self, other: &Self) -> bool {
+				#[allow(unreachable_patterns)]
+				match (self, other) {
+					#(
+						(
+							Call::#fn_name { #( #args_name: #args_left ),* },
+							Call::#fn_name { #( #args_name: #args_right ),* },
+						) => true #( && #args_left == #args_right )*,
+					)*
+					_ => false,
+				}
+			}
+		}
 
-			fn dispatch(&mut self, caller: Self::Caller, call: Self::Call) -> crate::support::DispatchResult {
-				match call {
+		// The encoding of a call is the index of the call in the enum, followed by its arguments.
+		impl<T: Config> crate::support::Encode for Call<T> {
+			fn encode_to(&self, dest: &mut Vec<u8>) {
+				match self {
 					#(
 						Call::#fn_name { #( #args_name ),* } => {
-							self.#fn_name(
-								// Note that we assume the first argument of every call is the `caller`.
-								caller,
-								#( #args_name ),*
-							)?;
+							dest.push(#call_indices);
+							#( crate::support::Encode::encode_to(#args_name, dest); )*
 						},
 					)*
 				}
-				Ok(())
+			}
+		}
+
+		impl<T: Config> crate::support::Decode for Call<T> {
+			fn decode(input: &mut &[u8]) -> Result<Self, crate::support::CodecError> {
+				match <u8 as crate::support::Decode>::decode(input)? {
+					#(
+						#call_indices => Ok(Call::#fn_name {
+							#( #args_name: crate::support::Decode::decode(input)? ),*
+						}),
+					)*
+					_ => Err(crate::support::CodecError("invalid call index")),
+				}
+			}
+		}
+
+		// Dispatch logic at the pallet level, mapping each of the items in the `Call` enum to the
+		// appropriate function call with all arguments, including the `caller` or the `origin`.
+		//
+		// The call is dispatched on the whole runtime, which gives access to the pallet, and to the
+		// other pallets for the calls taking `runtime: &mut T`.
+		impl<T: Config> Call<T> {
+			pub fn dispatch(
+				self,
+				runtime: &mut T,
+				origin: T::RuntimeOrigin,
+			) -> crate::support::DispatchResultWithPostInfo
+			where
+				T: crate::support::HasPallet<#pallet_struct<T>>,
+				#( #where_predicates, )*
+			{
+				// A call can either return a `DispatchResult`, or a `DispatchResultWithPostInfo` to
+				// report the weight it actually used. Both are converted into the latter.
+				match self {
+					#(
+						Call::#fn_name { #( #args_name ),* } => #fn_call.map(Into::into),
+					)*
+				}
 			}
 		}
 	};
@@ -5,6 +5,7 @@ use syn::spanned::Spanned;
 mod keyword {
 	syn::custom_keyword!(T);
 	syn::custom_keyword!(AccountId);
+	syn::custom_keyword!(RuntimeOrigin);
 }
 
 /// This object will collect all the information we need to keep while parsing the callable
@@ -14,6 +15,8 @@ pub struct CallDef {
 	/// This is the name of the pallet struct where the callable functions are implemented. We
 	/// mostly assume it is `Pallet`.
 	pub pallet_struct: syn::Ident,
+	/// The `where` clause of the `impl` block, which also bounds the dispatch of the calls.
+	pub where_clause: Option<syn::WhereClause>,
 	/// This is a list of the callable functions exposed by this pallet. See `CallVariantDef`.
 	pub methods: Vec<CallVariantDef>,
 }
@@ -23,8 +26,16 @@ pub struct CallDef {
 pub struct CallVariantDef {
 	/// The function name.
 	pub name: syn::Ident,
+	/// Whether the first argument of the function is `runtime: &mut T` rather than `self`.
+	pub takes_runtime: bool,
+	/// Whether the second argument of the function is `origin: T::RuntimeOrigin` rather than
+	/// `caller: T::AccountId`.
+	pub takes_origin: bool,
 	/// Information on args of the function: `(name, type)`.
 	pub args: Vec<(syn::Ident, Box<syn::Type>)>,
+	/// The expression given to `#[weight(..)]`, which computes the weight of the call. It can use
+	/// the args of the function, by reference.
+	pub weight: syn::Expr,
 }
 
 impl CallDef {
@@ -43,6 +54,8 @@ impl CallDef {
 			_ => panic!("not supported tokens"),
 		};
 
+		let where_clause = item_impl.generics.where_clause.clone();
+
 		// Here is where we will store all the callable functions.
 		let mut methods = vec![];
 		for item in item_impl.items {
@@ -50,31 +63,54 @@ impl CallDef {
 				// Here is where we will store all the args for each callable functions.
 				let mut args = vec![];
 
-				// First argument should be some variant of `self`.
-				match method.sig.inputs.first() {
-					Some(syn::FnArg::Receiver(_)) => {},
+				// First argument should be some variant of `self`, or `runtime: &mut T` for calls
+				// which need to access other pallets.
+				let takes_runtime = match method.sig.inputs.first() {
+					Some(syn::FnArg::Receiver(_)) => false,
+					Some(syn::FnArg::Typed(arg)) => {
+						check_runtime_arg(arg)?;
+						true
+					},
 					_ => {
-						let msg = "Invalid call, first argument must be a variant of self";
+						let msg = "Invalid call, first argument must be a variant of self or \
+							`runtime: &mut T`";
 						return Err(syn::Error::new(method.sig.span(), msg))
 					},
-				}
+				};
 
-				// The second argument should be the `caller: T::AccountId` argument.
-				match method.sig.inputs.iter().skip(1).next() {
+				// The second argument should be the `caller: T::AccountId` argument, or the
+				// `origin: T::RuntimeOrigin` argument for calls which are not only made by signed
+				// accounts.
+				let takes_origin = match method.sig.inputs.iter().skip(1).next() {
+					Some(syn::FnArg::Typed(arg)) if is_origin_arg(arg) => {
+						check_origin_arg(arg)?;
+						true
+					},
 					Some(syn::FnArg::Typed(arg)) => {
 						// Here we specifically check that this argument is as we expect for
 						// `caller: T::AccountId`.
 						check_caller_arg(arg)?;
+						false
 					},
 					_ => {
-						let msg = "Invalid call, second argument should be `caller: T::AccountId`";
+						let msg = "Invalid call, second argument should be `caller: T::AccountId` \
+							or `origin: T::RuntimeOrigin`";
 						return Err(syn::Error::new(method.sig.span(), msg))
 					},
-				}
+				};
 
 				let fn_name = method.sig.ident.clone();
 
-				// Parsing the rest of the args. Skipping 2 for `self` and `caller`.
+				// Every call must declare its weight with `#[weight(..)]`.
+				let weight = match method.attrs.iter().find(|attr| attr.path().is_ident("weight")) {
+					Some(attr) => attr.parse_args::<syn::Expr>()?,
+					None => {
+						let msg = "Invalid call, missing `#[weight(..)]` attribute";
+						return Err(syn::Error::new(method.sig.span(), msg))
+					},
+				};
+
+				// Parsing the rest of the args. Skipping 2 for `self` and `caller` or `origin`.
 				for arg in method.sig.inputs.iter().skip(2) {
 					// All arguments should be typed.
 					let arg = if let syn::FnArg::Typed(arg) = arg {
@@ -96,15 +132,61 @@ impl CallDef {
 				}
 
 				// Store all the function name and the arg data for the function.
-				methods.push(CallVariantDef { name: fn_name, args });
+				methods.push(CallVariantDef {
+					name: fn_name,
+					takes_runtime,
+					takes_origin,
+					args,
+					weight,
+				});
 			}
 		}
 
+		if methods.len() > u8::MAX as usize {
+			let msg = "Invalid pallet::call, too many callable functions";
+			return Err(syn::Error::new(pallet_struct.span(), msg))
+		}
+
 		// Return all callable functions for this pallet.
-		Ok(Self { pallet_struct, methods })
+		Ok(Self { pallet_struct, where_clause, methods })
 	}
 }
 
+/// Check runtime arg is exactly: `runtime: &mut T`.
+///
+/// This is kept strict to keep the code simple.
+pub fn check_runtime_arg(arg: &syn::PatType) -> syn::Result<()> {
+	pub struct CheckRuntimeArg;
+	impl syn::parse::Parse for CheckRuntimeArg {
+		fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+			input.parse::<syn::Token![&]>()?;
+			input.parse::<syn::Token![mut]>()?;
+			input.parse::<keyword::T>()?;
+			Ok(Self)
+		}
+	}
+
+	// This checks the arg name is `runtime`.
+	match &*arg.pat {
+		syn::Pat::Ident(ident) if ident.ident == "runtime" => {},
+		_ => {
+			let msg = "Invalid name for first parameter: expected `self` or `runtime: &mut T`";
+			return Err(syn::Error::new(arg.pat.span(), msg))
+		},
+	}
+
+	// This checks the type is `&mut T` with `CheckRuntimeArg`.
+	let ty = &arg.ty;
+	syn::parse2::<CheckRuntimeArg>(ty.to_token_stream()).map_err(|e| {
+		let msg = "Invalid type for first parameter: expected `runtime: &mut T`";
+		let mut err = syn::Error::new(ty.span(), msg);
+		err.combine(e);
+		err
+	})?;
+
+	Ok(())
+}
+
 /// Check caller arg is exactly: `caller: T::AccountId`.
 ///
 /// This is kept strict to keep the code simple.
@@ -139,3 +221,39 @@ pub fn check_caller_arg(arg: &syn::PatType) -> syn::Result<()> {
 
 	Ok(())
 }
+
+/// Whether the arg is named like an origin arg, `origin` or `_origin`, in which case it is checked
+/// with `check_origin_arg` rather than `check_caller_arg`.
+fn is_origin_arg(arg: &syn::PatType) -> bool {
+	match &*arg.pat {
+		syn::Pat::Ident(ident) => ident.ident == "origin" || ident.ident == "_origin",
+		_ => false,
+	}
+}
+
+/// Check origin arg is exactly: `origin: T::RuntimeOrigin`.
+///
+/// This is kept strict to keep the code simple.
+pub fn check_origin_arg(arg: &syn::PatType) -> syn::Result<()> {
+	pub struct CheckOriginArg;
+	impl syn::parse::Parse for CheckOriginArg {
+		fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+			input.parse::<keyword::T>()?;
+			input.parse::<syn::Token![::]>()?;
+			input.parse::<keyword::RuntimeOrigin>()?;
+			Ok(Self)
+		}
+	}
+
+	// This checks the type is `T::RuntimeOrigin` with `CheckOriginArg`. The name was already
+	// checked by `is_origin_arg`.
+	let ty = &arg.ty;
+	syn::parse2::<CheckOriginArg>(ty.to_token_stream()).map_err(|e| {
+		let msg = "Invalid type for second parameter: expected `origin: T::RuntimeOrigin`";
+		let mut err = syn::Error::new(ty.span(), msg);
+		err.combine(e);
+		err
+	})?;
+
+	Ok(())
+}
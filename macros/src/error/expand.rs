@@ -0,0 +1,58 @@
+use super::parse::ErrorDef;
+use quote::quote;
+
+/// See the `fn error` docs at the `lib.rs` of this crate for a high level definition.
+pub fn expand_error(def: ErrorDef) -> proc_macro2::TokenStream {
+	let ErrorDef { error_enum, variants } = def;
+
+	// The index of each variant, which is what identifies the error inside of the pallet.
+	let indices = (0..variants.len()).map(|i| i as u8).collect::<Vec<_>>();
+	// The name of each variant, which we keep as a human readable message.
+	let names = variants.iter().map(|variant| variant.to_string()).collect::<Vec<_>>();
+
+	// This quote block gives access to the index and name of each error, and allows converting it
+	// to and from a `DispatchError`.
+	let error_impl = quote! {
+		impl #error_enum {
+			// The index of this error inside of the enum.
+			pub fn index(&self) -> u8 {
+				match self {
+					#( Self::#variants => #indices, )*
+				}
+			}
+
+			// The name of this error, as it is written in the enum.
+			pub fn as_str(&self) -> &'static str {
+				match self {
+					#( Self::#variants => #names, )*
+				}
+			}
+		}
+
+		// Convert the error into a `DispatchError`. The pallet index is left empty, since only the
+		// runtime knows where the pallet lives, and it fills it in while dispatching the call.
+		impl From<#error_enum> for crate::support::DispatchError {
+			fn from(error: #error_enum) -> Self {
+				crate::support::DispatchError::Module(crate::support::ModuleError {
+					index: None,
+					error: error.index(),
+					message: error.as_str(),
+				})
+			}
+		}
+
+		// Look up an error from its index, for example the `error` field of a `ModuleError`.
+		impl TryFrom<u8> for #error_enum {
+			type Error = ();
+
+			fn try_from(index: u8) -> Result<Self, ()> {
+				match index {
+					#( #indices => Ok(Self::#variants), )*
+					_ => Err(()),
+				}
+			}
+		}
+	};
+
+	error_impl
+}
@@ -0,0 +1,51 @@
+use syn::spanned::Spanned;
+
+/// This object will collect all the information we need to keep while parsing the `Error` enum of
+/// a pallet.
+#[derive(Debug)]
+pub struct ErrorDef {
+	/// This is the name of the enum used by the user. We mostly assume it is `Error`.
+	pub error_enum: syn::Ident,
+	/// This is the list of the variants of the enum, in declaration order. The position of a
+	/// variant in this list is its index in the `ModuleError`.
+	pub variants: Vec<syn::Ident>,
+}
+
+impl ErrorDef {
+	pub fn try_from(item: syn::Item) -> syn::Result<Self> {
+		// First we check that we are parsing an `enum`.
+		let item_enum = if let syn::Item::Enum(item) = item {
+			item
+		} else {
+			return Err(syn::Error::new(item.span(), "Invalid pallet::error, expected item enum"))
+		};
+
+		// Errors are identified by their index, so we keep them as simple as possible.
+		if !item_enum.generics.params.is_empty() {
+			let msg = "Invalid pallet::error, generics are not supported";
+			return Err(syn::Error::new(item_enum.generics.span(), msg))
+		}
+
+		if item_enum.variants.len() > u8::MAX as usize {
+			let msg = "Invalid pallet::error, too many variants";
+			return Err(syn::Error::new(item_enum.span(), msg))
+		}
+
+		let mut variants = vec![];
+		for variant in item_enum.variants {
+			// Each variant should be a unit variant without an explicit discriminant, so that its
+			// index is simply its position in the enum.
+			if !matches!(variant.fields, syn::Fields::Unit) {
+				let msg = "Invalid pallet::error, variants must not have fields";
+				return Err(syn::Error::new(variant.fields.span(), msg))
+			}
+			if let Some((_, discriminant)) = &variant.discriminant {
+				let msg = "Invalid pallet::error, variants must not have explicit discriminants";
+				return Err(syn::Error::new(discriminant.span(), msg))
+			}
+			variants.push(variant.ident);
+		}
+
+		Ok(Self { error_enum: item_enum.ident, variants })
+	}
+}
@@ -1,6 +1,31 @@
 mod call;
+mod error;
 mod runtime;
 
+/// Expand the callable functions of a pallet.
+///
+/// Every function in the `impl` block becomes a variant of `enum Call`. Each function must declare
+/// its weight with a `#[weight(..)]` attribute, whose expression can use the arguments of the
+/// function by reference. It must return either a `support::DispatchResult`, or a
+/// `support::DispatchResultWithPostInfo` to report a lower actual weight.
+///
+/// The first argument of a function is either `self`, or `runtime: &mut T` for a function which
+/// needs other pallets, which it reaches through `support::HasPallet`. The bounds this requires on
+/// `T` go in the `where` clause of the `impl` block. The second argument is either
+/// `caller: T::AccountId`, for a function which can only be called by a signed origin, or
+/// `origin: T::RuntimeOrigin`, for a function which checks the origin itself, for example with
+/// `system::ensure_root`. This generates:
+/// - `fn dispatch()` on `Call`, which routes a `Call` to its function, given the runtime and the
+///   origin of the call. A function taking a `caller` fails with `system::Error::BadOrigin` when
+///   the origin is not signed.
+/// - `fn call_name()` - which returns the name of the function a `Call` dispatches to.
+/// - `fn weight()` - which returns the weight of a `Call`, as declared by its function.
+/// - implements `Clone`, `Debug` and `PartialEq` for `Call`, which only require the arguments to
+///   implement them. An argument can hold calls of the runtime, such as `Box<T::RuntimeCall>` or
+///   `Vec<T::RuntimeCall>`, when the `Config` of the pallet bounds its `RuntimeCall` by these
+///   traits.
+/// - implements `support::Encode` and `support::Decode` for `Call`. A call is encoded as the index
+///   of its function in the `impl` block, as a `u8`, followed by its arguments in order.
 #[proc_macro_attribute]
 pub fn call(
 	attr: proc_macro::TokenStream,
@@ -9,22 +34,82 @@ pub fn call(
 	call::call(attr, item)
 }
 
+/// Expand the `Error` enum of a pallet.
+///
+/// The enum must only contain unit variants. Each variant is identified by its position in the
+/// enum, and this generates:
+/// - `fn index()` and `fn as_str()` - which return the index and the name of the variant.
+/// - `impl From<Error> for support::DispatchError` - which creates a `DispatchError::Module`, so
+///   pallet calls can return their errors with `?`. The pallet index is filled in by the runtime.
+/// - `impl TryFrom<u8> for Error` - which finds the variant back from its index.
+#[proc_macro_attribute]
+pub fn error(
+	attr: proc_macro::TokenStream,
+	item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+	error::error(attr, item)
+}
+
 /// Expand the `Runtime` definition.
 ///
-/// This generates function implementations on `Runtime`:
+/// This generates function implementations on `Runtime`. The functions to build the runtime and
+/// its blocks are public, so a runtime can be used outside of the module declaring it, like the
+/// mock runtime of the tests of the pallets:
 /// - `fn new()` - which generates a new instance of the runtime, by instantiating all the pallets
-///   included in the runtime.
+///   included in the runtime over an in-memory `support::Storage`, and building the default
+///   genesis configuration of every pallet.
+/// - `fn with_storage()` - which does the same over the given `support::Storage`, shared by all
+///   the pallets.
+/// - `fn from_genesis()` - which generates a new instance of the runtime, and builds its genesis.
+/// - `fn build_genesis()` - which builds the initial state of every pallet from a
+///   `RuntimeGenesisConfig`, in the order of the `Runtime` struct. It fails if the configuration of
+///   a pallet is invalid. It then records the hash of the genesis block, whose header commits to
+///   the genesis state, as the hash of block `0` in `system`.
 /// - `fn execute_block()` - which handles basic logic for executing a block of extrinsics. It does
 ///   basic actions like incrementing the block number and checking the block to be executed has a
-///   valid block number.
+///   valid block number, parent hash, extrinsics root and state root. Every extrinsic must be
+///   signed by its caller over `support::signing_payload`, with the current nonce of the caller,
+///   or the block is rejected with a `system::Error`. The block is also rejected if the weight of
+///   its calls exceeds `system::Config::MAX_BLOCK_WEIGHT`, or if a caller cannot pay the fee for
+///   the weight of its call. The fee is withdrawn and then corrected to the actual weight of the
+///   call through `support::ChargeTransaction`, which the runtime must implement. The whole block
+///   is executed in a transaction, and every extrinsic is dispatched in its own nested
+///   transaction, so that a failed call does not leave partial writes behind. The events emitted
+///   by the pallets while applying an extrinsic are then collected with `take_events()` and
+///   deposited in `system`, which keeps the event log of the current block. Around the extrinsics,
+///   every pallet runs the `on_initialize`, `on_idle` and `on_finalize` hooks of its
+///   `support::Hooks`, in the order of the `Runtime` struct, starting with system. The outcome of
+///   every extrinsic is returned in a `support::BlockReceipt`.
+/// - `fn build_block()` - which builds the next block out of a list of signed extrinsics,
+///   computing its header by executing them and then rolling back the state.
+/// - `fn state_root()` - which computes the merkle root of the state of every pallet, including
+///   system. This requires every pallet to implement `support::Encode` over its storage.
 ///
 /// This also generates code needed for dispatching calls to the pallets:
 /// - Note: For simplicity, we assume that the system pallet is not callable.
 /// - `enum RuntimeCall` - an "outer"-enum representing the accumulation of all possible calls to
-///   all pallets. The system pallet is not included.
-/// - implements the trait `support::Dispatch` to dispatch calls to the appropriate pallet. Basic
-///   logic like incrementing the nonce of the user is included in the generated code. The system
-///   pallet is not included.
+///   all pallets. The system pallet is not included. It implements `support::Encode` and
+///   `support::Decode`, where a call is encoded as the index of its pallet, as a `u8`, followed by
+///   the encoding of the pallet `Call`. It also implements `support::GetWeight`, so pallets
+///   dispatching calls themselves can declare their weight.
+/// - implements the trait `support::Dispatch` to dispatch calls from a
+///   `system::Config::RuntimeOrigin` to the appropriate pallet. Extrinsics are dispatched from the
+///   signed origin of their caller. The system pallet is not included. Errors returned by a
+///   pallet are tagged with the index of the pallet, which is its position in the `Runtime`
+///   struct, `system` being `0`.
+/// - `struct RuntimeGenesisConfig` - which holds the `GenesisConfig` of every pallet, including
+///   system, in a field named after the pallet. Every pallet must declare a `GenesisConfig<T>`,
+///   which implements `Default` and `support::BuildGenesisConfig`.
+/// - `enum RuntimeEvent` - an "outer"-enum representing the accumulation of the `Event` enums of
+///   all pallets. The system pallet is not included. The `Pallet` of every pallet must implement
+///   `support::PalletEvent`, which names its `Event`.
+/// - implements the trait `support::Transactional`, which opens, commits and rolls back a
+///   transaction on the `support::Storage` of system, shared by all the pallets, and then on every
+///   pallet, including system. This requires every pallet to implement `support::Transactional`
+///   too.
+/// - implements the trait `support::HasPallet` for every pallet, including system, so that the
+///   calls of a pallet can access the other pallets of the runtime. `HasPallet::INDEX` is the index
+///   of the pallet.
 #[proc_macro_attribute]
 pub fn runtime(
 	attr: proc_macro::TokenStream,
@@ -9,41 +9,383 @@ pub fn expand_runtime(def: RuntimeDef) -> proc_macro2::TokenStream {
 	let pallet_names = pallets.iter().map(|(name, _)| name.clone()).collect::<Vec<_>>();
 	// This is a vector of all the pallet types, not including system.
 	let pallet_types = pallets.iter().map(|(_, type_)| type_.clone()).collect::<Vec<_>>();
+	// This is a vector of all the pallet names as strings, not including system.
+	let pallet_name_strs = pallet_names.iter().map(|name| name.to_string()).collect::<Vec<_>>();
+	// This is a vector of the index of each pallet in the `Runtime` struct. System is index `0`, so
+	// the other pallets start at `1`. Indices are `u8`, so a runtime with more than 255 pallets
+	// besides system is a compile error.
+	let pallet_indices = match (1..=pallets.len()).map(u8::try_from).collect::<Result<Vec<_>, _>>() {
+		Ok(indices) => indices,
+		Err(_) => {
+			let msg = "Invalid runtime, too many pallets";
+			return syn::Error::new(runtime_struct.span(), msg).to_compile_error()
+		},
+	};
+
+	// The `Hooks` of a pallet, as called by the runtime.
+	let hooks = quote! { crate::support::Hooks<Self, <Self as system::Config>::BlockNumber> };
 
 	// This quote block implements functions on the `Runtime` struct.
 	let runtime_impl = quote! {
 		impl #runtime_struct {
-			// Create a new instance of the main Runtime, by creating a new instance of each pallet.
-			fn new() -> Self {
+			// Create a new instance of the main Runtime, whose state is kept in memory, from the
+			// default genesis configuration of every pallet.
+			pub fn new() -> Self {
+				Self::from_genesis(Default::default()).expect("the default genesis is valid")
+			}
+
+			// Create a new instance of the main Runtime over `storage`, by creating a new instance
+			// of each pallet, all of them sharing `storage`.
+			pub fn with_storage(storage: crate::support::Storage) -> Self {
 				Self {
 					// Since system is not included in the list of pallets, we manually add it here.
-					system: <system::Pallet::<Self>>::new(),
+					system: <system::Pallet::<Self>>::new(&storage),
 					#(
-						#pallet_names: <#pallet_types>::new()
+						#pallet_names: <#pallet_types>::new(&storage)
 					),*
 				}
 			}
 
-			// Execute a block of extrinsics. Increments the block number.
-			fn execute_block(&mut self, block: types::Block) -> crate::support::DispatchResult {
+			// Create an instance of the runtime from its genesis configuration, with its state kept
+			// in memory.
+			pub fn from_genesis(
+				config: RuntimeGenesisConfig,
+			) -> Result<Self, crate::support::DispatchError> {
+				let mut runtime = Self::with_storage(crate::support::Storage::in_memory());
+				runtime.build_genesis(config)?;
+				Ok(runtime)
+			}
+
+			// Build the genesis state of a fresh runtime, by building the initial state of every
+			// pallet in the order of the `Runtime` struct, starting with system, and record the
+			// hash of the genesis block. Fails if the configuration of a pallet is invalid, with an
+			// error tagged with the index of the pallet.
+			pub fn build_genesis(
+				&mut self,
+				config: RuntimeGenesisConfig,
+			) -> Result<(), crate::support::DispatchError> {
+				use crate::support::BuildGenesisConfig;
+				config.system.build(self).map_err(|e| e.in_pallet(0))?;
+				#(
+					config
+						.#pallet_names
+						.build(self)
+						.map_err(|e| e.in_pallet(#pallet_indices))?;
+				)*
+				// Genesis is not part of any block, so the events emitted while building it are
+				// dropped.
+				#( self.#pallet_names.take_events(); )*
+				// The genesis block has no extrinsics and commits to the genesis state. Its hash is
+				// recorded as the hash of block `0`, which extrinsics sign so they are only valid on
+				// this chain.
+				let header = types::Header {
+					parent_hash: Default::default(),
+					block_number: self.system.block_number(),
+					state_root: self.state_root(),
+					extrinsics_root: Self::extrinsics_root(&[]),
+				};
+				self.system.finalize_block(
+					<<Self as system::Config>::Hashing as crate::support::Hashing>::hash_of(&header),
+				);
+				Ok(())
+			}
+
+			// Execute a block of extrinsics, checking it against its header. Increments the block
+			// number.
+			//
+			// Before executing, the header must reference the last executed block as its parent,
+			// and commit to the extrinsics of the block. After executing, the state root of the
+			// header must match the state of the runtime. The whole block is executed inside of a
+			// transaction, so an invalid block leaves the state untouched.
+			//
+			// A failed extrinsic does not invalidate the block. Instead, the outcome of every
+			// extrinsic is reported in the returned `BlockReceipt`.
+			pub fn execute_block(
+				&mut self,
+				block: types::Block,
+			) -> Result<types::BlockReceipt, crate::support::DispatchError> {
+				use crate::support::Hashing;
+				let types::Block { header, extrinsics } = block;
+				crate::support::with_transaction(self, |runtime| {
+					if header.parent_hash != runtime.system.parent_hash() {
+						return Err(crate::support::DispatchError::Other(
+							"parent hash does not match the last block",
+						))
+					}
+					if header.extrinsics_root != Self::extrinsics_root(&extrinsics) {
+						return Err(crate::support::DispatchError::Other(
+							"extrinsics root does not match the extrinsics of the block",
+						))
+					}
+					let receipt = runtime.apply_extrinsics(extrinsics)?;
+					if header.block_number != receipt.block_number {
+						return Err(crate::support::DispatchError::Other(
+							"block number does not match what is expected",
+						))
+					}
+					if header.state_root != runtime.state_root() {
+						return Err(crate::support::DispatchError::Other(
+							"state root does not match the state after execution",
+						))
+					}
+					runtime.system.finalize_block(
+						<<Self as system::Config>::Hashing>::hash_of(&header),
+					);
+					Ok(receipt)
+				})
+			}
+
+			// Build the next block out of a list of extrinsics, on top of the last executed block.
+			//
+			// The extrinsics are executed to compute the state root of the header, but the state
+			// is then rolled back, so the block still has to be passed to `execute_block`. Fails
+			// if one of the extrinsics has a bad signature or nonce.
+			pub fn build_block(
+				&mut self,
+				extrinsics: Vec<types::Extrinsic>,
+			) -> Result<types::Block, crate::support::DispatchError> {
+				let parent_hash = self.system.parent_hash();
+				let extrinsics_root = Self::extrinsics_root(&extrinsics);
+				crate::support::Transactional::start_transaction(self);
+				let block_number = self.apply_extrinsics(extrinsics.clone()).map(|r| r.block_number);
+				let state_root = self.state_root();
+				crate::support::Transactional::rollback_transaction(self);
+				Ok(types::Block {
+					header: types::Header {
+						parent_hash,
+						block_number: block_number?,
+						state_root,
+						extrinsics_root,
+					},
+					extrinsics,
+				})
+			}
+
+			// Apply the extrinsics of a new block on top of the current state, without any check on
+			// the block itself. Increments the block number.
+			//
+			// Every extrinsic must be signed by its caller, and carry the current nonce of the
+			// caller. Its weight must fit in the block, and the caller must be able to pay for
+			// it. Otherwise the whole block is invalid, and a `system` error is returned.
+			//
+			// The fee is withdrawn through `support::ChargeTransaction` before the call is
+			// dispatched, and the part of it paying for unused weight is refunded afterwards.
+			//
+			// Each extrinsic is dispatched inside of its own transaction, so a call which fails
+			// after writing to storage leaves no trace other than the incremented nonce and the
+			// fee.
+			//
+			// The events emitted by the pallets are collected into the event log of `system`,
+			// which is reset at the start of every block.
+			//
+			// Before the extrinsics, every pallet runs its `on_initialize` hook, and after them its
+			// `on_idle` and then its `on_finalize` hooks, in the order of the `Runtime` struct. The
+			// weight used by the hooks counts towards the weight of the block.
+			fn apply_extrinsics(
+				&mut self,
+				extrinsics: Vec<types::Extrinsic>,
+			) -> Result<types::BlockReceipt, crate::support::DispatchError> {
 				self.system.inc_block_number();
-				if block.header.block_number != self.system.block_number() {
-					return Err(&"block number does not match what is expected")
-				}
-				for (i, support::Extrinsic { caller, call }) in block.extrinsics.into_iter().enumerate() {
-					self.system.inc_nonce(&caller);
-					let _res = self.dispatch(caller, call).map_err(|e| {
-						eprintln!(
-							"Extrinsic Error\n\tBlock Number: {}\n\tExtrinsic Number: {}\n\tError: {}",
-							block.header.block_number, i, e
+				self.system.reset_events();
+				self.system.reset_block_weight();
+				let block_number = self.system.block_number();
+				let weight = <system::Pallet<Self> as #hooks>::on_initialize(self, block_number);
+				self.system.register_weight(weight);
+				#(
+					let weight = <#pallet_types as #hooks>::on_initialize(self, block_number);
+					self.system.register_weight(weight);
+				)*
+				self.collect_events(system::Phase::Initialization);
+				let genesis_hash = self.system.genesis_hash();
+				let mut receipts = Vec::with_capacity(extrinsics.len());
+				for (i, extrinsic) in extrinsics.into_iter().enumerate() {
+					let support::Extrinsic { caller, nonce, signature, call } = extrinsic;
+					let payload = crate::support::signing_payload(&call, &nonce, &genesis_hash);
+					if !crate::support::Verify::verify(&signature, &payload, &caller) {
+						return Err(crate::support::DispatchError::from(system::Error::BadSignature)
+							.in_pallet(0))
+					}
+					self.system.check_and_inc_nonce(&caller, nonce).map_err(|e| e.in_pallet(0))?;
+					let weight = crate::support::GetWeight::weight(&call);
+					if weight > self.system.remaining_weight() {
+						return Err(crate::support::DispatchError::from(
+							system::Error::ExhaustsResources,
 						)
+						.in_pallet(0))
+					}
+					let fee = crate::support::ChargeTransaction::withdraw_fee(self, &caller, weight)
+						.map_err(|_| {
+							crate::support::DispatchError::from(system::Error::Payment).in_pallet(0)
+						})?;
+					let pallet = call.pallet_name();
+					let call_name = call.call_name();
+					let result = crate::support::with_transaction(self, |runtime| {
+						let origin = crate::support::Origin::Signed(caller.clone()).into();
+						crate::support::Dispatch::dispatch(runtime, origin, call)
+					});
+					// A failed call pays for all of its declared weight.
+					let actual_weight = match &result {
+						Ok(post_info) => post_info.calc_actual_weight(weight),
+						Err(_) => weight,
+					};
+					self.system.register_weight(actual_weight);
+					crate::support::ChargeTransaction::correct_and_deposit_fee(
+						self,
+						&caller,
+						fee,
+						actual_weight,
+					);
+					self.collect_events(system::Phase::ApplyExtrinsic(i as u32));
+					receipts.push(crate::support::ExtrinsicReceipt {
+						caller,
+						pallet,
+						call: call_name,
+						nonce,
+						weight: actual_weight,
+						result: result.map(|_| ()),
 					});
 				}
-				Ok(())
+				let remaining_weight = self.system.remaining_weight();
+				let weight =
+					<system::Pallet<Self> as #hooks>::on_idle(self, block_number, remaining_weight);
+				self.system.register_weight(weight);
+				#(
+					let remaining_weight = self.system.remaining_weight();
+					let weight =
+						<#pallet_types as #hooks>::on_idle(self, block_number, remaining_weight);
+					self.system.register_weight(weight);
+				)*
+				<system::Pallet<Self> as #hooks>::on_finalize(self, block_number);
+				#( <#pallet_types as #hooks>::on_finalize(self, block_number); )*
+				self.collect_events(system::Phase::Finalization);
+				Ok(crate::support::BlockReceipt {
+					block_number: self.system.block_number(),
+					extrinsics: receipts,
+				})
+			}
+
+			// The merkle root of a list of extrinsics.
+			fn extrinsics_root(extrinsics: &[types::Extrinsic]) -> system::HashOf<Self> {
+				use crate::support::Hashing;
+				<<Self as system::Config>::Hashing>::merkle_root(
+					extrinsics.iter().map(<<Self as system::Config>::Hashing>::hash_of),
+				)
+			}
+
+			// The merkle root of the state of the runtime, which has one leaf for the state of each
+			// pallet, including system.
+			fn state_root(&self) -> system::HashOf<Self> {
+				use crate::support::Hashing;
+				<<Self as system::Config>::Hashing>::merkle_root([
+					<<Self as system::Config>::Hashing>::hash_of(&self.system),
+					#( <<Self as system::Config>::Hashing>::hash_of(&self.#pallet_names) ),*
+				])
+			}
+
+			// Move the events emitted by each pallet into the event log of `system`. Events of a
+			// failed extrinsic have already been dropped when its transaction was rolled back.
+			fn collect_events(&mut self, phase: system::Phase) {
+				#(
+					for event in self.#pallet_names.take_events() {
+						self.system.deposit_event(phase, RuntimeEvent::#pallet_names(event));
+					}
+				)*
+			}
+		}
+	};
+
+	// This quote block implements the `HasPallet` trait on the `Runtime` for every pallet, including
+	// system, so that pallets can access each other through the runtime.
+	let has_pallet_impl = quote! {
+		impl crate::support::HasPallet<system::Pallet<Self>> for #runtime_struct {
+			const INDEX: u8 = 0;
+
+			fn pallet(&self) -> &system::Pallet<Self> {
+				&self.system
+			}
+
+			fn pallet_mut(&mut self) -> &mut system::Pallet<Self> {
+				&mut self.system
+			}
+		}
+
+		#(
+			impl crate::support::HasPallet<#pallet_types> for #runtime_struct {
+				const INDEX: u8 = #pallet_indices;
+
+				fn pallet(&self) -> &#pallet_types {
+					&self.#pallet_names
+				}
+
+				fn pallet_mut(&mut self) -> &mut #pallet_types {
+					&mut self.#pallet_names
+				}
+			}
+		)*
+	};
+
+	// This quote block implements the `Transactional` trait on the `Runtime`, by opening, committing
+	// and rolling back the transaction on the storage shared by all the pallets, and then on every
+	// pallet, including system. Every clone of the storage is a handle on the same state.
+	let transactional_impl = quote! {
+		impl crate::support::Transactional for #runtime_struct {
+			fn start_transaction(&mut self) {
+				let mut storage = self.system.storage().clone();
+				crate::support::Transactional::start_transaction(&mut storage);
+				self.system.start_transaction();
+				#( self.#pallet_names.start_transaction(); )*
+			}
+
+			fn commit_transaction(&mut self) {
+				let mut storage = self.system.storage().clone();
+				crate::support::Transactional::commit_transaction(&mut storage);
+				self.system.commit_transaction();
+				#( self.#pallet_names.commit_transaction(); )*
+			}
+
+			fn rollback_transaction(&mut self) {
+				let mut storage = self.system.storage().clone();
+				crate::support::Transactional::rollback_transaction(&mut storage);
+				self.system.rollback_transaction();
+				#( self.#pallet_names.rollback_transaction(); )*
 			}
 		}
 	};
 
+	// This quote block implements the `RuntimeGenesisConfig` struct.
+	let genesis_config_impl = quote! {
+		// The genesis configuration of every pallet, including system, from which
+		// `from_genesis` builds the initial state of the runtime.
+		pub struct RuntimeGenesisConfig {
+			pub system: system::GenesisConfig<#runtime_struct>,
+			#( pub #pallet_names: #pallet_names::GenesisConfig<#runtime_struct> ),*
+		}
+
+		impl Default for RuntimeGenesisConfig {
+			fn default() -> Self {
+				Self {
+					system: Default::default(),
+					#( #pallet_names: Default::default() ),*
+				}
+			}
+		}
+	};
+
+	// This quote block implements the `RuntimeEvent` enum.
+	let event_impl = quote! {
+		// These are all the events which can be emitted by the runtime.
+		// Note that it is just an accumulation of the events declared by each pallet.
+		#[allow(non_camel_case_types)]
+		#[derive(Debug)]
+		pub enum RuntimeEvent {
+			#(
+				#pallet_names(
+					<#pallet_names::Pallet<#runtime_struct> as crate::support::PalletEvent>::Event
+				)
+			),*
+		}
+	};
+
 	// This quote block implements the `RuntimeCall` enum and implements the `Dispatch` trait.
 	let dispatch_impl = quote! {
 		// These are all the calls which are exposed to the world.
@@ -51,33 +393,87 @@ pub fn expand_runtime(def: RuntimeDef) -> proc_macro2::TokenStream {
 		//
 		// The parsed function names will be `snake_case`, and that will show up in the enum.
 		#[allow(non_camel_case_types)]
+		#[derive(Clone, Debug, PartialEq)]
 		pub enum RuntimeCall {
 			#( #pallet_names(#pallet_names::Call<#runtime_struct>) ),*
 		}
 
+		// The encoding of a runtime call is the index of the pallet, followed by the encoding of
+		// the call inside of that pallet.
+		impl crate::support::Encode for RuntimeCall {
+			fn encode_to(&self, dest: &mut Vec<u8>) {
+				match self {
+					#(
+						RuntimeCall::#pallet_names(call) => {
+							dest.push(#pallet_indices);
+							crate::support::Encode::encode_to(call, dest);
+						},
+					)*
+				}
+			}
+		}
+
+		impl crate::support::Decode for RuntimeCall {
+			fn decode(input: &mut &[u8]) -> Result<Self, crate::support::CodecError> {
+				match <u8 as crate::support::Decode>::decode(input)? {
+					#(
+						#pallet_indices => Ok(RuntimeCall::#pallet_names(
+							crate::support::Decode::decode(input)?,
+						)),
+					)*
+					_ => Err(crate::support::CodecError("invalid pallet index")),
+				}
+			}
+		}
+
+		impl RuntimeCall {
+			// The name of the pallet this call is routed to.
+			pub fn pallet_name(&self) -> &'static str {
+				match self {
+					#( RuntimeCall::#pallet_names(_) => #pallet_name_strs, )*
+				}
+			}
+
+			// The name of the function this call dispatches to, inside of its pallet.
+			pub fn call_name(&self) -> &'static str {
+				match self {
+					#( RuntimeCall::#pallet_names(call) => call.call_name(), )*
+				}
+			}
+		}
+
+		impl crate::support::GetWeight for RuntimeCall {
+			// The weight of this call, as declared by its pallet.
+			fn weight(&self) -> crate::support::Weight {
+				match self {
+					#( RuntimeCall::#pallet_names(call) => call.weight(), )*
+				}
+			}
+		}
+
 		impl crate::support::Dispatch for #runtime_struct {
-			type Caller = <Runtime as system::Config>::AccountId;
+			type Origin = <#runtime_struct as system::Config>::RuntimeOrigin;
 			type Call = RuntimeCall;
-			// Dispatch a call on behalf of a caller. Increments the caller's nonce.
+			// Dispatch a call from an origin.
 			//
 			// Dispatch allows us to identify which underlying pallet call we want to execute.
-			// Note that we extract the `caller` from the extrinsic, and use that information
-			// to determine who we are executing the call on behalf of.
+			// Note that an extrinsic is dispatched from the signed origin of its `caller`, which
+			// determines who we are executing the call on behalf of.
 			fn dispatch(
 				&mut self,
-				caller: Self::Caller,
+				origin: Self::Origin,
 				runtime_call: Self::Call,
-			) -> crate::support::DispatchResult {
+			) -> crate::support::DispatchResultWithPostInfo {
 				// This match statement will allow us to correctly route `RuntimeCall`s
-				// to the appropriate pallet level call.
+				// to the appropriate pallet level call. Errors coming from the pallet are tagged
+				// with the index of the pallet.
 				match runtime_call {
 					#(
 						RuntimeCall::#pallet_names(call) => {
-							self.#pallet_names.dispatch(caller, call)?;
+							call.dispatch(self, origin).map_err(|e| e.in_pallet(#pallet_indices))
 						}
 					),*
 				}
-				Ok(())
 			}
 		}
 	};
@@ -85,6 +481,10 @@ pub fn expand_runtime(def: RuntimeDef) -> proc_macro2::TokenStream {
 	// We combine and return all the generated code.
 	quote! {
 		#dispatch_impl
+		#event_impl
+		#genesis_config_impl
+		#transactional_impl
+		#has_pallet_impl
 		#runtime_impl
 	}
 	.into()
@@ -1,92 +1,1276 @@
-use std::collections::BTreeMap;
-use num::traits::{CheckedAdd, CheckedSub, Zero};
+use num::traits::{CheckedAdd, CheckedMul, CheckedSub, Saturating, Unsigned, Zero};
+use core::fmt::Debug;
+use std::collections::BTreeSet;
+use crate::support::{
+    Currency, Decode, DispatchError, DispatchResult, Encode, ExistenceRequirement, HasPallet,
+    Imbalance, LockIdentifier, LockableCurrency, ReservableCurrency, Storage, StorageDoubleMap,
+    StorageMap, StorageValue, Transactional, TransactionalVec, Weight,
+};
 
 pub trait Config : crate::system::Config {
-    type Balance: Zero + CheckedSub + CheckedAdd + Copy;
+    type Balance: Unsigned
+        + CheckedAdd
+        + CheckedSub
+        + CheckedMul
+        + Saturating
+        + Copy
+        + Ord
+        + Debug
+        + Encode
+        + Decode;
+
+    /// The minimum balance an account needs to exist, counting both its free and reserved funds.
+    /// An account whose balance falls below it is reaped, and whatever is left of it is lost.
+    const EXISTENTIAL_DEPOSIT: Self::Balance;
+
+    /// Convert the weight of a call into the fee its caller has to pay.
+    fn weight_to_fee(weight: Weight) -> Self::Balance;
+
+    /// The account which receives the transaction fees. When it is `None`, fees are burned.
+    fn treasury_account() -> Option<Self::AccountId>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[macros::error]
+pub enum Error {
+    /// The caller does not have enough funds for the transfer.
+    InsufficientBalance,
+    /// The balance of the receiver would overflow.
+    Overflow,
+    /// The transfer would create an account with less than the existential deposit.
+    ExistentialDeposit,
+    /// The transfer would reap the account of the caller, which asked to be kept alive.
+    KeepAlive,
+    /// The funds of the account are locked, so it cannot withdraw this much.
+    LiquidityRestrictions,
+}
+
+/// The events emitted by this pallet.
+#[derive(Debug)]
+pub enum Event<T: Config> {
+    /// `amount` was transferred from `from` to `to`.
+    Transfer { from: T::AccountId, to: T::AccountId, amount: T::Balance },
+    /// `who` paid `actual_fee` for an extrinsic, after the refund of its unused weight.
+    TransactionFeePaid { who: T::AccountId, actual_fee: T::Balance },
+    /// The account `who` fell below the existential deposit and was removed, losing `dust`.
+    Reaped { who: T::AccountId, dust: T::Balance },
+    /// `amount` was created in the account of `who`.
+    Minted { who: T::AccountId, amount: T::Balance },
+    /// `amount` was destroyed from the account of `who`.
+    Burned { who: T::AccountId, amount: T::Balance },
+    /// The free balance of `who` was set to `free` by root.
+    BalanceSet { who: T::AccountId, free: T::Balance },
+    /// `amount` was moved from the free balance of `who` to its reserved balance.
+    Reserved { who: T::AccountId, amount: T::Balance },
+    /// `amount` was moved from the reserved balance of `who` back to its free balance.
+    Unreserved { who: T::AccountId, amount: T::Balance },
+    /// `amount` was destroyed from the reserved balance of `who`.
+    ReserveSlashed { who: T::AccountId, amount: T::Balance },
+    /// `amount` was added to the free balance of `who` by another pallet.
+    Deposited { who: T::AccountId, amount: T::Balance },
+    /// `amount` was taken out of the free balance of `who` by another pallet.
+    Withdrawn { who: T::AccountId, amount: T::Balance },
+}
+
+// Implemented by hand, since a derive would require `T` itself to implement `PartialEq`.
+impl<T: Config> PartialEq for Event<T> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (
+                Self::Transfer { from: a0, to: a1, amount: a2 },
+                Self::Transfer { from: b0, to: b1, amount: b2 },
+            ) => (a0, a1, a2) == (b0, b1, b2),
+            (
+                Self::TransactionFeePaid { who: a0, actual_fee: a1 },
+                Self::TransactionFeePaid { who: b0, actual_fee: b1 },
+            ) => (a0, a1) == (b0, b1),
+            (
+                Self::Reaped { who: a0, dust: a1 },
+                Self::Reaped { who: b0, dust: b1 },
+            ) => (a0, a1) == (b0, b1),
+            (
+                Self::Minted { who: a0, amount: a1 },
+                Self::Minted { who: b0, amount: b1 },
+            ) => (a0, a1) == (b0, b1),
+            (
+                Self::Burned { who: a0, amount: a1 },
+                Self::Burned { who: b0, amount: b1 },
+            ) => (a0, a1) == (b0, b1),
+            (
+                Self::BalanceSet { who: a0, free: a1 },
+                Self::BalanceSet { who: b0, free: b1 },
+            ) => (a0, a1) == (b0, b1),
+            (
+                Self::Reserved { who: a0, amount: a1 },
+                Self::Reserved { who: b0, amount: b1 },
+            ) => (a0, a1) == (b0, b1),
+            (
+                Self::Unreserved { who: a0, amount: a1 },
+                Self::Unreserved { who: b0, amount: b1 },
+            ) => (a0, a1) == (b0, b1),
+            (
+                Self::ReserveSlashed { who: a0, amount: a1 },
+                Self::ReserveSlashed { who: b0, amount: b1 },
+            ) => (a0, a1) == (b0, b1),
+            (
+                Self::Deposited { who: a0, amount: a1 },
+                Self::Deposited { who: b0, amount: b1 },
+            ) => (a0, a1) == (b0, b1),
+            (
+                Self::Withdrawn { who: a0, amount: a1 },
+                Self::Withdrawn { who: b0, amount: b1 },
+            ) => (a0, a1) == (b0, b1),
+            _ => false,
+        }
+    }
+}
+
+/// Funds deposited in an account through `Currency`, which are not in the total issuance yet.
+#[must_use]
+#[derive(Debug)]
+pub struct PositiveImbalance<T: Config>(T::Balance);
+
+/// Funds withdrawn from an account through `Currency`, which are still in the total issuance.
+#[must_use]
+#[derive(Debug)]
+pub struct NegativeImbalance<T: Config>(T::Balance);
+
+impl<T: Config> PartialEq for PositiveImbalance<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<T: Config> PartialEq for NegativeImbalance<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<T: Config> Imbalance<T::Balance> for PositiveImbalance<T> {
+    fn zero() -> Self {
+        Self(T::Balance::zero())
+    }
+
+    fn peek(&self) -> T::Balance {
+        self.0
+    }
+
+    /// Panics if the amount overflows, which the total issuance would too.
+    fn merge(self, other: Self) -> Self {
+        Self(self.0.checked_add(&other.0).expect("imbalance overflows"))
+    }
+}
+
+impl<T: Config> Imbalance<T::Balance> for NegativeImbalance<T> {
+    fn zero() -> Self {
+        Self(T::Balance::zero())
+    }
+
+    fn peek(&self) -> T::Balance {
+        self.0
+    }
+
+    /// Saturates at the maximum balance. Withdrawn funds come out of the total issuance, so it is
+    /// only reached by imbalances which were never settled.
+    fn merge(self, other: Self) -> Self {
+        Self(self.0.saturating_add(other.0))
+    }
 }
 
 #[derive(Debug)]
 pub struct Pallet<T:Config> {
-    balances : BTreeMap<T::AccountId, T::Balance>,
+    balances : StorageMap<T::AccountId, T::Balance>,
+    /// The reserved balance of each account, which cannot be spent until it is unreserved.
+    reserved: StorageMap<T::AccountId, T::Balance>,
+    /// The named locks on the free balance of each account.
+    locks: StorageDoubleMap<T::AccountId, LockIdentifier, T::Balance>,
+    /// The sum of the balances of all the accounts.
+    total_issuance: StorageValue<T::Balance>,
+    /// The events emitted by this pallet which have not been collected by the runtime yet.
+    events: TransactionalVec<Event<T>>,
 }
 
 impl<T:Config > Pallet<T> {
     /// Create a new instance of balance module
-    pub fn new() -> Self {
-        Self { balances: BTreeMap::new() }
+    pub fn new(storage: &Storage) -> Self {
+        Self {
+            balances: StorageMap::new(storage, "Balances", "Account"),
+            reserved: StorageMap::new(storage, "Balances", "Reserved"),
+            locks: StorageDoubleMap::new(storage, "Balances", "Locks"),
+            total_issuance: StorageValue::new(storage, "Balances", "TotalIssuance"),
+            events: TransactionalVec::new(),
+        }
     }
 
+    fn deposit_event(&mut self, event: Event<T>) {
+        self.events.push(event);
+    }
+
+    pub fn take_events(&mut self) -> Vec<Event<T>> {
+        self.events.take()
+    }
+
+    /// Set the balance of `who`, without checking it against the existential deposit. A balance
+    /// of zero removes the account. The total issuance follows the change.
+    ///
+    /// Panics if the total issuance overflows.
     pub fn set_balance(&mut self, who: &T::AccountId, amount: T::Balance) {
-        self.balances.insert(who.clone(), amount);
+        let total_issuance = self
+            .total_issuance()
+            .checked_sub(&self.balance(who))
+            .and_then(|total_issuance| total_issuance.checked_add(&amount))
+            .expect("total issuance overflows");
+        self.total_issuance.put(total_issuance);
+        if amount.is_zero() {
+            self.balances.remove(who);
+        } else {
+            self.balances.insert(who.clone(), amount);
+        }
+    }
+
+    /// The sum of the free and reserved balances of all the accounts.
+    pub fn total_issuance(&self) -> T::Balance {
+        self.total_issuance.get().unwrap_or_else(T::Balance::zero)
+    }
+
+    /// Remove `amount` from the total issuance, once it has been taken out of an account without
+    /// being added to another one.
+    fn burn_issuance(&mut self, amount: T::Balance) {
+        let total_issuance =
+            self.total_issuance().checked_sub(&amount).unwrap_or_else(T::Balance::zero);
+        self.total_issuance.put(total_issuance);
+    }
+
+    /// Whether the total issuance is the sum of the free and reserved balances of all the
+    /// accounts, which must hold whenever no fee is being paid.
+    #[cfg(test)]
+    pub fn check_total_issuance(&self) -> bool {
+        let sum = self
+            .balances
+            .iter()
+            .chain(self.reserved.iter())
+            .try_fold(T::Balance::zero(), |sum, (_, balance)| sum.checked_add(&balance));
+        sum == Some(self.total_issuance())
     }
 
-    pub fn balance(&mut self, who: &T::AccountId) -> T::Balance {
-        *self.balances.get(who).unwrap_or(&T::Balance::zero())
+    pub fn balance(&self, who: &T::AccountId) -> T::Balance {
+        self.balances.get(who).unwrap_or_else(T::Balance::zero)
+    }
+
+    /// The amount of the funds of `who` which are reserved.
+    pub fn reserved_balance(&self, who: &T::AccountId) -> T::Balance {
+        self.reserved.get(who).unwrap_or_else(T::Balance::zero)
+    }
+
+    /// The amount of the free balance of `who` which cannot be withdrawn: the largest of its
+    /// locks.
+    pub fn locked_balance(&self, who: &T::AccountId) -> T::Balance {
+        let locks = self.locks.iter_prefix(who).map(|(_, amount)| amount);
+        locks.fold(T::Balance::zero(), |max, amount| if amount > max { amount } else { max })
+    }
+
+    /// Whether an account whose free balance is `free` would fall below the existential deposit,
+    /// once its reserved balance is counted.
+    fn below_existential_deposit(&self, who: &T::AccountId, free: T::Balance) -> bool {
+        match free.checked_add(&self.reserved_balance(who)) {
+            Some(total) => total < T::EXISTENTIAL_DEPOSIT,
+            None => false,
+        }
+    }
+
+    /// Check that the free balance of `who` can fall to `new_balance` without breaking its locks.
+    fn ensure_can_withdraw(&self, who: &T::AccountId, new_balance: T::Balance) -> DispatchResult {
+        if new_balance < self.locked_balance(who) {
+            return Err(Error::LiquidityRestrictions.into());
+        }
+        Ok(())
+    }
+
+    /// Write the free balance of `who`. An account with only reserved funds has no free balance
+    /// entry.
+    fn put_balance(&mut self, who: &T::AccountId, amount: T::Balance) {
+        if amount.is_zero() {
+            self.balances.remove(who);
+        } else {
+            self.balances.insert(who.clone(), amount);
+        }
+    }
+
+    /// Add `amount` to the balance of `who`. The amount was withdrawn from an account before, so
+    /// this only overflows if the balances were set beyond the limits of `T::Balance`. The amount
+    /// is burned if it overflows, or if it is not enough to create the account.
+    fn credit(&mut self, who: &T::AccountId, amount: T::Balance) {
+        let balance = self.balance(who);
+        match balance.checked_add(&amount) {
+            Some(new_balance) if !self.below_existential_deposit(who, new_balance) => {
+                self.put_balance(who, new_balance);
+            },
+            _ => self.burn_issuance(amount),
+        }
+    }
+
+    /// Withdraw the fee for a call of the given weight from `who`, before it is dispatched.
+    /// Returns the fee which was withdrawn. Paying a fee never reaps an account, and cannot use
+    /// locked funds.
+    pub fn withdraw_fee(
+        &mut self,
+        who: &T::AccountId,
+        weight: Weight,
+    ) -> Result<T::Balance, DispatchError> {
+        let fee = T::weight_to_fee(weight);
+        let new_balance = self.balance(who).checked_sub(&fee).ok_or(Error::InsufficientBalance)?;
+        self.ensure_can_withdraw(who, new_balance)?;
+        if self.below_existential_deposit(who, new_balance) {
+            return Err(Error::KeepAlive.into());
+        }
+        self.put_balance(who, new_balance);
+        Ok(fee)
+    }
+
+    /// Once a call has been dispatched, refund to `who` the part of the withdrawn `fee` which pays
+    /// for more than `actual_weight`. The rest goes to the treasury, or is burned.
+    ///
+    /// Until then, the fee is neither in an account nor out of the total issuance.
+    pub fn correct_and_deposit_fee(
+        &mut self,
+        who: &T::AccountId,
+        fee: T::Balance,
+        actual_weight: Weight,
+    ) {
+        let actual_fee = T::weight_to_fee(actual_weight);
+        let (actual_fee, refund) = match fee.checked_sub(&actual_fee) {
+            Some(refund) => (actual_fee, refund),
+            // The caller never pays more than what was withdrawn.
+            None => (fee, T::Balance::zero()),
+        };
+        self.credit(who, refund);
+        match T::treasury_account() {
+            Some(treasury) => self.credit(&treasury, actual_fee),
+            None => self.burn_issuance(actual_fee),
+        }
+        self.deposit_event(Event::TransactionFeePaid { who: who.clone(), actual_fee });
+    }
+}
+
+impl<T: Config> Pallet<T>
+where
+    T: HasPallet<Pallet<T>> + HasPallet<crate::system::Pallet<T>>,
+{
+    /// Transfer `amount` from `from` to `to`, out of the funds of `from` which are neither
+    /// reserved nor locked. The account of `from` is reaped if it falls below the existential
+    /// deposit, unless it must be kept alive, in which case the transfer fails.
+    ///
+    /// Reaping an account also removes it from `system`.
+    fn do_transfer(
+        runtime: &mut T,
+        from: T::AccountId,
+        to: T::AccountId,
+        amount: T::Balance,
+        keep_alive: bool,
+    ) -> DispatchResult {
+        let balances: &mut Self = runtime.pallet_mut();
+        let from_balance = balances.balance(&from);
+        let new_from_balance =
+            from_balance.checked_sub(&amount).ok_or(Error::InsufficientBalance)?;
+        balances.ensure_can_withdraw(&from, new_from_balance)?;
+        // A transfer to oneself only needs the funds to be there.
+        if from == to {
+            return Ok(());
+        }
+        let new_to_balance = balances.balance(&to).checked_add(&amount).ok_or(Error::Overflow)?;
+        if balances.below_existential_deposit(&to, new_to_balance) {
+            return Err(Error::ExistentialDeposit.into());
+        }
+        let reaped = balances.below_existential_deposit(&from, new_from_balance);
+        if reaped && keep_alive {
+            return Err(Error::KeepAlive.into());
+        }
+
+        balances.balances.insert(to.clone(), new_to_balance);
+        balances.deposit_event(Event::Transfer { from: from.clone(), to, amount });
+        if reaped {
+            Self::reap_account(runtime, &from, new_from_balance);
+        } else {
+            balances.put_balance(&from, new_from_balance);
+        }
+        Ok(())
+    }
+
+    /// Remove the account of `who`, whose free balance fell to `free`, leaving it below the
+    /// existential deposit. What is left of its free and reserved funds is burned as dust, its
+    /// locks are dropped, and the account is also removed from `system`.
+    fn reap_account(runtime: &mut T, who: &T::AccountId, free: T::Balance) {
+        let balances: &mut Self = runtime.pallet_mut();
+        // Both balances are part of the total issuance, so their sum cannot overflow.
+        let dust = free.checked_add(&balances.reserved_balance(who)).unwrap_or(free);
+        balances.balances.remove(who);
+        balances.reserved.remove(who);
+        balances.locks.remove_prefix(who);
+        balances.burn_issuance(dust);
+        balances.deposit_event(Event::Reaped { who: who.clone(), dust });
+        let system: &mut crate::system::Pallet<T> = runtime.pallet_mut();
+        system.kill_account(who);
     }
 }
 
 #[macros::call]
-impl<T:Config > Pallet<T> {
+impl<T: Config> Pallet<T>
+where
+    T: HasPallet<Pallet<T>> + HasPallet<crate::system::Pallet<T>>,
+{
+    /// Transfer `amount` to `to`, like `transfer_allow_death`. This is the first call of the
+    /// pallet, so its encoding is the same as before the existential deposit existed.
+    #[weight(10_000)]
     pub fn transfer(
-        &mut self,
+        runtime: &mut T,
+        caller: T::AccountId,
+        to: T::AccountId,
+        amount: T::Balance,
+    ) -> DispatchResult {
+        Self::do_transfer(runtime, caller, to, amount, false)
+    }
+
+    /// Transfer `amount` to `to`. The caller is reaped if its balance falls below the existential
+    /// deposit.
+    #[weight(10_000)]
+    pub fn transfer_allow_death(
+        runtime: &mut T,
         caller: T::AccountId,
         to: T::AccountId,
         amount: T::Balance,
-    ) -> crate::support::DispatchResult {
-        let caller_balance = self.balance(&caller);
-        let to_balance = self.balance(&to);
+    ) -> DispatchResult {
+        Self::do_transfer(runtime, caller, to, amount, false)
+    }
 
-        let new_caller_balance = caller_balance.checked_sub(&amount).ok_or("Not enough funds.")?;
-        let new_to_balance = to_balance.checked_add(&amount).ok_or("Overflow")?;
+    /// Transfer `amount` to `to`, failing if the balance of the caller would fall below the
+    /// existential deposit.
+    #[weight(10_000)]
+    pub fn transfer_keep_alive(
+        runtime: &mut T,
+        caller: T::AccountId,
+        to: T::AccountId,
+        amount: T::Balance,
+    ) -> DispatchResult {
+        Self::do_transfer(runtime, caller, to, amount, true)
+    }
 
-        self.balances.insert(caller, new_caller_balance);
-        self.balances.insert(to, new_to_balance);
+    /// Create `amount` in the account of `who`, increasing the total issuance. Only root can mint.
+    #[weight(10_000)]
+    pub fn mint(
+        runtime: &mut T,
+        origin: T::RuntimeOrigin,
+        who: T::AccountId,
+        amount: T::Balance,
+    ) -> DispatchResult {
+        crate::system::ensure_root::<T>(origin)?;
+        let balances: &mut Self = runtime.pallet_mut();
+        let total_issuance =
+            balances.total_issuance().checked_add(&amount).ok_or(Error::Overflow)?;
+        let new_balance = balances.balance(&who).checked_add(&amount).ok_or(Error::Overflow)?;
+        if balances.below_existential_deposit(&who, new_balance) {
+            return Err(Error::ExistentialDeposit.into());
+        }
+        balances.balances.insert(who.clone(), new_balance);
+        balances.total_issuance.put(total_issuance);
+        balances.deposit_event(Event::Minted { who, amount });
+        Ok(())
+    }
 
+    /// Destroy `amount` from the free balance of `who`, decreasing the total issuance. Locks do not
+    /// apply. The account is reaped if it falls below the existential deposit. Only root can burn.
+    #[weight(10_000)]
+    pub fn burn(
+        runtime: &mut T,
+        origin: T::RuntimeOrigin,
+        who: T::AccountId,
+        amount: T::Balance,
+    ) -> DispatchResult {
+        crate::system::ensure_root::<T>(origin)?;
+        let balances: &mut Self = runtime.pallet_mut();
+        let new_balance =
+            balances.balance(&who).checked_sub(&amount).ok_or(Error::InsufficientBalance)?;
+        balances.burn_issuance(amount);
+        balances.deposit_event(Event::Burned { who: who.clone(), amount });
+        if balances.below_existential_deposit(&who, new_balance) {
+            Self::reap_account(runtime, &who, new_balance);
+        } else {
+            balances.put_balance(&who, new_balance);
+        }
         Ok(())
     }
+
+    /// Set the free balance of `who` to `free`, changing the total issuance accordingly. The
+    /// account is reaped if this leaves it below the existential deposit. Only root can set a
+    /// balance.
+    #[weight(10_000)]
+    pub fn force_set_balance(
+        runtime: &mut T,
+        origin: T::RuntimeOrigin,
+        who: T::AccountId,
+        free: T::Balance,
+    ) -> DispatchResult {
+        crate::system::ensure_root::<T>(origin)?;
+        let balances: &mut Self = runtime.pallet_mut();
+        balances.set_balance(&who, free);
+        balances.deposit_event(Event::BalanceSet { who: who.clone(), free });
+        if balances.below_existential_deposit(&who, free) {
+            Self::reap_account(runtime, &who, free);
+        }
+        Ok(())
+    }
+
+    /// Transfer `amount` from `from` to `to`, as if `from` had made the transfer. Only root can
+    /// force a transfer.
+    #[weight(10_000)]
+    pub fn force_transfer(
+        runtime: &mut T,
+        origin: T::RuntimeOrigin,
+        from: T::AccountId,
+        to: T::AccountId,
+        amount: T::Balance,
+    ) -> DispatchResult {
+        crate::system::ensure_root::<T>(origin)?;
+        Self::do_transfer(runtime, from, to, amount, false)
+    }
 }
 
+impl<T: Config> Encode for Pallet<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.balances.encode_to(dest);
+        self.reserved.encode_to(dest);
+        self.locks.encode_to(dest);
+        self.total_issuance.encode_to(dest);
+    }
+}
 
+/// The initial state of this pallet.
+pub struct GenesisConfig<T: Config> {
+    /// The initial free balance of each account, which must be at least the existential deposit.
+    pub balances: Vec<(T::AccountId, T::Balance)>,
+}
 
+impl<T: Config> Default for GenesisConfig<T> {
+    fn default() -> Self {
+        Self { balances: Vec::new() }
+    }
+}
 
-#[cfg(test)]
-mod tests{
-    struct TestConfig;
-    impl crate::system::Config for TestConfig {
-        type AccountId = String;
-        type BlockNumber = u32;
-        type Nonce = u32;
+impl<T: Config> crate::support::BuildGenesisConfig<T> for GenesisConfig<T>
+where
+    T: HasPallet<Pallet<T>>,
+{
+    fn build(self, runtime: &mut T) -> DispatchResult {
+        let balances: &mut Pallet<T> = runtime.pallet_mut();
+        // Check every balance before writing any, so an invalid genesis leaves no trace.
+        let mut accounts = BTreeSet::new();
+        let mut total_issuance = balances.total_issuance();
+        for (who, balance) in &self.balances {
+            if *balance < T::EXISTENTIAL_DEPOSIT {
+                return Err(Error::ExistentialDeposit.into())
+            }
+            if !accounts.insert(who) || balances.balances.contains_key(who) {
+                return Err("duplicate account in the genesis balances".into())
+            }
+            total_issuance = total_issuance.checked_add(balance).ok_or(Error::Overflow)?;
+        }
+        for (who, balance) in &self.balances {
+            balances.set_balance(who, *balance);
+        }
+        Ok(())
+    }
+}
+
+impl<T: Config> crate::support::Hooks<T, T::BlockNumber> for Pallet<T> {}
+
+impl<T: Config> crate::support::PalletEvent for Pallet<T> {
+    type Event = Event<T>;
+}
+
+impl<T: Config> Transactional for Pallet<T> {
+    fn start_transaction(&mut self) {
+        self.events.start_transaction();
+    }
+
+    fn commit_transaction(&mut self) {
+        self.events.commit_transaction();
+    }
+
+    fn rollback_transaction(&mut self) {
+        self.events.rollback_transaction();
+    }
+}
+
+/// Errors of the currency are returned to the pallet using it, so they are tagged with the index
+/// of `balances` before the runtime tags them with the index of that pallet.
+impl<T: Config> Currency<T::AccountId, T> for Pallet<T>
+where
+    T: HasPallet<Pallet<T>> + HasPallet<crate::system::Pallet<T>>,
+{
+    type Balance = T::Balance;
+    type PositiveImbalance = PositiveImbalance<T>;
+    type NegativeImbalance = NegativeImbalance<T>;
+
+    fn free_balance(runtime: &T, who: &T::AccountId) -> T::Balance {
+        let balances: &Self = runtime.pallet();
+        balances.balance(who)
     }
-    impl super::Config for TestConfig {
-        type Balance = u128;
+
+    fn total_issuance(runtime: &T) -> T::Balance {
+        let balances: &Self = runtime.pallet();
+        balances.total_issuance()
     }
 
+    fn transfer(
+        runtime: &mut T,
+        from: &T::AccountId,
+        to: &T::AccountId,
+        amount: T::Balance,
+        existence: ExistenceRequirement,
+    ) -> DispatchResult {
+        let keep_alive = existence == ExistenceRequirement::KeepAlive;
+        Self::do_transfer(runtime, from.clone(), to.clone(), amount, keep_alive)
+            .map_err(|error| error.in_pallet(<T as HasPallet<Self>>::INDEX))
+    }
+
+    fn withdraw(
+        runtime: &mut T,
+        who: &T::AccountId,
+        amount: T::Balance,
+        existence: ExistenceRequirement,
+    ) -> Result<NegativeImbalance<T>, DispatchError> {
+        let balances: &mut Self = runtime.pallet_mut();
+        let withdraw = |balances: &mut Self| -> Result<bool, DispatchError> {
+            let new_balance =
+                balances.balance(who).checked_sub(&amount).ok_or(Error::InsufficientBalance)?;
+            balances.ensure_can_withdraw(who, new_balance)?;
+            let reaped = balances.below_existential_deposit(who, new_balance);
+            if reaped && existence == ExistenceRequirement::KeepAlive {
+                return Err(Error::KeepAlive.into());
+            }
+            balances.put_balance(who, new_balance);
+            balances.deposit_event(Event::Withdrawn { who: who.clone(), amount });
+            Ok(reaped)
+        };
+        let reaped =
+            withdraw(balances).map_err(|error| error.in_pallet(<T as HasPallet<Self>>::INDEX))?;
+        if reaped {
+            let balances: &Self = runtime.pallet();
+            let dust = balances.balance(who);
+            Self::reap_account(runtime, who, dust);
+        }
+        Ok(NegativeImbalance(amount))
+    }
+
+    fn deposit(
+        runtime: &mut T,
+        who: &T::AccountId,
+        amount: T::Balance,
+    ) -> Result<PositiveImbalance<T>, DispatchError> {
+        let balances: &mut Self = runtime.pallet_mut();
+        let deposit = |balances: &mut Self| -> DispatchResult {
+            // The total issuance must be able to grow by `amount` once the deposit is settled.
+            balances.total_issuance().checked_add(&amount).ok_or(Error::Overflow)?;
+            let new_balance = balances.balance(who).checked_add(&amount).ok_or(Error::Overflow)?;
+            if balances.below_existential_deposit(who, new_balance) {
+                return Err(Error::ExistentialDeposit.into());
+            }
+            balances.put_balance(who, new_balance);
+            balances.deposit_event(Event::Deposited { who: who.clone(), amount });
+            Ok(())
+        };
+        deposit(balances).map_err(|error| error.in_pallet(<T as HasPallet<Self>>::INDEX))?;
+        Ok(PositiveImbalance(amount))
+    }
+
+    /// Panics if the total issuance overflows, which `deposit` rules out as long as every deposit
+    /// is settled before the next one.
+    fn settle(runtime: &mut T, deposited: PositiveImbalance<T>, withdrawn: NegativeImbalance<T>) {
+        let balances: &mut Self = runtime.pallet_mut();
+        match deposited.0.checked_sub(&withdrawn.0) {
+            Some(minted) => {
+                let total_issuance = balances.total_issuance().checked_add(&minted);
+                balances.total_issuance.put(total_issuance.expect("total issuance overflows"));
+            },
+            None => {
+                let burned = withdrawn.0.checked_sub(&deposited.0).unwrap_or_else(T::Balance::zero);
+                balances.burn_issuance(burned);
+            },
+        }
+    }
+}
+
+impl<T: Config> ReservableCurrency<T::AccountId, T> for Pallet<T>
+where
+    T: HasPallet<Pallet<T>> + HasPallet<crate::system::Pallet<T>>,
+{
+    fn reserved_balance(runtime: &T, who: &T::AccountId) -> T::Balance {
+        let balances: &Self = runtime.pallet();
+        balances.reserved_balance(who)
+    }
+
+    fn reserve(runtime: &mut T, who: &T::AccountId, amount: T::Balance) -> DispatchResult {
+        let balances: &mut Self = runtime.pallet_mut();
+        let reserve = |balances: &mut Self| -> DispatchResult {
+            let new_balance =
+                balances.balance(who).checked_sub(&amount).ok_or(Error::InsufficientBalance)?;
+            balances.ensure_can_withdraw(who, new_balance)?;
+            let new_reserved =
+                balances.reserved_balance(who).checked_add(&amount).ok_or(Error::Overflow)?;
+            // The funds only move within the account, which is kept alive.
+            balances.put_balance(who, new_balance);
+            balances.reserved.insert(who.clone(), new_reserved);
+            balances.deposit_event(Event::Reserved { who: who.clone(), amount });
+            Ok(())
+        };
+        reserve(balances).map_err(|error| error.in_pallet(<T as HasPallet<Self>>::INDEX))
+    }
+
+    fn unreserve(runtime: &mut T, who: &T::AccountId, amount: T::Balance) -> T::Balance {
+        let balances: &mut Self = runtime.pallet_mut();
+        let reserved = balances.reserved_balance(who);
+        let actual = if amount < reserved { amount } else { reserved };
+        if actual.is_zero() {
+            return amount;
+        }
+        let new_reserved = reserved.checked_sub(&actual).unwrap_or_else(T::Balance::zero);
+        if new_reserved.is_zero() {
+            balances.reserved.remove(who);
+        } else {
+            balances.reserved.insert(who.clone(), new_reserved);
+        }
+        balances.credit(who, actual);
+        balances.deposit_event(Event::Unreserved { who: who.clone(), amount: actual });
+        amount.checked_sub(&actual).unwrap_or_else(T::Balance::zero)
+    }
+
+    fn slash_reserved(runtime: &mut T, who: &T::AccountId, amount: T::Balance) -> T::Balance {
+        let balances: &mut Self = runtime.pallet_mut();
+        let reserved = balances.reserved_balance(who);
+        let actual = if amount < reserved { amount } else { reserved };
+        if actual.is_zero() {
+            return amount;
+        }
+        let new_reserved = reserved.checked_sub(&actual).unwrap_or_else(T::Balance::zero);
+        if new_reserved.is_zero() {
+            balances.reserved.remove(who);
+        } else {
+            balances.reserved.insert(who.clone(), new_reserved);
+        }
+        balances.burn_issuance(actual);
+        balances.deposit_event(Event::ReserveSlashed { who: who.clone(), amount: actual });
+        let free = balances.balance(who);
+        if balances.below_existential_deposit(who, free) {
+            Self::reap_account(runtime, who, free);
+        }
+        amount.checked_sub(&actual).unwrap_or_else(T::Balance::zero)
+    }
+}
+
+impl<T: Config> LockableCurrency<T::AccountId, T> for Pallet<T>
+where
+    T: HasPallet<Pallet<T>> + HasPallet<crate::system::Pallet<T>>,
+{
+    fn set_lock(runtime: &mut T, id: LockIdentifier, who: &T::AccountId, amount: T::Balance) {
+        let balances: &mut Self = runtime.pallet_mut();
+        balances.locks.insert(who.clone(), id, amount);
+    }
+
+    fn remove_lock(runtime: &mut T, id: LockIdentifier, who: &T::AccountId) {
+        let balances: &mut Self = runtime.pallet_mut();
+        balances.locks.remove(who, &id);
+    }
+}
+
+
+
+
+#[cfg(test)]
+mod tests{
+    use super::Pallet;
+    use crate::{mock::TestRuntime, support::Storage, system};
+
     #[test]
     fn init_balances() {
-        let mut balances = super::Pallet::<TestConfig>::new();
+        let mut balances = Pallet::<TestRuntime>::new(&Storage::in_memory());
 
         assert_eq!(balances.balance(&"alice".to_string()), 0);
         balances.set_balance(&"alice".to_string(), 100);
         assert_eq!(balances.balance(&"alice".to_string()), 100);
         assert_eq!(balances.balance(&"bob".to_string()), 0);
     }
+
+    #[test]
+    fn genesis() {
+        use crate::support::{BuildGenesisConfig, DispatchError};
+        let mut runtime = TestRuntime::new();
+        let alice = "alice".to_string();
+        let bob = "bob".to_string();
+
+        let config =
+            super::GenesisConfig { balances: vec![(alice.clone(), 100), (bob.clone(), 50)] };
+        assert_eq!(config.build(&mut runtime), Ok(()));
+        assert_eq!(runtime.balances.balance(&alice), 100);
+        assert_eq!(runtime.balances.total_issuance(), 150);
+
+        let config = super::GenesisConfig { balances: vec![(alice, 10)] };
+        assert_eq!(
+            config.build(&mut runtime),
+            Err(DispatchError::Other("duplicate account in the genesis balances"))
+        );
+        let config = super::GenesisConfig { balances: vec![("charlie".to_string(), 4)] };
+        assert_eq!(config.build(&mut runtime), Err(super::Error::ExistentialDeposit.into()));
+
+        // Balances the total issuance cannot hold are rejected before any of them is set.
+        let config = super::GenesisConfig {
+            balances: vec![("charlie".to_string(), 10), ("dave".to_string(), u128::MAX)],
+        };
+        assert_eq!(config.build(&mut runtime), Err(super::Error::Overflow.into()));
+        assert_eq!(runtime.balances.balance(&"charlie".to_string()), 0);
+        assert_eq!(runtime.balances.total_issuance(), 150);
+    }
+
     #[test]
     fn test_transfer() {
-        let mut balances = super::Pallet::<TestConfig>::new();
+        let mut runtime = TestRuntime::new();
+        let alice = "alice".to_string();
+        let bob = "bob".to_string();
 
         assert_eq!(
-            balances.transfer("alice".to_string(), "bob".to_string(), 51),
-            Err("Not enough funds.")
+            Pallet::transfer_allow_death(&mut runtime, alice.clone(), bob.clone(), 51),
+            Err(super::Error::InsufficientBalance.into())
         );
 
-        balances.set_balance(&"alice".to_string(), 100);
-        assert_eq!(balances.transfer("alice".to_string(), "bob".to_string(), 51), Ok(()));
-        assert_eq!(balances.balance(&"alice".to_string()), 49);
-        assert_eq!(balances.balance(&"bob".to_string()), 51);
+        runtime.balances.set_balance(&alice, 100);
+        assert_eq!(
+            Pallet::transfer_allow_death(&mut runtime, alice.clone(), bob.clone(), 51),
+            Ok(())
+        );
+        assert_eq!(runtime.balances.balance(&alice), 49);
+        assert_eq!(runtime.balances.balance(&bob), 51);
+        assert_eq!(
+            runtime.balances.take_events(),
+            vec![super::Event::Transfer { from: alice.clone(), to: bob.clone(), amount: 51 }]
+        );
+
+        assert_eq!(
+            Pallet::transfer(&mut runtime, alice.clone(), bob.clone(), 51),
+            Err(super::Error::InsufficientBalance.into())
+        );
+        assert_eq!(Pallet::transfer(&mut runtime, alice.clone(), bob.clone(), 49), Ok(()));
+        assert_eq!(runtime.balances.balance(&bob), 100);
+    }
+
+    #[test]
+    fn existential_deposit() {
+        let mut runtime = TestRuntime::new();
+        let alice = "alice".to_string();
+        let bob = "bob".to_string();
+        let charlie = "charlie".to_string();
+        runtime.balances.set_balance(&alice, 100);
+        runtime.system.inc_nonce(&alice);
+
+        // An account cannot be created with less than the existential deposit.
+        assert_eq!(
+            Pallet::transfer_allow_death(&mut runtime, alice.clone(), bob.clone(), 4),
+            Err(super::Error::ExistentialDeposit.into())
+        );
+        assert_eq!(
+            Pallet::transfer_allow_death(&mut runtime, alice.clone(), bob.clone(), 5),
+            Ok(())
+        );
+        assert_eq!(
+            Pallet::transfer_allow_death(&mut runtime, alice.clone(), bob.clone(), 4),
+            Ok(())
+        );
+
+        // Keeping the caller alive, it cannot go below the existential deposit.
+        assert_eq!(
+            Pallet::transfer_keep_alive(&mut runtime, alice.clone(), charlie.clone(), 88),
+            Err(super::Error::KeepAlive.into())
+        );
+        assert_eq!(
+            Pallet::transfer_keep_alive(&mut runtime, alice.clone(), charlie.clone(), 86),
+            Ok(())
+        );
+        assert_eq!(runtime.balances.balance(&alice), 5);
+        runtime.balances.take_events();
+
+        // Otherwise it is reaped, along with its nonce, and the dust is lost.
+        assert_eq!(
+            Pallet::transfer_allow_death(&mut runtime, alice.clone(), bob.clone(), 2),
+            Ok(())
+        );
+        assert_eq!(runtime.balances.balance(&alice), 0);
+        assert_eq!(runtime.balances.balance(&bob), 11);
+        assert_eq!(runtime.system.account_nonce(&alice), 0);
+        assert_eq!(
+            runtime.balances.take_events(),
+            vec![
+                super::Event::Transfer { from: alice.clone(), to: bob.clone(), amount: 2 },
+                super::Event::Reaped { who: alice.clone(), dust: 3 },
+            ]
+        );
+        assert_eq!(runtime.balances.total_issuance(), 97);
+        assert!(runtime.balances.check_total_issuance());
+    }
+
+    #[test]
+    fn total_issuance() {
+        use crate::support::{DispatchError, ModuleError, Origin};
+        let mut runtime = TestRuntime::new();
+        let alice = "alice".to_string();
+        let root = Origin::Root;
+
+        runtime.balances.set_balance(&alice, 100);
+        runtime.balances.set_balance(&"bob".to_string(), 50);
+        runtime.balances.set_balance(&alice, 70);
+        assert_eq!(runtime.balances.total_issuance(), 120);
+        assert!(runtime.balances.check_total_issuance());
+
+        // Only root can mint and burn.
+        let bad_origin =
+            DispatchError::Module(ModuleError { index: Some(0), error: 5, message: "BadOrigin" });
+        let signed = Origin::Signed(alice.clone());
+        assert_eq!(Pallet::mint(&mut runtime, signed.clone(), alice.clone(), 10), Err(bad_origin));
+        assert_eq!(Pallet::burn(&mut runtime, signed, alice.clone(), 10), Err(bad_origin));
+
+        assert_eq!(Pallet::mint(&mut runtime, root.clone(), alice.clone(), 30), Ok(()));
+        assert_eq!(
+            Pallet::mint(&mut runtime, root.clone(), "charlie".to_string(), 4),
+            Err(super::Error::ExistentialDeposit.into())
+        );
+        assert_eq!(runtime.balances.balance(&alice), 100);
+        assert_eq!(runtime.balances.total_issuance(), 150);
+        assert_eq!(
+            Pallet::burn(&mut runtime, root.clone(), alice.clone(), 101),
+            Err(super::Error::InsufficientBalance.into())
+        );
+        assert_eq!(Pallet::burn(&mut runtime, root.clone(), alice.clone(), 40), Ok(()));
+        assert_eq!(runtime.balances.total_issuance(), 110);
+        assert!(runtime.balances.check_total_issuance());
+
+        // Burning below the existential deposit also burns the dust.
+        assert_eq!(Pallet::burn(&mut runtime, root.clone(), alice.clone(), 58), Ok(()));
+        assert_eq!(runtime.balances.balance(&alice), 0);
+        assert_eq!(runtime.balances.total_issuance(), 50);
+        assert!(runtime.balances.check_total_issuance());
+        assert_eq!(
+            runtime.balances.take_events(),
+            vec![
+                super::Event::Minted { who: alice.clone(), amount: 30 },
+                super::Event::Burned { who: alice.clone(), amount: 40 },
+                super::Event::Burned { who: alice.clone(), amount: 58 },
+                super::Event::Reaped { who: alice.clone(), dust: 2 },
+            ]
+        );
+    }
+
+    #[test]
+    fn reserves() {
+        use crate::support::{DispatchError, ModuleError, ReservableCurrency};
+        let mut runtime = TestRuntime::new();
+        let alice = "alice".to_string();
+        let bob = "bob".to_string();
+        runtime.balances.set_balance(&alice, 100);
+        runtime.system.inc_nonce(&alice);
+
+        // Errors of the currency are tagged with the index of `balances`.
+        assert_eq!(
+            Pallet::reserve(&mut runtime, &alice, 101),
+            Err(DispatchError::Module(ModuleError {
+                index: Some(1),
+                error: 0,
+                message: "InsufficientBalance"
+            }))
+        );
+        assert_eq!(Pallet::reserve(&mut runtime, &alice, 60), Ok(()));
+        assert_eq!(runtime.balances.balance(&alice), 40);
+        assert_eq!(runtime.balances.reserved_balance(&alice), 60);
+
+        // Reserved funds cannot be transferred, but they keep the account alive.
+        assert_eq!(
+            Pallet::transfer_allow_death(&mut runtime, alice.clone(), bob.clone(), 41),
+            Err(super::Error::InsufficientBalance.into())
+        );
+        assert_eq!(
+            Pallet::transfer_keep_alive(&mut runtime, alice.clone(), bob.clone(), 40),
+            Ok(())
+        );
+        assert_eq!(runtime.balances.balance(&alice), 0);
+        assert_eq!(runtime.system.account_nonce(&alice), 1);
+
+        // Only what is reserved can be unreserved or slashed.
+        assert_eq!(Pallet::unreserve(&mut runtime, &alice, 10), 0);
+        assert_eq!(Pallet::slash_reserved(&mut runtime, &alice, 20), 0);
+        assert_eq!(runtime.balances.balance(&alice), 10);
+        assert_eq!(runtime.balances.reserved_balance(&alice), 30);
+        assert_eq!(runtime.balances.total_issuance(), 80);
+        assert!(runtime.balances.check_total_issuance());
+
+        // Slashing below the existential deposit reaps the account.
+        assert_eq!(
+            Pallet::transfer_allow_death(&mut runtime, alice.clone(), bob.clone(), 8),
+            Ok(())
+        );
+        assert_eq!(Pallet::slash_reserved(&mut runtime, &alice, 40), 10);
+        assert_eq!(runtime.balances.balance(&alice), 0);
+        assert_eq!(runtime.system.account_nonce(&alice), 0);
+        assert_eq!(runtime.balances.total_issuance(), 48);
+        assert!(runtime.balances.check_total_issuance());
+        assert_eq!(
+            runtime.balances.take_events(),
+            vec![
+                super::Event::Reserved { who: alice.clone(), amount: 60 },
+                super::Event::Transfer { from: alice.clone(), to: bob.clone(), amount: 40 },
+                super::Event::Unreserved { who: alice.clone(), amount: 10 },
+                super::Event::ReserveSlashed { who: alice.clone(), amount: 20 },
+                super::Event::Transfer { from: alice.clone(), to: bob.clone(), amount: 8 },
+                super::Event::ReserveSlashed { who: alice.clone(), amount: 30 },
+                super::Event::Reaped { who: alice.clone(), dust: 2 },
+            ]
+        );
+    }
+
+    #[test]
+    fn locks() {
+        use crate::support::{DispatchError, LockableCurrency, ReservableCurrency};
+        let mut runtime = TestRuntime::new();
+        let alice = "alice".to_string();
+        let bob = "bob".to_string();
+        runtime.balances.set_balance(&alice, 100);
+
+        // Locks overlap, so only the largest one applies.
+        Pallet::set_lock(&mut runtime, *b"staking ", &alice, 50);
+        Pallet::set_lock(&mut runtime, *b"vesting ", &alice, 70);
+        assert_eq!(runtime.balances.locked_balance(&alice), 70);
+        assert_eq!(
+            Pallet::transfer_allow_death(&mut runtime, alice.clone(), bob.clone(), 31),
+            Err(super::Error::LiquidityRestrictions.into())
+        );
+        assert_eq!(
+            Pallet::reserve(&mut runtime, &alice, 31),
+            Err(DispatchError::from(super::Error::LiquidityRestrictions).in_pallet(1))
+        );
+        assert_eq!(
+            runtime.balances.withdraw_fee(&alice, 31_000),
+            Err(super::Error::LiquidityRestrictions.into())
+        );
+        assert_eq!(
+            Pallet::transfer_allow_death(&mut runtime, alice.clone(), bob.clone(), 30),
+            Ok(())
+        );
+
+        // Setting a lock again replaces it.
+        Pallet::set_lock(&mut runtime, *b"vesting ", &alice, 20);
+        assert_eq!(runtime.balances.locked_balance(&alice), 50);
+        Pallet::remove_lock(&mut runtime, *b"staking ", &alice);
+        assert_eq!(runtime.balances.locked_balance(&alice), 20);
+        assert_eq!(
+            Pallet::transfer_allow_death(&mut runtime, alice.clone(), bob.clone(), 50),
+            Ok(())
+        );
+        Pallet::remove_lock(&mut runtime, *b"vesting ", &alice);
+        assert_eq!(runtime.balances.locked_balance(&alice), 0);
+    }
+
+    #[test]
+    fn currency() {
+        use crate::support::{Currency, DispatchError, ExistenceRequirement::*, Imbalance};
+        let mut runtime = TestRuntime::new();
+        let alice = "alice".to_string();
+        let bob = "bob".to_string();
+        runtime.balances.set_balance(&alice, 100);
+
+        // The call named `transfer` shadows the function of `Currency`.
+        type Balances = Pallet<TestRuntime>;
+        let transfer = <Balances as Currency<String, TestRuntime>>::transfer;
+        assert_eq!(
+            transfer(&mut runtime, &alice, &bob, 96, KeepAlive),
+            Err(DispatchError::from(super::Error::KeepAlive).in_pallet(1))
+        );
+        assert_eq!(transfer(&mut runtime, &alice, &bob, 30, KeepAlive), Ok(()));
+        assert_eq!(Pallet::free_balance(&runtime, &bob), 30);
+
+        // Funds withdrawn and deposited elsewhere leave the total issuance as it is...
+        let withdrawn = Pallet::withdraw(&mut runtime, &alice, 20, KeepAlive).unwrap();
+        let deposited = Pallet::deposit(&mut runtime, &bob, 20).unwrap();
+        assert_eq!(withdrawn.peek(), 20);
+        Pallet::settle(&mut runtime, deposited, withdrawn);
+        assert_eq!(runtime.balances.total_issuance(), 100);
+        assert!(runtime.balances.check_total_issuance());
+
+        // ...while the difference between them is burned or minted.
+        let withdrawn = Pallet::withdraw(&mut runtime, &alice, 10, AllowDeath).unwrap();
+        let withdrawn =
+            withdrawn.merge(Pallet::withdraw(&mut runtime, &bob, 10, AllowDeath).unwrap());
+        let deposited = Pallet::deposit(&mut runtime, &bob, 5).unwrap();
+        Pallet::settle(&mut runtime, deposited, withdrawn);
+        assert_eq!(runtime.balances.total_issuance(), 85);
+        assert!(runtime.balances.check_total_issuance());
+        let deposited = Pallet::deposit(&mut runtime, &"charlie".to_string(), 15).unwrap();
+        Pallet::settle(&mut runtime, deposited, super::NegativeImbalance::zero());
+        assert_eq!(runtime.balances.total_issuance(), 100);
+        assert!(runtime.balances.check_total_issuance());
+
+        assert_eq!(
+            Pallet::deposit(&mut runtime, &"dave".to_string(), 4),
+            Err(DispatchError::from(super::Error::ExistentialDeposit).in_pallet(1))
+        );
+        // A deposit which the total issuance cannot take is rejected before it is settled.
+        assert_eq!(
+            Pallet::deposit(&mut runtime, &"dave".to_string(), u128::MAX - 99),
+            Err(DispatchError::from(super::Error::Overflow).in_pallet(1))
+        );
+        assert_eq!(Pallet::free_balance(&runtime, &"dave".to_string()), 0);
+        // Withdrawing below the existential deposit reaps the account, and burns the dust.
+        let withdrawn = Pallet::withdraw(&mut runtime, &alice, 38, AllowDeath).unwrap();
+        Pallet::settle(&mut runtime, super::PositiveImbalance::zero(), withdrawn);
+        assert_eq!(Pallet::free_balance(&runtime, &alice), 0);
+        assert_eq!(runtime.balances.total_issuance(), 60);
+        assert!(runtime.balances.check_total_issuance());
+    }
+
+    #[test]
+    fn errors_are_identified_by_index() {
+        use crate::support::{DispatchError, ModuleError};
+        let mut runtime = TestRuntime::new();
+
+        let error =
+            Pallet::transfer_allow_death(&mut runtime, "alice".to_string(), "bob".to_string(), 1)
+                .unwrap_err();
+        assert_eq!(
+            error.in_pallet(1),
+            DispatchError::Module(ModuleError {
+                index: Some(1),
+                error: 0,
+                message: "InsufficientBalance"
+            })
+        );
+        assert_eq!(super::Error::try_from(1), Ok(super::Error::Overflow));
+        assert_eq!(super::Error::try_from(5), Err(()));
+    }
+
+    #[test]
+    fn encode_call() {
+        use crate::support::{CodecError, Decode, Encode};
+        let call =
+            super::Call::<TestRuntime>::transfer_allow_death { to: "bob".to_string(), amount: 7 };
 
+        // The call index, followed by the arguments.
+        let encoded = [vec![1, 0x0c], b"bob".to_vec(), 7u128.to_le_bytes().to_vec()].concat();
+        assert_eq!(call.encode(), encoded);
+        assert_eq!(super::Call::<TestRuntime>::decode_all(&encoded), Ok(call));
+        let call = super::Call::<TestRuntime>::transfer { to: "bob".to_string(), amount: 7 };
+        assert_eq!(call.encode()[0], 0);
         assert_eq!(
-            balances.transfer("alice".to_string(), "bob".to_string(), 51),
-            Err("Not enough funds.")
+            super::Call::<TestRuntime>::decode_all(&[7]),
+            Err(CodecError("invalid call index"))
         );
     }
+
+    #[test]
+    fn dispatch_call() {
+        use crate::support::{DispatchError, Origin};
+        let mut runtime = TestRuntime::new();
+        runtime.balances.set_balance(&"alice".to_string(), 100);
+
+        let call =
+            super::Call::<TestRuntime>::transfer_keep_alive { to: "bob".to_string(), amount: 7 };
+        let origin = Origin::Signed("alice".to_string());
+        assert_eq!(call.dispatch(&mut runtime, origin), Ok(Default::default()));
+        assert_eq!(runtime.balances.balance(&"bob".to_string()), 7);
+
+        // Calls made by a `caller` need a signed origin.
+        let call =
+            super::Call::<TestRuntime>::transfer_keep_alive { to: "bob".to_string(), amount: 7 };
+        assert_eq!(
+            call.dispatch(&mut runtime, Origin::Root),
+            Err(DispatchError::from(system::Error::BadOrigin).in_pallet(0))
+        );
+
+        // Root can force the balance of any account...
+        let call =
+            super::Call::<TestRuntime>::force_set_balance { who: "bob".to_string(), free: 50 };
+        assert_eq!(call.clone().dispatch(&mut runtime, Origin::Root), Ok(Default::default()));
+        assert_eq!(runtime.balances.balance(&"bob".to_string()), 50);
+        assert_eq!(runtime.balances.total_issuance(), 143);
+        assert!(runtime.balances.check_total_issuance());
+        assert_eq!(
+            call.dispatch(&mut runtime, Origin::Signed("alice".to_string())),
+            Err(DispatchError::from(system::Error::BadOrigin).in_pallet(0))
+        );
+
+        // ...and transfer from it.
+        let call = super::Call::<TestRuntime>::force_transfer {
+            from: "bob".to_string(),
+            to: "charlie".to_string(),
+            amount: 48,
+        };
+        assert_eq!(call.dispatch(&mut runtime, Origin::Root), Ok(Default::default()));
+        assert_eq!(runtime.balances.balance(&"bob".to_string()), 0);
+        assert_eq!(runtime.balances.balance(&"charlie".to_string()), 48);
+    }
+
+    #[test]
+    fn fees() {
+        let mut balances = Pallet::<TestRuntime>::new(&Storage::in_memory());
+        let alice = "alice".to_string();
+        let treasury = "treasury".to_string();
+        balances.set_balance(&alice, 100);
+
+        let call =
+            super::Call::<TestRuntime>::transfer_allow_death { to: "bob".to_string(), amount: 7 };
+        let weight = call.weight();
+        assert_eq!(weight, 10_000);
+        assert_eq!(
+            balances.withdraw_fee(&alice, 200_000),
+            Err(super::Error::InsufficientBalance.into())
+        );
+        // Paying a fee cannot reap the account.
+        assert_eq!(balances.withdraw_fee(&alice, 96_000), Err(super::Error::KeepAlive.into()));
+        assert_eq!(balances.withdraw_fee(&alice, weight), Ok(10));
+        assert_eq!(balances.balance(&alice), 90);
+
+        // The call only used 6_000 of its weight, so 4 of the 10 withdrawn are refunded.
+        balances.correct_and_deposit_fee(&alice, 10, 6_000);
+        assert_eq!(balances.balance(&alice), 94);
+        assert_eq!(balances.balance(&treasury), 6);
+        assert_eq!(
+            balances.take_events(),
+            vec![super::Event::TransactionFeePaid { who: alice.clone(), actual_fee: 6 }]
+        );
+
+        // The caller never pays more than what was withdrawn.
+        let fee = balances.withdraw_fee(&alice, 5_000).unwrap();
+        balances.correct_and_deposit_fee(&alice, fee, 20_000);
+        assert_eq!(balances.balance(&alice), 89);
+        assert_eq!(balances.balance(&treasury), 11);
+        assert!(balances.check_total_issuance());
+
+        // A fee too small to create the account of the treasury is burned.
+        balances.set_balance(&treasury, 0);
+        let fee = balances.withdraw_fee(&alice, 4_000).unwrap();
+        balances.correct_and_deposit_fee(&alice, fee, 4_000);
+        assert_eq!(balances.balance(&treasury), 0);
+        assert_eq!(balances.total_issuance(), 85);
+        assert!(balances.check_total_issuance());
+    }
+
+    #[test]
+    fn failed_transaction_rolls_back() {
+        let mut runtime = TestRuntime::new();
+        runtime.balances.set_balance(&"alice".to_string(), 100);
+
+        // The first transfer succeeds, but the second one fails, so neither should be applied.
+        let result = crate::support::with_transaction(&mut runtime, |runtime| {
+            Pallet::transfer_allow_death(runtime, "alice".to_string(), "bob".to_string(), 60)?;
+            Pallet::transfer_allow_death(runtime, "alice".to_string(), "charlie".to_string(), 60)
+        });
+        assert_eq!(result, Err(super::Error::InsufficientBalance.into()));
+        assert_eq!(runtime.balances.balance(&"alice".to_string()), 100);
+        assert_eq!(runtime.balances.balance(&"bob".to_string()), 0);
+        assert_eq!(runtime.balances.balance(&"charlie".to_string()), 0);
+        assert_eq!(runtime.balances.take_events(), vec![]);
+    }
 }
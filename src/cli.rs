@@ -0,0 +1,591 @@
+//! The command line interface of the binary, which replays a scenario written in JSON: a genesis
+//! configuration, and a list of blocks to execute on top of it.
+//!
+//! Accounts are written as the name of a development account, such as `"alice"`, whose key pair
+//! signs the extrinsics of that account.
+
+use crate::{
+    balances,
+    database::{Database, SNAPSHOT_INTERVAL},
+    dev_account, multisig, print_events, print_receipt, proof_of_existence, proxy, sign, staking,
+    sudo, support, types, utility, vesting, ProxyType, Runtime, RuntimeCall, RuntimeGenesisConfig,
+};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+pub const USAGE: &str = "usage: rust-state-machine run --genesis <genesis.json> \
+    --blocks <blocks.json> [--database <dir>]";
+
+/// A command given on the command line.
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    /// Execute the blocks of `blocks` on top of the genesis of `genesis`, both paths to JSON files.
+    /// With `database`, the chain is kept in that directory, and a run resumes from the blocks
+    /// executed by the previous runs, skipping them in `blocks`.
+    Run { genesis: String, blocks: String, database: Option<String> },
+}
+
+impl Command {
+    /// Parse the arguments of the binary, without the name of the binary itself.
+    pub fn parse(args: &[String]) -> Result<Self, String> {
+        let (command, mut options) = match args.split_first() {
+            Some((command, options)) => (command.as_str(), options),
+            None => return Err("missing command".to_string()),
+        };
+        if command != "run" {
+            return Err(format!("unknown command `{command}`"))
+        }
+
+        let (mut genesis, mut blocks, mut database) = (None, None, None);
+        while let Some((option, rest)) = options.split_first() {
+            let slot = match option.as_str() {
+                "--genesis" => &mut genesis,
+                "--blocks" => &mut blocks,
+                "--database" => &mut database,
+                _ => return Err(format!("unknown option `{option}`")),
+            };
+            let Some((value, rest)) = rest.split_first() else {
+                return Err(format!("missing value for `{option}`"))
+            };
+            *slot = Some(value.clone());
+            options = rest;
+        }
+        match (genesis, blocks) {
+            (Some(genesis), Some(blocks)) => Ok(Command::Run { genesis, blocks, database }),
+            (None, _) => Err("missing option `--genesis`".to_string()),
+            (_, None) => Err("missing option `--blocks`".to_string()),
+        }
+    }
+}
+
+/// Run a command, printing the outcome of every block and the final state of the runtime.
+pub fn run(command: Command) -> Result<(), String> {
+    let Command::Run { genesis, blocks, database } = command;
+    let genesis: GenesisSpec = read_json(&genesis)?;
+    let blocks: Vec<BlockSpec> = read_json(&blocks)?;
+
+    let (mut runtime, mut database) = match &database {
+        Some(dir) => {
+            let (runtime, database) = resume(dir, genesis)?;
+            (runtime, Some((dir, database)))
+        },
+        None => {
+            let runtime = Runtime::from_genesis(genesis.into())
+                .map_err(|error| format!("invalid genesis: {error}"))?;
+            (runtime, None)
+        },
+    };
+    // The blocks which were executed by a previous run are already part of the chain.
+    let executed = runtime.system.block_number() as usize;
+    for (i, block) in blocks.into_iter().enumerate().skip(executed) {
+        let extrinsics = block.sign(&runtime);
+        let block = runtime
+            .build_block(extrinsics)
+            .map_err(|error| format!("invalid extrinsics in block {}: {error}", i + 1))?;
+        let receipt = runtime
+            .execute_block(block.clone())
+            .map_err(|error| format!("invalid block {}: {error}", i + 1))?;
+        if let Some((dir, database)) = &mut database {
+            database.append_block(&block).map_err(|error| format!("{dir}: {error}"))?;
+            if receipt.block_number % SNAPSHOT_INTERVAL == 0 {
+                runtime.system.storage().flush().map_err(|error| format!("{dir}: {error}"))?;
+            }
+        }
+        print_receipt(&receipt);
+        print_events(&runtime);
+    }
+    // A clean exit leaves no block to replay.
+    if let Some((dir, _)) = &database {
+        runtime.system.storage().flush().map_err(|error| format!("{dir}: {error}"))?;
+    }
+    println!("{runtime:#?}");
+    Ok(())
+}
+
+/// Open the database in `dir`, and bring a runtime back to the last block stored in it, by
+/// loading the latest snapshot of the state and replaying the blocks executed after it. A new
+/// database starts from `genesis`, whose state is its first snapshot.
+fn resume(dir: &str, genesis: GenesisSpec) -> Result<(Runtime, Database), String> {
+    let io_error = |error: std::io::Error| format!("{dir}: {error}");
+    let (mut database, blocks) = Database::open(dir).map_err(io_error)?;
+    let new = !database.has_state();
+    let mut runtime = Runtime::with_storage(database.state().map_err(io_error)?);
+    runtime.system.storage().check().map_err(io_error)?;
+    if new {
+        if !blocks.is_empty() {
+            return Err(format!("{dir}: the block log has no state snapshot"))
+        }
+        runtime
+            .build_genesis(genesis.into())
+            .map_err(|error| format!("invalid genesis: {error}"))?;
+        runtime.system.storage().flush().map_err(io_error)?;
+    }
+
+    let snapshot = runtime.system.block_number() as usize;
+    if snapshot > blocks.len() {
+        return Err(format!("{dir}: the state snapshot is ahead of the block log"))
+    }
+    for block in blocks.into_iter().skip(snapshot) {
+        let number = block.header.block_number;
+        runtime
+            .execute_block(block)
+            .map_err(|error| format!("{dir}: cannot replay block {number}: {error}"))?;
+    }
+    // The blocks of the log agree with the snapshot, so what follows them is only a torn record.
+    database.drop_torn_record().map_err(io_error)?;
+    Ok((runtime, database))
+}
+
+fn read_json<T: for<'de> Deserialize<'de>>(path: &str) -> Result<T, String> {
+    let json = std::fs::read_to_string(path).map_err(|error| format!("{path}: {error}"))?;
+    serde_json::from_str(&json).map_err(|error| format!("{path}: {error}"))
+}
+
+/// A development account, written as its name.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(transparent)]
+pub struct Account(pub String);
+
+impl Account {
+    fn public(&self) -> types::AccountId {
+        dev_account(&self.0).public()
+    }
+}
+
+/// A hash, written in hexadecimal with a `0x` prefix, as it is printed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hash(pub types::Hash);
+
+impl<'de> Deserialize<'de> for Hash {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+        let hex = String::deserialize(deserializer)?;
+        let digits = hex.strip_prefix("0x").unwrap_or(&hex);
+        if digits.len() != 64 || !digits.is_ascii() {
+            return Err(D::Error::custom("expected 32 bytes in hexadecimal"))
+        }
+        let mut hash = [0u8; 32];
+        for (byte, pair) in hash.iter_mut().zip(digits.as_bytes().chunks(2)) {
+            let pair = core::str::from_utf8(pair).expect("checked to be ascii");
+            *byte = u8::from_str_radix(pair, 16).map_err(D::Error::custom)?;
+        }
+        Ok(Hash(support::H256(hash)))
+    }
+}
+
+/// The genesis configuration of a scenario. Every field can be left out.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GenesisSpec {
+    /// The initial free balance of each account.
+    pub balances: Vec<(Account, types::Balance)>,
+    /// The initial claims of `proof_of_existence`, along with their owner.
+    pub claims: Vec<(types::Content, Account)>,
+    /// The sudo key.
+    pub sudo: Option<Account>,
+    /// The initial vesting schedules.
+    pub vesting: Vec<(Account, VestingInfoSpec)>,
+    /// The initial stashes, along with the funds they bond.
+    pub stakers: Vec<(Account, types::Balance)>,
+}
+
+impl From<GenesisSpec> for RuntimeGenesisConfig {
+    fn from(spec: GenesisSpec) -> Self {
+        let accounts = |list: Vec<(Account, types::Balance)>| {
+            list.into_iter().map(|(who, amount)| (who.public(), amount)).collect()
+        };
+        RuntimeGenesisConfig {
+            balances: balances::GenesisConfig { balances: accounts(spec.balances) },
+            proof_of_existence: proof_of_existence::GenesisConfig {
+                claims: spec.claims.into_iter().map(|(claim, who)| (claim, who.public())).collect(),
+            },
+            sudo: sudo::GenesisConfig { key: spec.sudo.map(|key| key.public()) },
+            vesting: vesting::GenesisConfig {
+                vesting: spec
+                    .vesting
+                    .into_iter()
+                    .map(|(who, schedule)| (who.public(), schedule.into()))
+                    .collect(),
+            },
+            staking: staking::GenesisConfig { stakers: accounts(spec.stakers) },
+            ..Default::default()
+        }
+    }
+}
+
+/// A block of a scenario.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BlockSpec {
+    pub extrinsics: Vec<ExtrinsicSpec>,
+}
+
+impl BlockSpec {
+    /// Sign the extrinsics of the block on top of the current state of `runtime`. An extrinsic
+    /// without a nonce uses the next nonce of its signer.
+    fn sign(self, runtime: &Runtime) -> Vec<types::Extrinsic> {
+        let mut nonces = BTreeMap::new();
+        self.extrinsics
+            .into_iter()
+            .map(|extrinsic| {
+                let signer = extrinsic.signer.public();
+                let next_nonce =
+                    nonces.entry(signer).or_insert_with(|| runtime.system.account_nonce(&signer));
+                let nonce = extrinsic.nonce.unwrap_or(*next_nonce);
+                *next_nonce = nonce.saturating_add(1);
+                sign(runtime, &dev_account(&extrinsic.signer.0), nonce, extrinsic.call.into())
+            })
+            .collect()
+    }
+}
+
+/// An extrinsic of a scenario, signed by the development account `signer`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExtrinsicSpec {
+    pub signer: Account,
+    #[serde(default)]
+    pub nonce: Option<types::Nonce>,
+    pub call: CallSpec,
+}
+
+/// A vesting schedule.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VestingInfoSpec {
+    pub locked: types::Balance,
+    pub per_block: types::Balance,
+    pub starting_block: types::BlockNumber,
+}
+
+impl From<VestingInfoSpec> for vesting::VestingInfoOf<Runtime> {
+    fn from(spec: VestingInfoSpec) -> Self {
+        vesting::VestingInfo {
+            locked: spec.locked,
+            per_block: spec.per_block,
+            starting_block: spec.starting_block,
+        }
+    }
+}
+
+/// A call of the runtime, written as `{ "<pallet>": { "<call>": { <arguments> } } }`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum CallSpec {
+    Balances(BalancesCallSpec),
+    ProofOfExistence(ProofOfExistenceCallSpec),
+    Sudo(SudoCallSpec),
+    Utility(UtilityCallSpec),
+    Multisig(MultisigCallSpec),
+    Proxy(ProxyCallSpec),
+    Vesting(VestingCallSpec),
+    Staking(StakingCallSpec),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum BalancesCallSpec {
+    Transfer { to: Account, amount: types::Balance },
+    TransferAllowDeath { to: Account, amount: types::Balance },
+    TransferKeepAlive { to: Account, amount: types::Balance },
+    Mint { who: Account, amount: types::Balance },
+    Burn { who: Account, amount: types::Balance },
+    ForceSetBalance { who: Account, free: types::Balance },
+    ForceTransfer { from: Account, to: Account, amount: types::Balance },
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum ProofOfExistenceCallSpec {
+    CreateClaim { claim: types::Content },
+    RevokeClaim { claim: types::Content },
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum SudoCallSpec {
+    Sudo { call: Box<CallSpec> },
+    SetKey { new: Account },
+    SudoAs { who: Account, call: Box<CallSpec> },
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum UtilityCallSpec {
+    Batch { calls: Vec<CallSpec> },
+    BatchAll { calls: Vec<CallSpec> },
+    ForceBatch { calls: Vec<CallSpec> },
+}
+
+// The variants are named after the calls of `multisig`.
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum MultisigCallSpec {
+    AsMulti { threshold: u16, other_signatories: Vec<Account>, call: Box<CallSpec> },
+    ApproveAsMulti { threshold: u16, other_signatories: Vec<Account>, call_hash: Hash },
+    CancelAsMulti { threshold: u16, other_signatories: Vec<Account>, call_hash: Hash },
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum ProxyCallSpec {
+    Proxy { real: Account, call: Box<CallSpec> },
+    AddProxy { delegate: Account, proxy_type: ProxyType },
+    RemoveProxy { delegate: Account, proxy_type: ProxyType },
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum VestingCallSpec {
+    Vest {},
+    VestedTransfer { target: Account, schedule: VestingInfoSpec },
+    MergeSchedules { schedule1_index: u32, schedule2_index: u32 },
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum StakingCallSpec {
+    Bond { value: types::Balance },
+    Unbond { value: types::Balance },
+    WithdrawUnbonded {},
+}
+
+/// The signatories of a multisig account, sorted as `multisig` expects them.
+fn signatories(accounts: Vec<Account>) -> Vec<types::AccountId> {
+    let mut signatories: Vec<_> = accounts.iter().map(Account::public).collect();
+    signatories.sort();
+    signatories
+}
+
+impl From<CallSpec> for RuntimeCall {
+    fn from(spec: CallSpec) -> Self {
+        let boxed = |call: Box<CallSpec>| Box::new(RuntimeCall::from(*call));
+        let calls = |calls: Vec<CallSpec>| calls.into_iter().map(RuntimeCall::from).collect();
+        match spec {
+            CallSpec::Balances(call) => RuntimeCall::balances(match call {
+                BalancesCallSpec::Transfer { to, amount } =>
+                    balances::Call::transfer { to: to.public(), amount },
+                BalancesCallSpec::TransferAllowDeath { to, amount } =>
+                    balances::Call::transfer_allow_death { to: to.public(), amount },
+                BalancesCallSpec::TransferKeepAlive { to, amount } =>
+                    balances::Call::transfer_keep_alive { to: to.public(), amount },
+                BalancesCallSpec::Mint { who, amount } =>
+                    balances::Call::mint { who: who.public(), amount },
+                BalancesCallSpec::Burn { who, amount } =>
+                    balances::Call::burn { who: who.public(), amount },
+                BalancesCallSpec::ForceSetBalance { who, free } =>
+                    balances::Call::force_set_balance { who: who.public(), free },
+                BalancesCallSpec::ForceTransfer { from, to, amount } =>
+                    balances::Call::force_transfer { from: from.public(), to: to.public(), amount },
+            }),
+            CallSpec::ProofOfExistence(call) => RuntimeCall::proof_of_existence(match call {
+                ProofOfExistenceCallSpec::CreateClaim { claim } =>
+                    proof_of_existence::Call::create_claim { claim },
+                ProofOfExistenceCallSpec::RevokeClaim { claim } =>
+                    proof_of_existence::Call::revoke_claim { claim },
+            }),
+            CallSpec::Sudo(call) => RuntimeCall::sudo(match call {
+                SudoCallSpec::Sudo { call } => sudo::Call::sudo { call: boxed(call) },
+                SudoCallSpec::SetKey { new } => sudo::Call::set_key { new: new.public() },
+                SudoCallSpec::SudoAs { who, call } =>
+                    sudo::Call::sudo_as { who: who.public(), call: boxed(call) },
+            }),
+            CallSpec::Utility(call) => RuntimeCall::utility(match call {
+                UtilityCallSpec::Batch { calls: items } =>
+                    utility::Call::batch { calls: calls(items) },
+                UtilityCallSpec::BatchAll { calls: items } =>
+                    utility::Call::batch_all { calls: calls(items) },
+                UtilityCallSpec::ForceBatch { calls: items } =>
+                    utility::Call::force_batch { calls: calls(items) },
+            }),
+            CallSpec::Multisig(call) => RuntimeCall::multisig(match call {
+                MultisigCallSpec::AsMulti { threshold, other_signatories, call } =>
+                    multisig::Call::as_multi {
+                        threshold,
+                        other_signatories: signatories(other_signatories),
+                        call: boxed(call),
+                    },
+                MultisigCallSpec::ApproveAsMulti { threshold, other_signatories, call_hash } =>
+                    multisig::Call::approve_as_multi {
+                        threshold,
+                        other_signatories: signatories(other_signatories),
+                        call_hash: call_hash.0,
+                    },
+                MultisigCallSpec::CancelAsMulti { threshold, other_signatories, call_hash } =>
+                    multisig::Call::cancel_as_multi {
+                        threshold,
+                        other_signatories: signatories(other_signatories),
+                        call_hash: call_hash.0,
+                    },
+            }),
+            CallSpec::Proxy(call) => RuntimeCall::proxy(match call {
+                ProxyCallSpec::Proxy { real, call } =>
+                    proxy::Call::proxy { real: real.public(), call: boxed(call) },
+                ProxyCallSpec::AddProxy { delegate, proxy_type } =>
+                    proxy::Call::add_proxy { delegate: delegate.public(), proxy_type },
+                ProxyCallSpec::RemoveProxy { delegate, proxy_type } =>
+                    proxy::Call::remove_proxy { delegate: delegate.public(), proxy_type },
+            }),
+            CallSpec::Vesting(call) => RuntimeCall::vesting(match call {
+                VestingCallSpec::Vest {} => vesting::Call::vest {},
+                VestingCallSpec::VestedTransfer { target, schedule } =>
+                    vesting::Call::vested_transfer {
+                        target: target.public(),
+                        schedule: schedule.into(),
+                    },
+                VestingCallSpec::MergeSchedules { schedule1_index, schedule2_index } =>
+                    vesting::Call::merge_schedules { schedule1_index, schedule2_index },
+            }),
+            CallSpec::Staking(call) => RuntimeCall::staking(match call {
+                StakingCallSpec::Bond { value } => staking::Call::bond { value },
+                StakingCallSpec::Unbond { value } => staking::Call::unbond { value },
+                StakingCallSpec::WithdrawUnbonded {} => staking::Call::withdraw_unbonded {},
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{resume, BlockSpec, CallSpec, Command, GenesisSpec};
+    use crate::{balances, dev_account, utility, Runtime, RuntimeCall};
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn parse_command() {
+        assert_eq!(
+            Command::parse(&args(&["run", "--blocks", "b.json", "--genesis", "g.json"])),
+            Ok(Command::Run {
+                genesis: "g.json".to_string(),
+                blocks: "b.json".to_string(),
+                database: None,
+            })
+        );
+        assert_eq!(
+            Command::parse(&args(&["run", "--genesis", "g", "--blocks", "b", "--database", "d"])),
+            Ok(Command::Run {
+                genesis: "g".to_string(),
+                blocks: "b".to_string(),
+                database: Some("d".to_string()),
+            })
+        );
+        assert_eq!(Command::parse(&args(&[])), Err("missing command".to_string()));
+        assert_eq!(Command::parse(&args(&["walk"])), Err("unknown command `walk`".to_string()));
+        assert_eq!(
+            Command::parse(&args(&["run", "--genesis", "g.json"])),
+            Err("missing option `--blocks`".to_string())
+        );
+        assert_eq!(
+            Command::parse(&args(&["run", "--genesis"])),
+            Err("missing value for `--genesis`".to_string())
+        );
+    }
+
+    #[test]
+    fn calls_from_json() {
+        let json = r#"{ "utility": { "batch": { "calls": [
+            { "balances": { "transfer_allow_death": { "to": "bob", "amount": 30 } } },
+            { "vesting": { "vest": {} } }
+        ] } } }"#;
+        let call: CallSpec = serde_json::from_str(json).expect("valid call");
+        assert_eq!(
+            RuntimeCall::from(call),
+            RuntimeCall::utility(utility::Call::batch {
+                calls: vec![
+                    RuntimeCall::balances(balances::Call::transfer_allow_death {
+                        to: dev_account("bob").public(),
+                        amount: 30,
+                    }),
+                    RuntimeCall::vesting(crate::vesting::Call::vest {}),
+                ],
+            })
+        );
+        let unknown = r#"{ "balances": { "steal": { "amount": 30 } } }"#;
+        assert!(serde_json::from_str::<CallSpec>(unknown).is_err());
+    }
+
+    #[test]
+    fn replay_scenario() {
+        let genesis: GenesisSpec =
+            serde_json::from_str(r#"{ "balances": [["alice", 100]], "sudo": "alice" }"#)
+                .expect("valid genesis");
+        let mut runtime = Runtime::from_genesis(genesis.into()).expect("valid genesis");
+        let block: BlockSpec = serde_json::from_str(
+            r#"{ "extrinsics": [
+                { "signer": "alice", "call": {
+                    "proof_of_existence": { "create_claim": { "claim": "hola" } }
+                } },
+                { "signer": "alice", "call": {
+                    "balances": { "transfer_allow_death": { "to": "bob", "amount": 30 } }
+                } }
+            ] }"#,
+        )
+        .expect("valid block");
+
+        // Extrinsics without a nonce use the next nonces of their signer.
+        let extrinsics = block.sign(&runtime);
+        assert_eq!(extrinsics.iter().map(|extrinsic| extrinsic.nonce).collect::<Vec<_>>(), [0, 1]);
+        let block = runtime.build_block(extrinsics).expect("valid extrinsics");
+        let receipt = runtime.execute_block(block).expect("valid block");
+        assert!(receipt.extrinsics.iter().all(|extrinsic| extrinsic.result.is_ok()));
+        assert_eq!(runtime.balances.balance(&dev_account("bob").public()), 30);
+
+        let block: BlockSpec = serde_json::from_str(
+            r#"{ "extrinsics": [
+                { "signer": "alice", "nonce": 4294967295, "call": { "vesting": { "vest": {} } } },
+                { "signer": "alice", "call": { "vesting": { "vest": {} } } }
+            ] }"#,
+        )
+        .expect("valid block");
+        // The last nonce does not overflow, and is left for the runtime to reject.
+        let extrinsics = block.sign(&runtime);
+        let nonces = extrinsics.iter().map(|extrinsic| extrinsic.nonce).collect::<Vec<_>>();
+        assert_eq!(nonces, [u32::MAX, u32::MAX]);
+    }
+
+    #[test]
+    fn resume_from_database() {
+        let dir = std::env::temp_dir()
+            .join(format!("rust-state-machine-{}-resume", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let dir = dir.to_str().expect("utf-8 path").to_string();
+        let genesis = || -> GenesisSpec {
+            serde_json::from_str(r#"{ "balances": [["alice", 100]] }"#).expect("valid genesis")
+        };
+        let transfer = || -> BlockSpec {
+            serde_json::from_str(
+                r#"{ "extrinsics": [ { "signer": "alice", "call": {
+                    "balances": { "transfer_allow_death": { "to": "bob", "amount": 10 } }
+                } } ] }"#,
+            )
+            .expect("valid block")
+        };
+
+        // Execute three blocks, and stop without taking a snapshot after them.
+        let (mut runtime, mut database) = resume(&dir, genesis()).expect("new database");
+        for _ in 0..3 {
+            let block = runtime.build_block(transfer().sign(&runtime)).expect("valid extrinsics");
+            runtime.execute_block(block.clone()).expect("valid block");
+            database.append_block(&block).expect("log can be written");
+        }
+        let state_root = runtime.state_root();
+        drop((runtime, database));
+
+        // The blocks executed after the genesis snapshot are replayed.
+        let (runtime, database) = resume(&dir, genesis()).expect("valid database");
+        assert_eq!(runtime.system.block_number(), 3);
+        assert_eq!(runtime.state_root(), state_root);
+        assert_eq!(runtime.balances.balance(&dev_account("bob").public()), 30);
+
+        // Once a snapshot is taken, the state starts from it.
+        runtime.system.storage().flush().expect("state can be written");
+        let snapshot = Runtime::with_storage(database.state().expect("valid state"));
+        assert_eq!(snapshot.system.block_number(), 3);
+
+        std::fs::remove_dir_all(&dir).expect("database exists");
+    }
+}
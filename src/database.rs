@@ -0,0 +1,201 @@
+//! The database of a node, which lets it stop and resume from the last block it executed. It is a
+//! directory holding two files:
+//!
+//! - `blocks`, an append-only log of the executed blocks, in order. Each record is the length of
+//!   the encoded block as a little endian `u32`, the `BlakeTwo256` hash of the length followed by
+//!   the encoded block as a checksum, and the encoded block itself.
+//! - `state`, the latest snapshot of the state of the runtime, written by a `FileBackend` whenever
+//!   the storage is flushed.
+//!
+//! A snapshot is only taken every `SNAPSHOT_INTERVAL` blocks, so resuming loads the snapshot and
+//! replays the blocks of the log executed after it.
+
+use crate::{
+    support::{storage::FileBackend, BlakeTwo256, Decode, Encode, Hashing, Storage},
+    types,
+};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Write},
+    path::PathBuf,
+};
+
+/// The number of blocks between two snapshots of the state.
+pub const SNAPSHOT_INTERVAL: types::BlockNumber = 10;
+
+const BLOCKS_FILE: &str = "blocks";
+const STATE_FILE: &str = "state";
+
+/// The length of the header of a record of the block log: the length of the block, then its
+/// checksum.
+const RECORD_HEADER_LEN: usize = 4 + 32;
+
+/// An open database, ready to have blocks appended to it.
+#[derive(Debug)]
+pub struct Database {
+    dir: PathBuf,
+    /// The block log, opened for appending.
+    blocks: File,
+    /// The length of the complete records of the block log. Whatever follows them is a record cut
+    /// short, which must be dropped before appending.
+    len: u64,
+    /// Whether a record cut short follows the complete records.
+    torn: bool,
+}
+
+impl Database {
+    /// Open the database in `dir`, creating it if needed, and read back the blocks of its log.
+    ///
+    /// A record cut short at the end of the log is what a crash in the middle of an append leaves
+    /// behind, so it is left out of the blocks. It is only removed from the file by
+    /// `drop_torn_record`, once the caller has accepted the blocks read back. Any other damage is
+    /// reported as `InvalidData`.
+    pub fn open(dir: impl Into<PathBuf>) -> io::Result<(Self, Vec<types::Block>)> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        let mut blocks =
+            OpenOptions::new().read(true).append(true).create(true).open(dir.join(BLOCKS_FILE))?;
+        let mut bytes = Vec::new();
+        blocks.read_to_end(&mut bytes)?;
+        let (decoded, len) = read_blocks(&bytes)?;
+        let torn = len < bytes.len();
+        Ok((Self { dir, blocks, len: len as u64, torn }, decoded))
+    }
+
+    /// Remove the record cut short at the end of the block log, if any, so blocks can be appended
+    /// again. A damaged length can make a record look cut short, so this is only to be called
+    /// once the blocks read back agree with the state snapshot.
+    pub fn drop_torn_record(&mut self) -> io::Result<()> {
+        if self.torn {
+            self.blocks.set_len(self.len)?;
+            self.torn = false;
+        }
+        Ok(())
+    }
+
+    /// Whether a snapshot of the state was taken, which is not the case of a new database.
+    pub fn has_state(&self) -> bool {
+        self.dir.join(STATE_FILE).exists()
+    }
+
+    /// The storage of the runtime, starting from the latest snapshot. Flushing the storage takes a
+    /// new snapshot. The snapshot should be checked with `Storage::check` once the runtime is
+    /// created over it.
+    pub fn state(&self) -> io::Result<Storage> {
+        Ok(Storage::new(FileBackend::open(self.dir.join(STATE_FILE))?))
+    }
+
+    /// Append `block` to the log, once it has been executed. The block is synced to disk before
+    /// returning, so the log never falls behind a snapshot taken after it.
+    pub fn append_block(&mut self, block: &types::Block) -> io::Result<()> {
+        if self.torn {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "torn record in the block log"))
+        }
+        let encoded = block.encode();
+        let len = u32::try_from(encoded.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "block too large"))?;
+        let mut record = Vec::with_capacity(RECORD_HEADER_LEN + encoded.len());
+        len.encode_to(&mut record);
+        record.extend_from_slice(checksum(&record, &encoded).as_ref());
+        record.extend_from_slice(&encoded);
+        self.blocks.write_all(&record)?;
+        self.blocks.sync_data()?;
+        self.len += record.len() as u64;
+        Ok(())
+    }
+}
+
+/// The checksum of a record of the block log, which covers both the encoded length of the block
+/// and the encoded block.
+fn checksum(len: &[u8], encoded: &[u8]) -> <BlakeTwo256 as Hashing>::Output {
+    let mut checked = Vec::with_capacity(len.len() + encoded.len());
+    checked.extend_from_slice(len);
+    checked.extend_from_slice(encoded);
+    BlakeTwo256::hash(&checked)
+}
+
+/// Decode the records of the block log in `bytes`. Returns the blocks along with the length of the
+/// complete records, which is less than the length of `bytes` if the last record was cut short.
+fn read_blocks(bytes: &[u8]) -> io::Result<(Vec<types::Block>, usize)> {
+    let mut blocks = Vec::new();
+    let mut offset = 0;
+    while offset < bytes.len() {
+        let record = &bytes[offset..];
+        if record.len() < RECORD_HEADER_LEN {
+            break
+        }
+        let len = u32::decode(&mut &record[..4]).expect("four bytes decode as u32") as usize;
+        let Some(encoded) = record.get(RECORD_HEADER_LEN..RECORD_HEADER_LEN + len) else { break };
+        let number = blocks.len() + 1;
+        let invalid = |reason: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid block {number} in the block log: {reason}"),
+            )
+        };
+        if checksum(&record[..4], encoded).as_ref() != &record[4..RECORD_HEADER_LEN] {
+            return Err(invalid("checksum mismatch"))
+        }
+        blocks.push(types::Block::decode_all(encoded).map_err(|e| invalid(e.0))?);
+        offset += RECORD_HEADER_LEN + len;
+    }
+    Ok((blocks, offset))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Database, BLOCKS_FILE};
+    use crate::{support::Encode, Runtime};
+
+    #[test]
+    fn block_log_survives_torn_writes_only() {
+        let dir = std::env::temp_dir()
+            .join(format!("rust-state-machine-{}-block-log", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let mut runtime = Runtime::new();
+        let (mut database, blocks) = Database::open(&dir).expect("new database");
+        assert!(blocks.is_empty());
+        assert!(!database.has_state());
+        let mut encoded = Vec::new();
+        for _ in 0..2 {
+            let block = runtime.build_block(Vec::new()).expect("empty block");
+            runtime.execute_block(block.clone()).expect("valid block");
+            database.append_block(&block).expect("log can be written");
+            encoded.push(block.encode());
+        }
+        drop(database);
+        let reopen = || Database::open(&dir).map(|(_, blocks)| blocks);
+        let reopened = reopen().expect("valid log");
+        assert_eq!(reopened.iter().map(Encode::encode).collect::<Vec<_>>(), encoded);
+
+        // A record cut short by a crash is left out of the blocks read back.
+        let path = dir.join(BLOCKS_FILE);
+        let mut bytes = std::fs::read(&path).expect("log exists");
+        let len = bytes.len();
+        bytes.extend_from_slice(&[7, 0, 0, 0, 1, 2]);
+        std::fs::write(&path, &bytes).expect("log can be written");
+        let (mut database, blocks) = Database::open(&dir).expect("valid log");
+        assert_eq!(blocks.len(), 2);
+        // It is only removed from the file once the caller accepts the blocks.
+        assert_eq!(std::fs::metadata(&path).expect("log exists").len(), bytes.len() as u64);
+        assert!(database.append_block(&runtime.build_block(Vec::new()).unwrap()).is_err());
+        database.drop_torn_record().expect("log can be truncated");
+        assert_eq!(std::fs::metadata(&path).expect("log exists").len(), len as u64);
+        drop(database);
+
+        // A record damaged on disk fails its checksum, which also covers its length.
+        bytes.truncate(len);
+        bytes[len - 1] ^= 1;
+        std::fs::write(&path, &bytes).expect("log can be written");
+        let error = reopen().expect_err("damaged log");
+        assert_eq!(error.to_string(), "invalid block 2 in the block log: checksum mismatch");
+        bytes[len - 1] ^= 1;
+        bytes[0] ^= 1;
+        std::fs::write(&path, &bytes).expect("log can be written");
+        let error = reopen().expect_err("damaged length");
+        assert_eq!(error.to_string(), "invalid block 1 in the block log: checksum mismatch");
+
+        std::fs::remove_dir_all(&dir).expect("database exists");
+    }
+}
@@ -1,18 +1,30 @@
 mod balances;
 mod support;
 mod system;
-use crate::support::Dispatch;
 mod proof_of_existence;
+mod sudo;
+mod utility;
+mod multisig;
+mod proxy;
+mod vesting;
+mod staking;
+mod cli;
+mod database;
+#[cfg(test)]
+mod mock;
 
 
 mod types {
-    pub type AccountId = String;
+    pub type AccountId = crate::support::ed25519::Public;
+    pub type Signature = crate::support::ed25519::Signature;
     pub type Balance = u128;
     pub type BlockNumber = u32;
     pub type Nonce = u32;
-    pub type Extrinsic = crate::support::Extrinsic<AccountId, crate::RuntimeCall>;
-    pub type Header = crate::support::Header<BlockNumber>;
+    pub type Extrinsic = crate::support::Extrinsic<AccountId, crate::RuntimeCall, Nonce, Signature>;
+    pub type Hash = crate::support::H256;
+    pub type Header = crate::support::Header<BlockNumber, Hash>;
     pub type Block = crate::support::Block<Header, Extrinsic>;
+    pub type BlockReceipt = crate::support::BlockReceipt<BlockNumber, AccountId, Nonce>;
     pub type Content = String;
 }
 
@@ -22,6 +34,12 @@ pub struct Runtime {
    system: system::Pallet<Self>,
    balances: balances::Pallet<Self>,
    proof_of_existence: proof_of_existence::Pallet<Self>,
+   sudo: sudo::Pallet<Self>,
+   utility: utility::Pallet<Self>,
+   multisig: multisig::Pallet<Self>,
+   proxy: proxy::Pallet<Self>,
+   vesting: vesting::Pallet<Self>,
+   staking: staking::Pallet<Self>,
 }
 
 
@@ -29,87 +47,817 @@ impl system::Config for Runtime {
     type AccountId = types::AccountId;
     type BlockNumber = types::BlockNumber;
     type Nonce = types::Nonce;
+    type RuntimeEvent = RuntimeEvent;
+    type RuntimeOrigin = support::Origin<types::AccountId>;
+    type Hashing = support::BlakeTwo256;
+    type Signature = types::Signature;
+    const MAX_BLOCK_WEIGHT: support::Weight = 100_000;
 }
 
 impl balances::Config for Runtime {
     type Balance = types::Balance;
+    const EXISTENTIAL_DEPOSIT: types::Balance = 5;
+
+    // One unit for every 1_000 of weight, so a simple call costs 10.
+    fn weight_to_fee(weight: support::Weight) -> types::Balance {
+        weight as types::Balance / 1_000
+    }
+
+    fn treasury_account() -> Option<types::AccountId> {
+        Some(dev_account("treasury").public())
+    }
 }
 
 impl proof_of_existence::Config for Runtime {
     type Content = types::Content;
+    type Currency = balances::Pallet<Self>;
+    const CLAIM_DEPOSIT: types::Balance = 20;
+}
+
+impl sudo::Config for Runtime {
+    type RuntimeCall = RuntimeCall;
+}
+
+impl utility::Config for Runtime {
+    type RuntimeCall = RuntimeCall;
 }
 
+impl multisig::Config for Runtime {
+    type RuntimeCall = RuntimeCall;
+    type Currency = balances::Pallet<Self>;
+    const DEPOSIT: types::Balance = 20;
+    const MAX_SIGNATORIES: u32 = 16;
+
+    fn account_from_hash(hash: types::Hash) -> types::AccountId {
+        support::ed25519::Public(hash.0)
+    }
+}
+
+/// The types of proxies of the runtime, each allowing a subset of the calls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProxyType {
+    /// Any call.
+    Any,
+    /// Transfers of balance only.
+    Transfer,
+    /// Calls to `proof_of_existence` only.
+    ProofOfExistence,
+}
+
+impl support::InstanceFilter<RuntimeCall> for ProxyType {
+    fn filter(&self, call: &RuntimeCall) -> bool {
+        match self {
+            ProxyType::Any => true,
+            ProxyType::Transfer => matches!(
+                call,
+                RuntimeCall::balances(
+                    balances::Call::transfer { .. } |
+                        balances::Call::transfer_allow_death { .. } |
+                        balances::Call::transfer_keep_alive { .. }
+                )
+            ),
+            ProxyType::ProofOfExistence => matches!(call, RuntimeCall::proof_of_existence(..)),
+        }
+    }
+}
+
+impl support::Encode for ProxyType {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        (*self as u8).encode_to(dest);
+    }
+}
+
+impl support::Decode for ProxyType {
+    fn decode(input: &mut &[u8]) -> Result<Self, support::CodecError> {
+        match u8::decode(input)? {
+            0 => Ok(ProxyType::Any),
+            1 => Ok(ProxyType::Transfer),
+            2 => Ok(ProxyType::ProofOfExistence),
+            _ => Err(support::CodecError("invalid proxy type")),
+        }
+    }
+}
+
+impl proxy::Config for Runtime {
+    type RuntimeCall = RuntimeCall;
+    type ProxyType = ProxyType;
+    type Currency = balances::Pallet<Self>;
+    const PROXY_DEPOSIT: types::Balance = 20;
+    const MAX_PROXIES: u32 = 8;
+}
+
+impl vesting::Config for Runtime {
+    type Currency = balances::Pallet<Self>;
+    const MIN_VESTED_TRANSFER: types::Balance = 100;
+    const MAX_VESTING_SCHEDULES: u32 = 8;
+
+    fn block_number_to_balance(block_number: types::BlockNumber) -> types::Balance {
+        block_number.into()
+    }
+}
+
+impl staking::Config for Runtime {
+    type Currency = balances::Pallet<Self>;
+    const BLOCKS_PER_ERA: types::BlockNumber = 10;
+    const BONDING_DURATION: types::BlockNumber = 20;
+    const ERA_REWARD: types::Balance = 100;
+    const MAX_UNLOCKING_CHUNKS: u32 = 8;
+}
 
+// Fees are paid with the free balance of the caller in `balances`.
+impl support::ChargeTransaction for Runtime {
+    type Caller = types::AccountId;
+    type Fee = types::Balance;
+
+    fn withdraw_fee(
+        &mut self,
+        who: &types::AccountId,
+        weight: support::Weight,
+    ) -> Result<types::Balance, support::DispatchError> {
+        self.balances.withdraw_fee(who, weight)
+    }
+
+    fn correct_and_deposit_fee(
+        &mut self,
+        who: &types::AccountId,
+        fee: types::Balance,
+        actual_weight: support::Weight,
+    ) {
+        self.balances.correct_and_deposit_fee(who, fee, actual_weight)
+    }
+}
+
+/// The key pair of a development account, derived from its name.
+fn dev_account(name: &str) -> support::ed25519::Pair {
+    use support::Hashing;
+    support::ed25519::Pair::from_seed(&support::BlakeTwo256::hash(name.as_bytes()).0)
+}
+
+/// Sign a call on behalf of `signer`, which must be at the given nonce when it is applied.
+fn sign(
+    runtime: &Runtime,
+    signer: &support::ed25519::Pair,
+    nonce: types::Nonce,
+    call: RuntimeCall,
+) -> types::Extrinsic {
+    let payload = support::signing_payload(&call, &nonce, &runtime.system.genesis_hash());
+    support::Extrinsic { caller: signer.public(), nonce, signature: signer.sign(&payload), call }
+}
+
+/// Print the outcome of every extrinsic of a block.
+fn print_receipt(receipt: &types::BlockReceipt) {
+    println!("Bloco number {}", receipt.block_number);
+    for (i, extrinsic) in receipt.extrinsics.iter().enumerate() {
+        let result = match extrinsic.result {
+            Ok(()) => "Ok".to_string(),
+            Err(e) => format!("Erro {e}"),
+        };
+        println!(
+            "Extrinsic {} ({}::{} por {:?} com nonce {} e peso {}): {}",
+            i,
+            extrinsic.pallet,
+            extrinsic.call,
+            extrinsic.caller,
+            extrinsic.nonce,
+            extrinsic.weight,
+            result
+        );
+    }
+}
+
+/// Print the events emitted during the last executed block.
+fn print_events(runtime: &Runtime) {
+    for record in runtime.system.events() {
+        println!("Evento {:?}: {:?}", record.phase, record.event);
+    }
+}
 
 fn main() {
-    let mut runtime = Runtime::new();
-    let alice = "alice".to_string();
-    let bob = "bob".to_string();
-    let charlie = "charlie".to_string();
-
-    // Genesis
-    runtime.balances.set_balance(&alice, 100);
-
-    // Começa a produção de blocos
-    let block_1 = types::Block {
-        header: support::Header { block_number: 1 },
-        extrinsics: vec![
-            support::Extrinsic {
-                caller: "alice".to_string(),
-                call: RuntimeCall::balances(balances::Call::transfer { to: bob, amount: 30 }),
-            },
-            support::Extrinsic {
-                caller: "alice".to_string(),
-                call: RuntimeCall::balances(balances::Call::transfer { to: charlie, amount: 20 }),
-            },
-        ],
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let command = match cli::Command::parse(&args) {
+        Ok(command) => command,
+        Err(error) => {
+            eprintln!("{error}\n{}", cli::USAGE);
+            std::process::exit(2)
+        },
     };
-    runtime.execute_block(block_1).expect("invalid block");
-    println!("Bloco number {}", runtime.system.block_number());
-    println!("{runtime:#?}");
-
+    if let Err(error) = cli::run(command) {
+        eprintln!("{error}");
+        std::process::exit(1)
+    }
+}
 
-    let block_2 = types::Block {
-        header: support::Header { block_number: 2 },
-        extrinsics: vec![
-            support::Extrinsic {
-                caller: "alice".to_string(),
-                call: RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim { claim: "hola".to_string() }),
-            },
-        ],
+#[cfg(test)]
+mod tests {
+    use crate::{
+        balances, dev_account, multisig, proof_of_existence, proxy, sign, sudo, support, utility,
+        staking, system, vesting, ProxyType, Runtime, RuntimeCall, RuntimeEvent,
+        RuntimeGenesisConfig,
     };
-    runtime.execute_block(block_2).expect("invalid block");
-    println!("Bloco number {}", runtime.system.block_number());
-    println!("{runtime:#?}");
 
+    #[test]
+    fn execute_block_returns_receipts() {
+        let mut runtime = Runtime::new();
+        let alice = dev_account("alice");
+        runtime.balances.set_balance(&alice.public(), 100);
 
+        let block = runtime
+            .build_block(vec![
+                sign(
+                    &runtime,
+                    &alice,
+                    0,
+                    RuntimeCall::balances(balances::Call::transfer_allow_death {
+                        to: dev_account("bob").public(),
+                        amount: 200,
+                    }),
+                ),
+                sign(
+                    &runtime,
+                    &alice,
+                    1,
+                    RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+                        claim: "hola".to_string(),
+                    }),
+                ),
+            ])
+            .expect("valid extrinsics");
+        let receipt = runtime.execute_block(block).expect("valid block");
 
-    let block_3 = types::Block {
-        header: support::Header { block_number: 3 },
-        extrinsics: vec![
-            support::Extrinsic {
-                caller: "alice".to_string(),
-                call: RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim { claim: "hola".to_string() }),
-            },
-        ],
-    };
-    runtime.execute_block(block_3).expect("invalid block");
-    println!("Bloco number {}", runtime.system.block_number());
-    println!("{runtime:#?}");
+        assert_eq!(receipt.block_number, 1);
+        let outcomes = receipt
+            .extrinsics
+            .iter()
+            .map(|r| (r.caller, r.pallet, r.call, r.nonce, r.weight, r.result))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            outcomes,
+            vec![
+                (
+                    alice.public(),
+                    "balances",
+                    "transfer_allow_death",
+                    0,
+                    10_000,
+                    Err(support::DispatchError::Module(support::ModuleError {
+                        index: Some(1),
+                        error: 0,
+                        message: "InsufficientBalance",
+                    })),
+                ),
+                (alice.public(), "proof_of_existence", "create_claim", 1, 10_000, Ok(())),
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_blocks_are_rejected() {
+        use crate::support::{DispatchError, Hashing, H256};
+        let mut runtime = Runtime::new();
+        let alice = dev_account("alice");
+        runtime.balances.set_balance(&alice.public(), 100);
+        let transfer = sign(
+            &runtime,
+            &alice,
+            0,
+            RuntimeCall::balances(balances::Call::transfer_allow_death {
+                to: dev_account("bob").public(),
+                amount: 10,
+            }),
+        );
+
+        // A block whose state root does not match is rolled back after execution.
+        let mut block = runtime.build_block(vec![transfer.clone()]).expect("valid extrinsics");
+        block.header.state_root = H256::default();
+        assert_eq!(
+            runtime.execute_block(block).unwrap_err(),
+            DispatchError::Other("state root does not match the state after execution")
+        );
+        assert_eq!(runtime.balances.balance(&alice.public()), 100);
+        assert_eq!(runtime.system.block_number(), 0);
+
+        let mut block = runtime.build_block(vec![transfer.clone()]).expect("valid extrinsics");
+        block.header.parent_hash = H256([1; 32]);
+        assert_eq!(
+            runtime.execute_block(block).unwrap_err(),
+            DispatchError::Other("parent hash does not match the last block")
+        );
+
+        let mut block = runtime.build_block(vec![transfer.clone()]).expect("valid extrinsics");
+        block.extrinsics.push(transfer.clone());
+        assert_eq!(
+            runtime.execute_block(block).unwrap_err(),
+            DispatchError::Other("extrinsics root does not match the extrinsics of the block")
+        );
 
+        // A valid block becomes the parent of the next one.
+        let block = runtime.build_block(vec![transfer.clone()]).expect("valid extrinsics");
+        let hash = support::BlakeTwo256::hash_of(&block.header);
+        runtime.execute_block(block).expect("valid block");
+        assert_eq!(runtime.balances.balance(&alice.public()), 80);
+        assert_eq!(runtime.system.parent_hash(), hash);
+        assert_eq!(runtime.build_block(vec![]).expect("valid extrinsics").header.parent_hash, hash);
+    }
 
-    let block_4 = types::Block {
-        header: support::Header { block_number: 4 },
-        extrinsics: vec![
-            support::Extrinsic {
-                caller: "alice".to_string(),
-                call: RuntimeCall::proof_of_existence(proof_of_existence::Call::revoke_claim { claim: "hola".to_string() }),
+    #[test]
+    fn extrinsics_must_be_signed_by_the_caller() {
+        use crate::support::{DispatchError, ModuleError};
+        let mut runtime = Runtime::new();
+        let alice = dev_account("alice");
+        let mallory = dev_account("mallory");
+        runtime.balances.set_balance(&alice.public(), 100);
+        let bad_signature = DispatchError::Module(ModuleError {
+            index: Some(0),
+            error: 0,
+            message: "BadSignature",
+        });
+
+        // Mallory signs a transfer out of the account of alice.
+        let call = RuntimeCall::balances(balances::Call::transfer_allow_death {
+            to: mallory.public(),
+            amount: 100,
+        });
+        let mut forged = sign(&runtime, &mallory, 0, call.clone());
+        forged.caller = alice.public();
+        assert_eq!(runtime.build_block(vec![forged.clone()]).unwrap_err(), bad_signature);
+
+        // The signature covers the call, so it cannot be reused for another one.
+        let mut tampered = sign(&runtime, &alice, 0, call);
+        tampered.call = RuntimeCall::balances(balances::Call::transfer_allow_death {
+            to: mallory.public(),
+            amount: 1,
+        });
+        assert_eq!(runtime.build_block(vec![tampered]).unwrap_err(), bad_signature);
+
+        // A block built by someone else with a forged extrinsic is rejected as a whole.
+        let mut block = runtime.build_block(vec![]).expect("valid extrinsics");
+        block.extrinsics.push(forged);
+        block.header.extrinsics_root = Runtime::extrinsics_root(&block.extrinsics);
+        assert_eq!(runtime.execute_block(block).unwrap_err(), bad_signature);
+        assert_eq!(runtime.balances.balance(&alice.public()), 100);
+        assert_eq!(runtime.system.block_number(), 0);
+    }
+
+    #[test]
+    fn extrinsics_cannot_be_replayed() {
+        use crate::support::{DispatchError, ModuleError};
+        let mut runtime = Runtime::new();
+        let alice = dev_account("alice");
+        runtime.balances.set_balance(&alice.public(), 100);
+        let stale_nonce = DispatchError::Module(ModuleError {
+            index: Some(0),
+            error: 1,
+            message: "StaleNonce",
+        });
+        let future_nonce = DispatchError::Module(ModuleError {
+            index: Some(0),
+            error: 2,
+            message: "FutureNonce",
+        });
+        let transfer = sign(
+            &runtime,
+            &alice,
+            0,
+            RuntimeCall::balances(balances::Call::transfer_allow_death {
+                to: dev_account("bob").public(),
+                amount: 10,
+            }),
+        );
+
+        // The same extrinsic twice in a block.
+        assert_eq!(
+            runtime.build_block(vec![transfer.clone(), transfer.clone()]).unwrap_err(),
+            stale_nonce
+        );
+
+        // An extrinsic which skips a nonce cannot be applied until the missing one is.
+        let next = sign(
+            &runtime,
+            &alice,
+            1,
+            RuntimeCall::balances(balances::Call::transfer_allow_death {
+                to: dev_account("bob").public(),
+                amount: 5,
+            }),
+        );
+        assert_eq!(runtime.build_block(vec![next.clone()]).unwrap_err(), future_nonce);
+
+        // The same extrinsic in a later block.
+        let block = runtime.build_block(vec![transfer.clone()]).expect("valid extrinsics");
+        runtime.execute_block(block).expect("valid block");
+        assert_eq!(runtime.build_block(vec![transfer]).unwrap_err(), stale_nonce);
+        assert_eq!(runtime.system.account_nonce(&alice.public()), 1);
+        let block = runtime.build_block(vec![next]).expect("valid extrinsics");
+        runtime.execute_block(block).expect("valid block");
+        assert_eq!(runtime.balances.balance(&alice.public()), 65);
+    }
+
+    #[test]
+    fn callers_pay_for_the_weight_of_their_calls() {
+        use crate::support::{DispatchError, ModuleError};
+        let mut runtime = Runtime::new();
+        let alice = dev_account("alice");
+        let bob = dev_account("bob");
+        let treasury = dev_account("treasury").public();
+        runtime.balances.set_balance(&alice.public(), 100);
+        runtime.balances.set_balance(&bob.public(), 5);
+        let transfer = || {
+            RuntimeCall::balances(balances::Call::transfer_allow_death {
+                to: dev_account("charlie").public(),
+                amount: 10,
+            })
+        };
+        let overdraft = RuntimeCall::balances(balances::Call::transfer_allow_death {
+            to: bob.public(),
+            amount: 1_000,
+        });
+
+        // The fee is paid even if the call fails, and goes to the treasury.
+        let block = runtime
+            .build_block(vec![
+                sign(&runtime, &alice, 0, transfer()),
+                sign(&runtime, &alice, 1, overdraft),
+            ])
+            .expect("valid extrinsics");
+        runtime.execute_block(block).expect("valid block");
+        assert_eq!(runtime.balances.balance(&alice.public()), 70);
+        assert_eq!(runtime.balances.balance(&treasury), 20);
+        assert_eq!(runtime.system.block_weight(), 20_000);
+
+        // An extrinsic whose caller cannot pay invalidates the block.
+        assert_eq!(
+            runtime.build_block(vec![sign(&runtime, &bob, 0, transfer())]).unwrap_err(),
+            DispatchError::Module(ModuleError { index: Some(0), error: 4, message: "Payment" })
+        );
+
+        // A block cannot use more than the maximum block weight, which fits 10 transfers.
+        runtime.balances.set_balance(&alice.public(), 1_000);
+        let extrinsics =
+            (2..13).map(|nonce| sign(&runtime, &alice, nonce, transfer())).collect();
+        assert_eq!(
+            runtime.build_block(extrinsics).unwrap_err(),
+            DispatchError::Module(ModuleError {
+                index: Some(0),
+                error: 3,
+                message: "ExhaustsResources",
+            })
+        );
+        let extrinsics =
+            (2..12).map(|nonce| sign(&runtime, &alice, nonce, transfer())).collect();
+        let block = runtime.build_block(extrinsics).expect("valid extrinsics");
+        runtime.execute_block(block).expect("valid block");
+        assert_eq!(runtime.system.block_weight(), 100_000);
+        assert!(runtime.balances.check_total_issuance());
+    }
+
+    #[test]
+    fn reaped_accounts_start_over() {
+        let mut runtime = Runtime::new();
+        let alice = dev_account("alice");
+        let bob = dev_account("bob").public();
+        runtime.balances.set_balance(&alice.public(), 100);
+
+        // After paying the fee, alice sends all but 3, which is below the existential deposit.
+        let transfer = sign(
+            &runtime,
+            &alice,
+            0,
+            RuntimeCall::balances(balances::Call::transfer_allow_death { to: bob, amount: 87 }),
+        );
+        let block = runtime.build_block(vec![transfer]).expect("valid extrinsics");
+        runtime.execute_block(block).expect("valid block");
+        assert_eq!(runtime.balances.balance(&alice.public()), 0);
+        assert_eq!(runtime.balances.balance(&bob), 87);
+        assert_eq!(runtime.system.account_nonce(&alice.public()), 0);
+        assert!(runtime.balances.check_total_issuance());
+    }
+
+    #[test]
+    fn sudo_key_makes_root_calls() {
+        let mut runtime = Runtime::new();
+        let alice = dev_account("alice");
+        let bob = dev_account("bob").public();
+        runtime.balances.set_balance(&alice.public(), 100);
+        runtime.sudo.initialize_key(alice.public());
+
+        let mint = RuntimeCall::balances(balances::Call::mint { who: bob, amount: 50 });
+        let sudo = RuntimeCall::sudo(sudo::Call::sudo { call: Box::new(mint.clone()) });
+        let block = runtime
+            .build_block(vec![sign(&runtime, &alice, 0, mint), sign(&runtime, &alice, 1, sudo)])
+            .expect("valid extrinsics");
+        let receipt = runtime.execute_block(block).expect("valid block");
+
+        // Only the call made through sudo has root privileges.
+        let bad_origin = support::DispatchError::from(crate::system::Error::BadOrigin).in_pallet(0);
+        assert_eq!(receipt.extrinsics[0].result, Err(bad_origin));
+        assert_eq!(receipt.extrinsics[1].result, Ok(()));
+        assert_eq!(receipt.extrinsics[1].weight, 20_000);
+        assert_eq!(runtime.balances.balance(&bob), 50);
+        assert!(runtime.balances.check_total_issuance());
+    }
+
+    #[test]
+    fn failed_batch_all_is_rolled_back() {
+        let mut runtime = Runtime::new();
+        let alice = dev_account("alice");
+        let bob = dev_account("bob").public();
+        runtime.balances.set_balance(&alice.public(), 100);
+
+        let transfer = |amount| {
+            RuntimeCall::balances(balances::Call::transfer_allow_death { to: bob, amount })
+        };
+        let batch = RuntimeCall::utility(utility::Call::batch_all {
+            calls: vec![transfer(20), transfer(20), transfer(100)],
+        });
+        let block =
+            runtime.build_block(vec![sign(&runtime, &alice, 0, batch)]).expect("valid extrinsics");
+        let receipt = runtime.execute_block(block).expect("valid block");
+
+        assert!(receipt.extrinsics[0].result.is_err());
+        assert_eq!(receipt.extrinsics[0].weight, 33_000);
+        assert_eq!(runtime.balances.balance(&alice.public()), 67);
+        assert_eq!(runtime.balances.balance(&bob), 0);
+    }
+
+    #[test]
+    fn multisig_accounts_dispatch_approved_calls() {
+        let mut runtime = Runtime::new();
+        let alice = dev_account("alice");
+        let bob = dev_account("bob");
+        let charlie = dev_account("charlie").public();
+        let mut signatories = [alice.public(), bob.public()];
+        signatories.sort();
+        let multisig_account = multisig::Pallet::<Runtime>::multi_account_id(&signatories, 2);
+        for who in [alice.public(), bob.public(), multisig_account] {
+            runtime.balances.set_balance(&who, 100);
+        }
+
+        let transfer = Box::new(RuntimeCall::balances(balances::Call::transfer_allow_death {
+            to: charlie,
+            amount: 50,
+        }));
+        let as_multi = |other: &support::ed25519::Pair| {
+            RuntimeCall::multisig(multisig::Call::as_multi {
+                threshold: 2,
+                other_signatories: vec![other.public()],
+                call: transfer.clone(),
+            })
+        };
+        let block = runtime
+            .build_block(vec![
+                sign(&runtime, &alice, 0, as_multi(&bob)),
+                sign(&runtime, &bob, 0, as_multi(&alice)),
+            ])
+            .expect("valid extrinsics");
+        let receipt = runtime.execute_block(block).expect("valid block");
+
+        assert!(receipt.extrinsics.iter().all(|extrinsic| extrinsic.result.is_ok()));
+        assert_eq!(runtime.balances.balance(&charlie), 50);
+        assert_eq!(runtime.balances.balance(&multisig_account), 50);
+        assert_eq!(runtime.balances.reserved_balance(&alice.public()), 0);
+    }
+
+    #[test]
+    fn proxies_only_dispatch_the_calls_of_their_type() {
+        let mut runtime = Runtime::new();
+        let alice = dev_account("alice");
+        let bob = dev_account("bob");
+        let charlie = dev_account("charlie").public();
+        runtime.balances.set_balance(&alice.public(), 100);
+        runtime.balances.set_balance(&bob.public(), 100);
+
+        let proxy = |call: RuntimeCall| {
+            RuntimeCall::proxy(proxy::Call::proxy { real: alice.public(), call: Box::new(call) })
+        };
+        let block = runtime
+            .build_block(vec![
+                sign(
+                    &runtime,
+                    &alice,
+                    0,
+                    RuntimeCall::proxy(proxy::Call::add_proxy {
+                        delegate: bob.public(),
+                        proxy_type: ProxyType::Transfer,
+                    }),
+                ),
+                sign(
+                    &runtime,
+                    &bob,
+                    0,
+                    proxy(RuntimeCall::balances(balances::Call::transfer_allow_death {
+                        to: charlie,
+                        amount: 30,
+                    })),
+                ),
+                sign(
+                    &runtime,
+                    &bob,
+                    1,
+                    proxy(RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+                        claim: "hola".to_string(),
+                    })),
+                ),
+            ])
+            .expect("valid extrinsics");
+        let receipt = runtime.execute_block(block).expect("valid block");
+
+        assert!(receipt.extrinsics[1].result.is_ok());
+        assert_eq!(
+            receipt.extrinsics[2].result,
+            Err(support::DispatchError::from(proxy::Error::Unproxyable).in_pallet(6))
+        );
+        assert_eq!(runtime.balances.balance(&charlie), 30);
+        assert_eq!(runtime.balances.reserved_balance(&alice.public()), 20);
+        assert_eq!(runtime.proof_of_existence.get_claim(&"hola".to_string()), None);
+    }
+
+    #[test]
+    fn vesting_funds_unlock_over_blocks() {
+        let mut runtime = Runtime::new();
+        let alice = dev_account("alice");
+        let bob = dev_account("bob").public();
+        runtime.balances.set_balance(&alice.public(), 1_000);
+        let schedule = vesting::VestingInfo { locked: 500, per_block: 10, starting_block: 0 };
+        vesting::Pallet::add_vesting_schedule(&mut runtime, &alice.public(), schedule)
+            .expect("valid vesting schedule");
+
+        let transfer = |amount| {
+            RuntimeCall::balances(balances::Call::transfer_allow_death { to: bob, amount })
+        };
+        let block =
+            runtime.build_block(vec![sign(&runtime, &alice, 0, transfer(500))]).expect("valid");
+        let receipt = runtime.execute_block(block).expect("valid block");
+        assert_eq!(
+            receipt.extrinsics[0].result,
+            Err(support::DispatchError::from(balances::Error::LiquidityRestrictions).in_pallet(1))
+        );
+
+        while runtime.system.block_number() < 10 {
+            let block = runtime.build_block(vec![]).expect("valid extrinsics");
+            runtime.execute_block(block).expect("valid block");
+        }
+        // By block 11, 110 have vested, which `vest` unlocks.
+        let block = runtime
+            .build_block(vec![
+                sign(&runtime, &alice, 1, RuntimeCall::vesting(vesting::Call::vest {})),
+                sign(&runtime, &alice, 2, transfer(500)),
+            ])
+            .expect("valid extrinsics");
+        let receipt = runtime.execute_block(block).expect("valid block");
+        assert!(receipt.extrinsics.iter().all(|extrinsic| extrinsic.result.is_ok()));
+        assert_eq!(runtime.balances.locked_balance(&alice.public()), 390);
+        assert_eq!(runtime.balances.balance(&bob), 500);
+    }
+
+    #[test]
+    fn bonded_funds_cannot_be_transferred() {
+        let mut runtime = Runtime::new();
+        let alice = dev_account("alice");
+        let bob = dev_account("bob").public();
+        runtime.balances.set_balance(&alice.public(), 100);
+
+        let block = runtime
+            .build_block(vec![
+                sign(&runtime, &alice, 0, RuntimeCall::staking(staking::Call::bond { value: 60 })),
+                sign(
+                    &runtime,
+                    &alice,
+                    1,
+                    RuntimeCall::balances(balances::Call::transfer_allow_death {
+                        to: bob,
+                        amount: 30,
+                    }),
+                ),
+            ])
+            .expect("valid extrinsics");
+        let receipt = runtime.execute_block(block).expect("valid block");
+
+        assert!(receipt.extrinsics[0].result.is_ok());
+        assert_eq!(
+            receipt.extrinsics[1].result,
+            Err(support::DispatchError::from(balances::Error::LiquidityRestrictions).in_pallet(1))
+        );
+        assert_eq!(runtime.balances.locked_balance(&alice.public()), 60);
+    }
+
+    #[test]
+    fn era_rewards_are_paid_by_block_hooks() {
+        let mut runtime = Runtime::new();
+        let alice = dev_account("alice");
+        runtime.balances.set_balance(&alice.public(), 100);
+
+        let bond = RuntimeCall::staking(staking::Call::bond { value: 60 });
+        let block = runtime.build_block(vec![sign(&runtime, &alice, 0, bond)]).expect("valid");
+        runtime.execute_block(block).expect("valid block");
+        while runtime.system.block_number() < 10 {
+            let block = runtime.build_block(vec![]).expect("valid extrinsics");
+            runtime.execute_block(block).expect("valid block");
+        }
+
+        // The only stash earns the whole reward, at the end of the last block of the era.
+        assert_eq!(runtime.balances.balance(&alice.public()), 190);
+        assert_eq!(runtime.staking.current_era(), 1);
+        assert!(runtime.system.events().any(|record| {
+            record.phase == system::Phase::Finalization &&
+                matches!(
+                    record.event,
+                    RuntimeEvent::staking(staking::Event::EraPaid { era: 0, total_reward: 100 })
+                )
+        }));
+    }
+
+    #[test]
+    fn from_genesis() {
+        let alice = dev_account("alice").public();
+        let bob = dev_account("bob").public();
+        let genesis = || RuntimeGenesisConfig {
+            balances: balances::GenesisConfig { balances: vec![(alice, 100), (bob, 50)] },
+            proof_of_existence: proof_of_existence::GenesisConfig {
+                claims: vec![("hola".to_string(), alice)],
             },
-        ],
-    };
-    runtime.execute_block(block_4).expect("invalid block");
-    println!("Bloco number {}", runtime.system.block_number());
-    println!("{runtime:#?}");
+            staking: staking::GenesisConfig { stakers: vec![(bob, 30)] },
+            ..Default::default()
+        };
 
-}
+        let runtime = Runtime::from_genesis(genesis()).expect("valid genesis");
+        assert_eq!(runtime.balances.total_issuance(), 150);
+        assert_eq!(runtime.balances.balance(&alice), 80);
+        assert_eq!(runtime.proof_of_existence.get_claim(&"hola".to_string()), Some(alice));
+        assert_eq!(runtime.balances.locked_balance(&bob), 30);
+        assert_eq!(runtime.system.block_number(), 0);
+        assert!(runtime.system.block_hash(&0).is_some_and(|hash| hash != Default::default()));
+        assert_eq!(runtime.system.parent_hash(), runtime.system.genesis_hash());
+
+        // An invalid configuration is reported along with the pallet it belongs to.
+        let mut config = genesis();
+        config.balances.balances.push((dev_account("charlie").public(), 1));
+        assert_eq!(
+            Runtime::from_genesis(config).err(),
+            Some(support::DispatchError::from(balances::Error::ExistentialDeposit).in_pallet(1))
+        );
+        let mut config = genesis();
+        config.proof_of_existence.claims.push(("hola".to_string(), bob));
+        assert_eq!(
+            Runtime::from_genesis(config).err(),
+            Some(
+                support::DispatchError::from(proof_of_existence::Error::AlreadyClaimed)
+                    .in_pallet(2)
+            )
+        );
+    }
+
+    #[test]
+    fn signatures_are_bound_to_the_genesis() {
+        let alice = dev_account("alice");
+        let genesis = |amount| RuntimeGenesisConfig {
+            balances: balances::GenesisConfig { balances: vec![(alice.public(), amount)] },
+            ..Default::default()
+        };
+        let mut runtime = Runtime::from_genesis(genesis(100)).expect("valid genesis");
+        let mut other = Runtime::from_genesis(genesis(200)).expect("valid genesis");
+        assert_ne!(runtime.system.genesis_hash(), other.system.genesis_hash());
 
+        // The same call with the same nonce is only valid on the chain it was signed for.
+        let call = RuntimeCall::balances(balances::Call::transfer_allow_death {
+            to: dev_account("bob").public(),
+            amount: 10,
+        });
+        let extrinsic = sign(&runtime, &alice, 0, call);
+        assert_eq!(
+            other.build_block(vec![extrinsic.clone()]).err(),
+            Some(support::DispatchError::from(system::Error::BadSignature).in_pallet(0))
+        );
+        let block = runtime.build_block(vec![extrinsic]).expect("valid extrinsics");
+        runtime.execute_block(block).expect("valid block");
+    }
+
+    #[test]
+    fn encode_block() {
+        use crate::{
+            support::{CodecError, Decode, Encode},
+            types,
+        };
+        let mut runtime = Runtime::new();
+        runtime.balances.set_balance(&dev_account("alice").public(), 100);
+        let call = RuntimeCall::proof_of_existence(proof_of_existence::Call::revoke_claim {
+            claim: "hola".to_string(),
+        });
+
+        // The pallet index, followed by the call index and the arguments.
+        let encoded = call.encode();
+        assert_eq!(encoded, [&[2, 1, 0x10][..], b"hola"].concat());
+        assert_eq!(RuntimeCall::decode_all(&encoded), Ok(call.clone()));
+        assert_eq!(RuntimeCall::decode_all(&[0]), Err(CodecError("invalid pallet index")));
+
+        let extrinsic = sign(&runtime, &dev_account("alice"), 0, call);
+        let block = runtime.build_block(vec![extrinsic]).expect("valid extrinsics");
+        let decoded = types::Block::decode_all(&block.encode()).expect("valid encoding");
+        assert_eq!(decoded.encode(), block.encode());
+        assert_eq!(decoded.extrinsics[0].call, block.extrinsics[0].call);
+        assert_eq!(decoded.extrinsics[0].signature, block.extrinsics[0].signature);
+
+        // A block which went through the codec can still be executed.
+        runtime.execute_block(decoded).expect("valid block");
+    }
+}
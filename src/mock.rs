@@ -0,0 +1,171 @@
+//! The runtime of the tests of the pallets. It holds every pallet of `Runtime`, but accounts are
+//! plain strings, and signatures are `support::TestSignature`.
+
+// Each test only uses part of the runtime.
+#![allow(dead_code)]
+
+use crate::{
+    balances, multisig, proof_of_existence, proxy, staking, sudo, support, system, utility, vesting,
+};
+
+mod types {
+    pub type AccountId = String;
+    pub type Balance = u128;
+    pub type BlockNumber = u32;
+    pub type Nonce = u32;
+    pub type Extrinsic = crate::support::Extrinsic<
+        AccountId,
+        super::RuntimeCall,
+        Nonce,
+        crate::support::TestSignature,
+    >;
+    pub type Hash = crate::support::H256;
+    pub type Header = crate::support::Header<BlockNumber, Hash>;
+    pub type Block = crate::support::Block<Header, Extrinsic>;
+    pub type BlockReceipt = crate::support::BlockReceipt<BlockNumber, AccountId, Nonce>;
+}
+
+#[derive(Debug)]
+#[macros::runtime]
+pub struct TestRuntime {
+    pub system: system::Pallet<Self>,
+    pub balances: balances::Pallet<Self>,
+    pub proof_of_existence: proof_of_existence::Pallet<Self>,
+    pub sudo: sudo::Pallet<Self>,
+    pub utility: utility::Pallet<Self>,
+    pub multisig: multisig::Pallet<Self>,
+    pub proxy: proxy::Pallet<Self>,
+    pub vesting: vesting::Pallet<Self>,
+    pub staking: staking::Pallet<Self>,
+}
+
+impl system::Config for TestRuntime {
+    type AccountId = types::AccountId;
+    type BlockNumber = types::BlockNumber;
+    type Nonce = types::Nonce;
+    type RuntimeEvent = RuntimeEvent;
+    type RuntimeOrigin = support::Origin<types::AccountId>;
+    type Hashing = support::BlakeTwo256;
+    type Signature = support::TestSignature;
+    const MAX_BLOCK_WEIGHT: support::Weight = 100_000;
+}
+
+impl balances::Config for TestRuntime {
+    type Balance = types::Balance;
+    const EXISTENTIAL_DEPOSIT: types::Balance = 5;
+
+    fn weight_to_fee(weight: support::Weight) -> types::Balance {
+        weight as types::Balance / 1_000
+    }
+
+    fn treasury_account() -> Option<types::AccountId> {
+        Some("treasury".to_string())
+    }
+}
+
+impl proof_of_existence::Config for TestRuntime {
+    type Content = String;
+    type Currency = balances::Pallet<Self>;
+    const CLAIM_DEPOSIT: types::Balance = 20;
+}
+
+impl sudo::Config for TestRuntime {
+    type RuntimeCall = RuntimeCall;
+}
+
+impl utility::Config for TestRuntime {
+    type RuntimeCall = RuntimeCall;
+}
+
+impl multisig::Config for TestRuntime {
+    type RuntimeCall = RuntimeCall;
+    type Currency = balances::Pallet<Self>;
+    const DEPOSIT: types::Balance = 20;
+    const MAX_SIGNATORIES: u32 = 3;
+
+    fn account_from_hash(hash: types::Hash) -> types::AccountId {
+        format!("multisig {:?}", hash)
+    }
+}
+
+/// The types of proxies of the test runtime.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TestProxyType {
+    Any,
+    Transfer,
+}
+
+impl support::InstanceFilter<RuntimeCall> for TestProxyType {
+    fn filter(&self, call: &RuntimeCall) -> bool {
+        match self {
+            TestProxyType::Any => true,
+            TestProxyType::Transfer =>
+                matches!(call, RuntimeCall::balances(balances::Call::transfer_allow_death { .. })),
+        }
+    }
+}
+
+impl support::Encode for TestProxyType {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        (*self as u8).encode_to(dest);
+    }
+}
+
+impl support::Decode for TestProxyType {
+    fn decode(input: &mut &[u8]) -> Result<Self, support::CodecError> {
+        match u8::decode(input)? {
+            0 => Ok(TestProxyType::Any),
+            1 => Ok(TestProxyType::Transfer),
+            _ => Err(support::CodecError("invalid proxy type")),
+        }
+    }
+}
+
+impl proxy::Config for TestRuntime {
+    type RuntimeCall = RuntimeCall;
+    type ProxyType = TestProxyType;
+    type Currency = balances::Pallet<Self>;
+    const PROXY_DEPOSIT: types::Balance = 10;
+    const MAX_PROXIES: u32 = 2;
+}
+
+impl vesting::Config for TestRuntime {
+    type Currency = balances::Pallet<Self>;
+    const MIN_VESTED_TRANSFER: types::Balance = 50;
+    const MAX_VESTING_SCHEDULES: u32 = 2;
+
+    fn block_number_to_balance(block_number: types::BlockNumber) -> types::Balance {
+        block_number.into()
+    }
+}
+
+impl staking::Config for TestRuntime {
+    type Currency = balances::Pallet<Self>;
+    const BLOCKS_PER_ERA: types::BlockNumber = 10;
+    const BONDING_DURATION: types::BlockNumber = 5;
+    const ERA_REWARD: types::Balance = 90;
+    const MAX_UNLOCKING_CHUNKS: u32 = 2;
+}
+
+// Fees are paid with the free balance of the caller in `balances`, as in `Runtime`.
+impl support::ChargeTransaction for TestRuntime {
+    type Caller = types::AccountId;
+    type Fee = types::Balance;
+
+    fn withdraw_fee(
+        &mut self,
+        who: &types::AccountId,
+        weight: support::Weight,
+    ) -> Result<types::Balance, support::DispatchError> {
+        self.balances.withdraw_fee(who, weight)
+    }
+
+    fn correct_and_deposit_fee(
+        &mut self,
+        who: &types::AccountId,
+        fee: types::Balance,
+        actual_weight: support::Weight,
+    ) {
+        self.balances.correct_and_deposit_fee(who, fee, actual_weight)
+    }
+}
@@ -0,0 +1,547 @@
+use core::fmt::Debug;
+use crate::{
+    support::{
+        CodecError, Currency, Decode, Dispatch, DispatchResult, DispatchResultWithPostInfo, Encode,
+        GetWeight, HasPallet, Hashing, Origin, PostDispatchInfo, ReservableCurrency, Storage,
+        StorageDoubleMap, Transactional, TransactionalVec, Weight,
+    },
+    system::HashOf,
+};
+
+/// The type of the deposit reserved for a pending operation.
+pub type BalanceOf<T> = <<T as Config>::Currency as Currency<
+    <T as crate::system::Config>::AccountId,
+    T,
+>>::Balance;
+
+pub trait Config: crate::system::Config + Sized {
+    /// The aggregated call type of the runtime, generated by `#[macros::runtime]`, which a
+    /// multisig account can dispatch.
+    type RuntimeCall: GetWeight + Clone + Debug + PartialEq + Encode + Decode;
+
+    /// The currency in which the deposits of pending operations are reserved.
+    type Currency: ReservableCurrency<Self::AccountId, Self>;
+
+    /// The deposit reserved from the account which starts an operation, until the operation is
+    /// executed or cancelled.
+    const DEPOSIT: BalanceOf<Self>;
+
+    /// The maximum number of signatories of a multisig account, including the caller.
+    const MAX_SIGNATORIES: u32;
+
+    /// Turn the hash of the signatories and threshold of a multisig account into its account id.
+    fn account_from_hash(hash: HashOf<Self>) -> Self::AccountId;
+}
+
+/// The weight of the calls of this pallet, on top of the weight of the call they dispatch.
+const MULTISIG_WEIGHT: Weight = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[macros::error]
+pub enum Error {
+    /// The threshold must be at least 2. A single signatory can make the call directly.
+    MinimumThreshold,
+    /// There must be other signatories than the caller.
+    TooFewSignatories,
+    /// There are more signatories than `Config::MAX_SIGNATORIES`.
+    TooManySignatories,
+    /// The other signatories must be sorted, without duplicates.
+    SignatoriesOutOfOrder,
+    /// The caller must not be part of the other signatories.
+    SenderInSignatories,
+    /// The caller already approved this operation.
+    AlreadyApproved,
+    /// There is no such pending operation.
+    NotFound,
+    /// Only the account which started the operation can cancel it.
+    NotOwner,
+}
+
+/// The events emitted by this pallet.
+#[derive(Debug)]
+pub enum Event<T: Config> {
+    /// `approving` started an operation of `multisig` on the call with the hash `call_hash`.
+    NewMultisig { approving: T::AccountId, multisig: T::AccountId, call_hash: HashOf<T> },
+    /// `approving` approved an operation of `multisig`, which needs more approvals.
+    MultisigApproval { approving: T::AccountId, multisig: T::AccountId, call_hash: HashOf<T> },
+    /// The last approval of `approving` executed an operation of `multisig`, with this result.
+    MultisigExecuted {
+        approving: T::AccountId,
+        multisig: T::AccountId,
+        call_hash: HashOf<T>,
+        result: DispatchResult,
+    },
+    /// `cancelling` cancelled an operation of `multisig`.
+    MultisigCancelled { cancelling: T::AccountId, multisig: T::AccountId, call_hash: HashOf<T> },
+}
+
+// Implemented by hand, since a derive would require `T` itself to implement `PartialEq`.
+impl<T: Config> PartialEq for Event<T> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (
+                Self::NewMultisig { approving: a0, multisig: a1, call_hash: a2 },
+                Self::NewMultisig { approving: b0, multisig: b1, call_hash: b2 },
+            ) => (a0, a1, a2) == (b0, b1, b2),
+            (
+                Self::MultisigApproval { approving: a0, multisig: a1, call_hash: a2 },
+                Self::MultisigApproval { approving: b0, multisig: b1, call_hash: b2 },
+            ) => (a0, a1, a2) == (b0, b1, b2),
+            (
+                Self::MultisigExecuted { approving: a0, multisig: a1, call_hash: a2, result: a3 },
+                Self::MultisigExecuted { approving: b0, multisig: b1, call_hash: b2, result: b3 },
+            ) => (a0, a1, a2, a3) == (b0, b1, b2, b3),
+            (
+                Self::MultisigCancelled { cancelling: a0, multisig: a1, call_hash: a2 },
+                Self::MultisigCancelled { cancelling: b0, multisig: b1, call_hash: b2 },
+            ) => (a0, a1, a2) == (b0, b1, b2),
+            _ => false,
+        }
+    }
+}
+
+/// An operation of a multisig account which is waiting for approvals.
+#[derive(Debug)]
+pub struct Multisig<T: Config> {
+    /// The account which started the operation, and reserved the deposit.
+    pub depositor: T::AccountId,
+    /// The deposit reserved from `depositor`.
+    pub deposit: BalanceOf<T>,
+    /// The signatories who approved the operation so far, sorted.
+    pub approvals: Vec<T::AccountId>,
+}
+
+// Implemented by hand, since a derive would require `T` itself to implement `Clone`.
+impl<T: Config> Clone for Multisig<T> {
+    fn clone(&self) -> Self {
+        Self {
+            depositor: self.depositor.clone(),
+            deposit: self.deposit,
+            approvals: self.approvals.clone(),
+        }
+    }
+}
+
+impl<T: Config> PartialEq for Multisig<T> {
+    fn eq(&self, other: &Self) -> bool {
+        (&self.depositor, &self.deposit, &self.approvals) ==
+            (&other.depositor, &other.deposit, &other.approvals)
+    }
+}
+
+impl<T: Config> Encode for Multisig<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        (&self.depositor, &self.deposit, &self.approvals).encode_to(dest);
+    }
+}
+
+impl<T: Config> Decode for Multisig<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        let (depositor, deposit, approvals) = Decode::decode(input)?;
+        Ok(Self { depositor, deposit, approvals })
+    }
+}
+
+/// This is the Multisig Module.
+/// It lets a set of signatories control an account together, each of its calls needing the
+/// approval of a threshold of them.
+#[derive(Debug)]
+pub struct Pallet<T: Config> {
+    /// The pending operations, by multisig account and hash of the call they will dispatch.
+    multisigs: StorageDoubleMap<T::AccountId, HashOf<T>, Multisig<T>>,
+    /// The events emitted by this pallet which have not been collected by the runtime yet.
+    events: TransactionalVec<Event<T>>,
+}
+
+impl<T: Config> Pallet<T> {
+    /// Create a new instance of the Multisig Module.
+    pub fn new(storage: &Storage) -> Self {
+        Self {
+            multisigs: StorageDoubleMap::new(storage, "Multisig", "Multisigs"),
+            events: TransactionalVec::new(),
+        }
+    }
+
+    fn deposit_event(&mut self, event: Event<T>) {
+        self.events.push(event);
+    }
+
+    pub fn take_events(&mut self) -> Vec<Event<T>> {
+        self.events.take()
+    }
+
+    /// The account controlled by `signatories`, which must be sorted, with `threshold` of them
+    /// needed to approve each call. It does not depend on the order the signatories were given.
+    pub fn multi_account_id(signatories: &[T::AccountId], threshold: u16) -> T::AccountId {
+        T::account_from_hash(T::Hashing::hash_of(&(b"multisig", signatories, threshold)))
+    }
+
+    /// The pending operation of `multisig` on the call with the hash `call_hash`, if any.
+    pub fn multisig(
+        &self,
+        multisig: &T::AccountId,
+        call_hash: &HashOf<T>,
+    ) -> Option<Multisig<T>> {
+        self.multisigs.get(multisig, call_hash)
+    }
+
+    /// Check the signatories of a call, and return the multisig account they control along with
+    /// `caller`.
+    fn ensure_multisig(
+        caller: &T::AccountId,
+        threshold: u16,
+        other_signatories: &[T::AccountId],
+    ) -> Result<T::AccountId, Error> {
+        if threshold < 2 {
+            return Err(Error::MinimumThreshold)
+        }
+        if other_signatories.is_empty() {
+            return Err(Error::TooFewSignatories)
+        }
+        if other_signatories.len() >= T::MAX_SIGNATORIES as usize {
+            return Err(Error::TooManySignatories)
+        }
+        if other_signatories.windows(2).any(|pair| pair[0] >= pair[1]) {
+            return Err(Error::SignatoriesOutOfOrder)
+        }
+        let index = match other_signatories.binary_search(caller) {
+            Ok(_) => return Err(Error::SenderInSignatories),
+            Err(index) => index,
+        };
+        let mut signatories = other_signatories.to_vec();
+        signatories.insert(index, caller.clone());
+        Ok(Self::multi_account_id(&signatories, threshold))
+    }
+}
+
+impl<T: Config> Pallet<T>
+where
+    T: HasPallet<Pallet<T>>
+        + Dispatch<Origin = T::RuntimeOrigin, Call = <T as Config>::RuntimeCall>
+        + Transactional,
+{
+    /// Record the approval of `caller` for the operation of `multisig` on `call_hash`, starting
+    /// the operation if it is new. When `call` is given and the operation has enough approvals,
+    /// it is dispatched from `multisig` instead. Returns the weight used by the dispatched call.
+    fn operate(
+        runtime: &mut T,
+        caller: T::AccountId,
+        threshold: u16,
+        multisig: T::AccountId,
+        call_hash: HashOf<T>,
+        call: Option<<T as Config>::RuntimeCall>,
+    ) -> Result<Weight, crate::support::DispatchError> {
+        let pallet: &mut Self = runtime.pallet_mut();
+        let Some(mut operation) = pallet.multisigs.get(&multisig, &call_hash) else {
+            // A new operation, which the deposit of the caller pays for until it is done.
+            T::Currency::reserve(runtime, &caller, T::DEPOSIT)?;
+            let pallet: &mut Self = runtime.pallet_mut();
+            let operation = Multisig::<T> {
+                depositor: caller.clone(),
+                deposit: T::DEPOSIT,
+                approvals: vec![caller.clone()],
+            };
+            pallet.multisigs.insert(multisig.clone(), call_hash, operation);
+            pallet.deposit_event(Event::NewMultisig { approving: caller, multisig, call_hash });
+            return Ok(0)
+        };
+
+        let approved = match operation.approvals.binary_search(&caller) {
+            Ok(_) => true,
+            Err(index) => {
+                operation.approvals.insert(index, caller.clone());
+                false
+            },
+        };
+        match call {
+            Some(call) if operation.approvals.len() >= threshold as usize => {
+                pallet.multisigs.remove(&multisig, &call_hash);
+                T::Currency::unreserve(runtime, &operation.depositor, operation.deposit);
+                let weight = call.weight();
+                let result = crate::support::with_transaction(runtime, |runtime| {
+                    runtime.dispatch(Origin::Signed(multisig.clone()).into(), call)
+                });
+                let (weight, result) = match result {
+                    Ok(post_info) => (post_info.calc_actual_weight(weight), Ok(())),
+                    Err(error) => (weight, Err(error)),
+                };
+                let pallet: &mut Self = runtime.pallet_mut();
+                pallet.deposit_event(Event::MultisigExecuted {
+                    approving: caller,
+                    multisig,
+                    call_hash,
+                    result,
+                });
+                Ok(weight)
+            },
+            _ if approved => Err(Error::AlreadyApproved.into()),
+            _ => {
+                pallet.multisigs.insert(multisig.clone(), call_hash, operation);
+                pallet.deposit_event(Event::MultisigApproval {
+                    approving: caller,
+                    multisig,
+                    call_hash,
+                });
+                Ok(0)
+            },
+        }
+    }
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T>
+where
+    T: HasPallet<Pallet<T>>
+        + Dispatch<Origin = T::RuntimeOrigin, Call = <T as Config>::RuntimeCall>
+        + Transactional,
+{
+    /// Approve `call` on behalf of the multisig account of the caller and `other_signatories`,
+    /// with `threshold` approvals needed. The first approval starts the operation, and reserves
+    /// `T::DEPOSIT` from the caller. The approval which reaches the threshold dispatches `call`
+    /// from the multisig account, and unreserves the deposit.
+    // The call has to be boxed, since `RuntimeCall` contains the calls of this pallet.
+    #[allow(clippy::boxed_local)]
+    #[weight(call.weight().saturating_add(MULTISIG_WEIGHT))]
+    pub fn as_multi(
+        runtime: &mut T,
+        caller: T::AccountId,
+        threshold: u16,
+        other_signatories: Vec<T::AccountId>,
+        call: Box<<T as Config>::RuntimeCall>,
+    ) -> DispatchResultWithPostInfo {
+        let multisig = Self::ensure_multisig(&caller, threshold, &other_signatories)?;
+        let call_hash = T::Hashing::hash_of(&call);
+        let weight = Self::operate(runtime, caller, threshold, multisig, call_hash, Some(*call))?;
+        Ok(PostDispatchInfo { actual_weight: Some(weight.saturating_add(MULTISIG_WEIGHT)) })
+    }
+
+    /// Approve the call with the hash `call_hash` on behalf of the multisig account of the caller
+    /// and `other_signatories`, without dispatching it. The first approval starts the operation,
+    /// and reserves `T::DEPOSIT` from the caller. Once there are enough approvals, any signatory
+    /// can dispatch the call with `as_multi`.
+    #[weight(MULTISIG_WEIGHT)]
+    pub fn approve_as_multi(
+        runtime: &mut T,
+        caller: T::AccountId,
+        threshold: u16,
+        other_signatories: Vec<T::AccountId>,
+        call_hash: HashOf<T>,
+    ) -> DispatchResult {
+        let multisig = Self::ensure_multisig(&caller, threshold, &other_signatories)?;
+        Self::operate(runtime, caller, threshold, multisig, call_hash, None)?;
+        Ok(())
+    }
+
+    /// Cancel the pending operation of the multisig account of the caller and `other_signatories`
+    /// on the call with the hash `call_hash`, unreserving its deposit. Only the account which
+    /// started the operation can cancel it.
+    #[weight(MULTISIG_WEIGHT)]
+    pub fn cancel_as_multi(
+        runtime: &mut T,
+        caller: T::AccountId,
+        threshold: u16,
+        other_signatories: Vec<T::AccountId>,
+        call_hash: HashOf<T>,
+    ) -> DispatchResult {
+        let multisig = Self::ensure_multisig(&caller, threshold, &other_signatories)?;
+        let pallet: &mut Self = runtime.pallet_mut();
+        let deposit = match pallet.multisigs.get(&multisig, &call_hash) {
+            Some(operation) if operation.depositor == caller => operation.deposit,
+            Some(_) => return Err(Error::NotOwner.into()),
+            None => return Err(Error::NotFound.into()),
+        };
+        pallet.multisigs.remove(&multisig, &call_hash);
+        pallet.deposit_event(Event::MultisigCancelled {
+            cancelling: caller.clone(),
+            multisig,
+            call_hash,
+        });
+        T::Currency::unreserve(runtime, &caller, deposit);
+        Ok(())
+    }
+}
+
+impl<T: Config> Encode for Pallet<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.multisigs.encode_to(dest);
+    }
+}
+
+/// The initial state of this pallet, which has nothing to configure.
+pub struct GenesisConfig<T: Config>(core::marker::PhantomData<T>);
+
+impl<T: Config> Default for GenesisConfig<T> {
+    fn default() -> Self {
+        Self(core::marker::PhantomData)
+    }
+}
+
+impl<T: Config> crate::support::BuildGenesisConfig<T> for GenesisConfig<T> {
+    fn build(self, _runtime: &mut T) -> DispatchResult {
+        Ok(())
+    }
+}
+
+impl<T: Config> crate::support::Hooks<T, T::BlockNumber> for Pallet<T> {}
+
+impl<T: Config> crate::support::PalletEvent for Pallet<T> {
+    type Event = Event<T>;
+}
+
+impl<T: Config> Transactional for Pallet<T> {
+    fn start_transaction(&mut self) {
+        self.events.start_transaction();
+    }
+
+    fn commit_transaction(&mut self) {
+        self.events.commit_transaction();
+    }
+
+    fn rollback_transaction(&mut self) {
+        self.events.rollback_transaction();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Pallet;
+    use crate::{
+        balances,
+        mock::{RuntimeCall, TestRuntime},
+        support::{DispatchError, Hashing},
+    };
+
+    fn transfer(to: &str, amount: u128) -> RuntimeCall {
+        RuntimeCall::balances(balances::Call::transfer_allow_death { to: to.to_string(), amount })
+    }
+
+    #[test]
+    fn multi_account_id() {
+        let signatories = ["alice".to_string(), "bob".to_string(), "charlie".to_string()];
+        let multisig = Pallet::<TestRuntime>::multi_account_id(&signatories, 2);
+        assert_ne!(multisig, Pallet::<TestRuntime>::multi_account_id(&signatories, 3));
+        assert_ne!(multisig, Pallet::<TestRuntime>::multi_account_id(&signatories[..2], 2));
+
+        // Each signatory finds the same account, given the others in order.
+        for caller in &signatories {
+            let others: Vec<_> = signatories.iter().filter(|s| *s != caller).cloned().collect();
+            let found = Pallet::<TestRuntime>::ensure_multisig(caller, 2, &others);
+            assert_eq!(found, Ok(multisig.clone()));
+        }
+
+        let alice = &signatories[0];
+        let bob = signatories[1].clone();
+        let charlie = signatories[2].clone();
+        let dave = "dave".to_string();
+        let ensure = |threshold, others: Vec<String>| {
+            Pallet::<TestRuntime>::ensure_multisig(alice, threshold, &others)
+        };
+        assert_eq!(ensure(1, vec![bob.clone()]), Err(super::Error::MinimumThreshold));
+        assert_eq!(ensure(2, vec![]), Err(super::Error::TooFewSignatories));
+        assert_eq!(
+            ensure(2, vec![bob.clone(), charlie.clone(), dave]),
+            Err(super::Error::TooManySignatories)
+        );
+        assert_eq!(ensure(2, vec![charlie, bob.clone()]), Err(super::Error::SignatoriesOutOfOrder));
+        assert_eq!(ensure(2, vec![alice.clone(), bob]), Err(super::Error::SenderInSignatories));
+    }
+
+    #[test]
+    fn two_of_three() {
+        let mut runtime = TestRuntime::new();
+        let [alice, bob, charlie] = ["alice", "bob", "charlie"].map(String::from);
+        let multisig = Pallet::<TestRuntime>::multi_account_id(
+            &[alice.clone(), bob.clone(), charlie.clone()],
+            2,
+        );
+        runtime.balances.set_balance(&alice, 100);
+        runtime.balances.set_balance(&multisig, 100);
+
+        let call = Box::new(transfer("dave", 30));
+        let call_hash = crate::support::BlakeTwo256::hash_of(&call);
+        let others = |caller: &String| -> Vec<String> {
+            [&alice, &bob, &charlie].into_iter().filter(|s| *s != caller).cloned().collect()
+        };
+
+        // The first approval reserves the deposit of alice, and a second one is needed.
+        let result = Pallet::as_multi(&mut runtime, alice.clone(), 2, others(&alice), call.clone());
+        assert!(result.is_ok());
+        assert_eq!(runtime.balances.reserved_balance(&alice), 20);
+        assert_eq!(
+            Pallet::approve_as_multi(&mut runtime, alice.clone(), 2, others(&alice), call_hash),
+            Err(super::Error::AlreadyApproved.into())
+        );
+        assert_eq!(
+            Pallet::cancel_as_multi(&mut runtime, bob.clone(), 2, others(&bob), call_hash),
+            Err(super::Error::NotOwner.into())
+        );
+        assert_eq!(runtime.balances.balance(&"dave".to_string()), 0);
+
+        // Bob approves last, which dispatches the call from the multisig account.
+        let post_info =
+            Pallet::as_multi(&mut runtime, bob.clone(), 2, others(&bob), call.clone()).unwrap();
+        assert_eq!(post_info.actual_weight, Some(20_000));
+        assert_eq!(runtime.balances.balance(&"dave".to_string()), 30);
+        assert_eq!(runtime.balances.balance(&multisig), 70);
+        assert_eq!(runtime.balances.reserved_balance(&alice), 0);
+        assert_eq!(runtime.multisig.multisig(&multisig, &call_hash), None);
+        assert_eq!(
+            runtime.multisig.take_events(),
+            vec![
+                super::Event::NewMultisig {
+                    approving: alice.clone(),
+                    multisig: multisig.clone(),
+                    call_hash
+                },
+                super::Event::MultisigExecuted {
+                    approving: bob.clone(),
+                    multisig: multisig.clone(),
+                    call_hash,
+                    result: Ok(()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn approvals_without_the_call() {
+        let mut runtime = TestRuntime::new();
+        let [alice, bob] = ["alice", "bob"].map(String::from);
+        let multisig = Pallet::<TestRuntime>::multi_account_id(&[alice.clone(), bob.clone()], 2);
+        runtime.balances.set_balance(&alice, 100);
+        runtime.balances.set_balance(&bob, 10);
+        runtime.balances.set_balance(&multisig, 100);
+        let call = Box::new(transfer("dave", 30));
+        let call_hash = crate::support::BlakeTwo256::hash_of(&call);
+
+        // Bob cannot pay the deposit.
+        assert_eq!(
+            Pallet::approve_as_multi(&mut runtime, bob.clone(), 2, vec![alice.clone()], call_hash),
+            Err(DispatchError::from(balances::Error::InsufficientBalance).in_pallet(1))
+        );
+
+        // Both approve the hash, so the call only needs to be given once.
+        let approve = |runtime: &mut TestRuntime, caller: &String, other: &String| {
+            Pallet::approve_as_multi(runtime, caller.clone(), 2, vec![other.clone()], call_hash)
+        };
+        assert_eq!(approve(&mut runtime, &alice, &bob), Ok(()));
+        assert_eq!(approve(&mut runtime, &bob, &alice), Ok(()));
+        let operation = runtime.multisig.multisig(&multisig, &call_hash).unwrap();
+        assert_eq!(operation.approvals, vec![alice.clone(), bob.clone()]);
+        assert!(Pallet::as_multi(&mut runtime, alice.clone(), 2, vec![bob.clone()], call).is_ok());
+        assert_eq!(runtime.balances.balance(&"dave".to_string()), 30);
+
+        // A cancelled operation gives the deposit back.
+        assert_eq!(approve(&mut runtime, &alice, &bob), Ok(()));
+        assert_eq!(runtime.balances.reserved_balance(&alice), 20);
+        assert_eq!(
+            Pallet::cancel_as_multi(&mut runtime, alice.clone(), 2, vec![bob.clone()], call_hash),
+            Ok(())
+        );
+        assert_eq!(runtime.balances.reserved_balance(&alice), 0);
+        assert_eq!(
+            Pallet::cancel_as_multi(&mut runtime, alice.clone(), 2, vec![bob], call_hash),
+            Err(super::Error::NotFound.into())
+        );
+    }
+}
@@ -1,78 +1,200 @@
 use core::fmt::Debug;
-use std::collections::BTreeMap;
-use crate::support::DispatchResult;
+use crate::support::{
+    Currency, Decode, DispatchResult, Encode, HasPallet, ReservableCurrency, Storage, StorageMap,
+    Transactional, TransactionalVec,
+};
 
-pub trait Config: crate::system::Config {
+/// The type of the deposit reserved for a claim.
+pub type BalanceOf<T> = <<T as Config>::Currency as Currency<
+    <T as crate::system::Config>::AccountId,
+    T,
+>>::Balance;
+
+pub trait Config: crate::system::Config + Sized {
     /// The type which represents the content that can be claimed using this pallet.
     /// Could be the content directly as bytes, or better yet the hash of that content.
     /// We leave that decision to the runtime developer.
-    type Content: Debug + Ord;
+    type Content: Debug + Ord + Clone + Encode + Decode;
+
+    /// The currency in which claim deposits are reserved.
+    type Currency: ReservableCurrency<Self::AccountId, Self>;
+
+    /// The deposit reserved from the owner of a claim, until the claim is revoked.
+    const CLAIM_DEPOSIT: BalanceOf<Self>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[macros::error]
+pub enum Error {
+    /// The content has already been claimed by some account.
+    AlreadyClaimed,
+    /// The content has not been claimed by anyone.
+    NoSuchClaim,
+    /// The caller is not the owner of the claim.
+    NotClaimOwner,
+}
+
+/// The events emitted by this pallet.
+#[derive(Debug)]
+pub enum Event<T: Config> {
+    /// `owner` claimed `claim`.
+    ClaimCreated { owner: T::AccountId, claim: T::Content },
+    /// `owner` revoked their claim on `claim`.
+    ClaimRevoked { owner: T::AccountId, claim: T::Content },
+}
+
+// Implemented by hand, since a derive would require `T` itself to implement `PartialEq`.
+impl<T: Config> PartialEq for Event<T> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (
+                Self::ClaimCreated { owner: a0, claim: a1 },
+                Self::ClaimCreated { owner: b0, claim: b1 },
+            ) => (a0, a1) == (b0, b1),
+            (
+                Self::ClaimRevoked { owner: a0, claim: a1 },
+                Self::ClaimRevoked { owner: b0, claim: b1 },
+            ) => (a0, a1) == (b0, b1),
+            _ => false,
+        }
+    }
 }
 
 /// This is the Proof of Existence Module.
 /// It is a simple module that allows accounts to claim existence of some data.
 #[derive(Debug)]
 pub struct Pallet<T: Config> {
-    /// A simple storage map from content to the owner of that content.
-    /// Accounts can make multiple different claims, but each claim can only have one owner.
-    /* TODO: Add a field `claims` which is a `BTreeMap` fom `T::Content` to `T::AccountId`. */
-    claims: BTreeMap<T::Content, T::AccountId>,
+    /// A simple storage map from content to the owner of that content, and the deposit they
+    /// reserved for it. Accounts can make multiple different claims, but each claim can only have
+    /// one owner.
+    claims: StorageMap<T::Content, (T::AccountId, BalanceOf<T>)>,
+    /// The events emitted by this pallet which have not been collected by the runtime yet.
+    events: TransactionalVec<Event<T>>,
 }
 
 impl<T: Config> Pallet<T> {
     /// Create a new instance of the Proof of Existence Module.
-    pub fn new() -> Self {
-        /* TODO: Return a new instance of the `Pallet` struct. */
-        Self { claims: BTreeMap::new() }
+    pub fn new(storage: &Storage) -> Self {
+        Self {
+            claims: StorageMap::new(storage, "ProofOfExistence", "Claims"),
+            events: TransactionalVec::new(),
+        }
     }
 
-    /// Get the owner (if any) of a claim.
-//    pub fn get_claim(&self, claim: &T::Content) -> Option<&T::AccountId> {
-//        *self.claims.get(claim).unwrap_or(None)
+    fn deposit_event(&mut self, event: Event<T>) {
+        self.events.push(event);
+    }
+
+    pub fn take_events(&mut self) -> Vec<Event<T>> {
+        self.events.take()
+    }
 
-    pub fn get_claim(&self, claim: &T::Content) -> Option<&T::AccountId> {
-        self.claims.get(claim)
+    /// Get the owner (if any) of a claim.
+    pub fn get_claim(&self, claim: &T::Content) -> Option<T::AccountId> {
+        self.claims.get(claim).map(|(owner, _)| owner)
     }
 }
 
 #[macros::call]
-impl<T: Config> Pallet<T> {
-    pub fn create_claim(&mut self, caller: T::AccountId, claim: T::Content) -> DispatchResult {
-        if self.claims.contains_key(&claim) {
-            return Err("This content has already been claimed.");
+impl<T: Config> Pallet<T>
+where
+    T: HasPallet<Pallet<T>>,
+{
+    /// Claim `claim` for the caller, reserving `T::CLAIM_DEPOSIT` from its funds.
+    #[weight(10_000)]
+    pub fn create_claim(
+        runtime: &mut T,
+        caller: T::AccountId,
+        claim: T::Content,
+    ) -> DispatchResult {
+        let poe: &mut Self = runtime.pallet_mut();
+        if poe.claims.contains_key(&claim) {
+            return Err(Error::AlreadyClaimed.into());
         }
-        self.claims.insert(claim, caller);
+        T::Currency::reserve(runtime, &caller, T::CLAIM_DEPOSIT)?;
+        let poe: &mut Self = runtime.pallet_mut();
+        poe.claims.insert(claim.clone(), (caller.clone(), T::CLAIM_DEPOSIT));
+        poe.deposit_event(Event::ClaimCreated { owner: caller, claim });
         Ok(())
     }
 
-    pub fn revoke_claim(&mut self, caller: T::AccountId, claim: T::Content) -> DispatchResult {
-        match self.claims.get(&claim) {
-            Some(owner) if *owner == caller => {
-                self.claims.remove(&claim);
+    /// Revoke the claim of the caller on `claim`, unreserving its deposit.
+    #[weight(10_000)]
+    pub fn revoke_claim(
+        runtime: &mut T,
+        caller: T::AccountId,
+        claim: T::Content,
+    ) -> DispatchResult {
+        let poe: &mut Self = runtime.pallet_mut();
+        match poe.claims.get(&claim) {
+            Some((owner, deposit)) if owner == caller => {
+                poe.claims.remove(&claim);
+                poe.deposit_event(Event::ClaimRevoked { owner: caller.clone(), claim });
+                T::Currency::unreserve(runtime, &caller, deposit);
                 Ok(())
             }
-            Some(_) => Err("You are not the owner of this claim."),
-            None => Err("This claim does not exist."),
+            Some(_) => Err(Error::NotClaimOwner.into()),
+            None => Err(Error::NoSuchClaim.into()),
         }
     }
+}
 
+impl<T: Config> Encode for Pallet<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.claims.encode_to(dest);
+    }
 }
 
+/// The initial state of this pallet.
+pub struct GenesisConfig<T: Config> {
+    /// The initial claims, along with their owner, who must be able to reserve the claim deposit.
+    pub claims: Vec<(T::Content, T::AccountId)>,
+}
 
-#[cfg(test)]
-mod test {
-    struct TestConfig;
+impl<T: Config> Default for GenesisConfig<T> {
+    fn default() -> Self {
+        Self { claims: Vec::new() }
+    }
+}
+
+impl<T: Config> crate::support::BuildGenesisConfig<T> for GenesisConfig<T>
+where
+    T: HasPallet<Pallet<T>>,
+{
+    fn build(self, runtime: &mut T) -> DispatchResult {
+        for (claim, owner) in self.claims {
+            Pallet::create_claim(runtime, owner, claim)?;
+        }
+        Ok(())
+    }
+}
 
-    impl super::Config for TestConfig {
-        type Content = String;
+impl<T: Config> crate::support::Hooks<T, T::BlockNumber> for Pallet<T> {}
+
+impl<T: Config> crate::support::PalletEvent for Pallet<T> {
+    type Event = Event<T>;
+}
+
+impl<T: Config> Transactional for Pallet<T> {
+    fn start_transaction(&mut self) {
+        self.events.start_transaction();
     }
 
-    impl crate::system::Config for TestConfig {
-        type AccountId = String;
-        type BlockNumber = u32;
-        type Nonce = u32;
+    fn commit_transaction(&mut self) {
+        self.events.commit_transaction();
     }
 
+    fn rollback_transaction(&mut self) {
+        self.events.rollback_transaction();
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::Pallet;
+    use crate::mock::TestRuntime;
+
     #[test]
     fn basic_proof_of_existence() {
         /*
@@ -82,16 +204,53 @@ mod test {
                 - Check that all functions work successfully.
                 - Check that all error conditions error as expected.
         */
-        let mut poe = super::Pallet::<TestConfig>::new();
+        let mut runtime = TestRuntime::new();
         let alice = "alice".to_string();
         let bob = "bob".to_string();
         let content = "my_content".to_string();
-        assert_eq!(poe.get_claim(&content), None);
-        assert_eq!(poe.create_claim(alice.clone(), content.clone()), Ok(()));
-        assert_eq!(poe.get_claim(&content), Some(&alice));
-        assert_eq!(poe.create_claim(bob.clone(), content.clone()), Err("This content has already been claimed."));
-        assert_eq!(poe.revoke_claim(bob.clone(), content.clone()), Err("You are not the owner of this claim."));
-        assert_eq!(poe.revoke_claim(alice.clone(), content.clone()), Ok(()));
-        assert_eq!(poe.get_claim(&content), None);
+        runtime.balances.set_balance(&alice, 100);
+        runtime.balances.set_balance(&bob, 100);
+        assert_eq!(runtime.proof_of_existence.get_claim(&content), None);
+        assert_eq!(Pallet::create_claim(&mut runtime, alice.clone(), content.clone()), Ok(()));
+        assert_eq!(runtime.proof_of_existence.get_claim(&content), Some(alice.clone()));
+        assert_eq!(Pallet::create_claim(&mut runtime, bob.clone(), content.clone()), Err(super::Error::AlreadyClaimed.into()));
+        assert_eq!(Pallet::revoke_claim(&mut runtime, bob.clone(), content.clone()), Err(super::Error::NotClaimOwner.into()));
+        assert_eq!(Pallet::revoke_claim(&mut runtime, alice.clone(), content.clone()), Ok(()));
+        assert_eq!(runtime.proof_of_existence.get_claim(&content), None);
+        assert_eq!(Pallet::revoke_claim(&mut runtime, alice.clone(), content.clone()), Err(super::Error::NoSuchClaim.into()));
+        assert_eq!(
+            runtime.proof_of_existence.take_events(),
+            vec![
+                super::Event::ClaimCreated { owner: alice.clone(), claim: content.clone() },
+                super::Event::ClaimRevoked { owner: alice.clone(), claim: content.clone() },
+            ]
+        );
+    }
+
+    #[test]
+    fn claims_cost_a_deposit() {
+        use crate::support::{DispatchError, ModuleError};
+        let mut runtime = TestRuntime::new();
+        let alice = "alice".to_string();
+        let first = "first".to_string();
+        runtime.balances.set_balance(&alice, 30);
+
+        assert_eq!(Pallet::create_claim(&mut runtime, alice.clone(), first.clone()), Ok(()));
+        assert_eq!(runtime.balances.balance(&alice), 10);
+        assert_eq!(runtime.balances.reserved_balance(&alice), 20);
+
+        // A failed deposit is reported as an error of `balances`.
+        assert_eq!(
+            Pallet::create_claim(&mut runtime, alice.clone(), "second".to_string()),
+            Err(DispatchError::Module(ModuleError {
+                index: Some(1),
+                error: 0,
+                message: "InsufficientBalance"
+            }))
+        );
+
+        assert_eq!(Pallet::revoke_claim(&mut runtime, alice.clone(), first), Ok(()));
+        assert_eq!(runtime.balances.balance(&alice), 30);
+        assert_eq!(runtime.balances.reserved_balance(&alice), 0);
     }
-}
\ No newline at end of file
+}
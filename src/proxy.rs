@@ -0,0 +1,398 @@
+use core::fmt::Debug;
+use crate::support::{
+    Currency, Decode, Dispatch, DispatchResult, DispatchResultWithPostInfo, Encode, GetWeight,
+    HasPallet, InstanceFilter, Origin, PostDispatchInfo, ReservableCurrency, Storage, StorageMap,
+    Transactional, TransactionalVec, Weight,
+};
+
+/// The type of the deposit reserved for each proxy.
+pub type BalanceOf<T> = <<T as Config>::Currency as Currency<
+    <T as crate::system::Config>::AccountId,
+    T,
+>>::Balance;
+
+/// A proxy of an account: the delegate, and the type of calls it can make.
+pub type ProxyOf<T> = (<T as crate::system::Config>::AccountId, <T as Config>::ProxyType);
+
+pub trait Config: crate::system::Config + Sized {
+    /// The aggregated call type of the runtime, generated by `#[macros::runtime]`, which a proxy
+    /// can dispatch.
+    type RuntimeCall: GetWeight + Clone + Debug + PartialEq + Encode + Decode;
+
+    /// The types of proxies, each of which can only make the calls passing its filter.
+    type ProxyType: InstanceFilter<<Self as Config>::RuntimeCall>
+        + Copy
+        + Debug
+        + PartialEq
+        + Encode
+        + Decode;
+
+    /// The currency in which proxy deposits are reserved.
+    type Currency: ReservableCurrency<Self::AccountId, Self>;
+
+    /// The deposit reserved from the delegator for each of its proxies.
+    const PROXY_DEPOSIT: BalanceOf<Self>;
+
+    /// The maximum number of proxies of an account.
+    const MAX_PROXIES: u32;
+}
+
+/// The weight of the calls of this pallet, on top of the weight of the call they dispatch.
+const PROXY_WEIGHT: Weight = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[macros::error]
+pub enum Error {
+    /// The account already has `Config::MAX_PROXIES` proxies.
+    TooMany,
+    /// There is no such proxy.
+    NotFound,
+    /// The caller is not a proxy of the real account.
+    NotProxy,
+    /// None of the proxies of the caller for the real account can make this call.
+    Unproxyable,
+    /// The delegate is already a proxy of this type.
+    Duplicate,
+    /// An account cannot be its own proxy.
+    NoSelfProxy,
+}
+
+/// The events emitted by this pallet.
+#[derive(Debug)]
+pub enum Event<T: Config> {
+    /// A proxy dispatched a call on behalf of its delegator, with this result.
+    Executed { result: DispatchResult },
+    /// `delegate` became a proxy of `delegator`, for the calls of `proxy_type`.
+    Added { delegator: T::AccountId, delegate: T::AccountId, proxy_type: T::ProxyType },
+    /// `delegate` is no longer a proxy of `delegator` for the calls of `proxy_type`.
+    Removed { delegator: T::AccountId, delegate: T::AccountId, proxy_type: T::ProxyType },
+}
+
+// Implemented by hand, since a derive would require `T` itself to implement `PartialEq`.
+impl<T: Config> PartialEq for Event<T> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Executed { result: a }, Self::Executed { result: b }) => a == b,
+            (
+                Self::Added { delegator: a0, delegate: a1, proxy_type: a2 },
+                Self::Added { delegator: b0, delegate: b1, proxy_type: b2 },
+            ) => (a0, a1, a2) == (b0, b1, b2),
+            (
+                Self::Removed { delegator: a0, delegate: a1, proxy_type: a2 },
+                Self::Removed { delegator: b0, delegate: b1, proxy_type: b2 },
+            ) => (a0, a1, a2) == (b0, b1, b2),
+            _ => false,
+        }
+    }
+}
+
+/// This is the Proxy Module.
+/// It lets an account delegate some of its calls to other accounts, its proxies.
+#[derive(Debug)]
+pub struct Pallet<T: Config> {
+    /// The proxies of each account, along with their type.
+    proxies: StorageMap<T::AccountId, Vec<ProxyOf<T>>>,
+    /// The events emitted by this pallet which have not been collected by the runtime yet.
+    events: TransactionalVec<Event<T>>,
+}
+
+impl<T: Config> Pallet<T> {
+    /// Create a new instance of the Proxy Module.
+    pub fn new(storage: &Storage) -> Self {
+        Self {
+            proxies: StorageMap::new(storage, "Proxy", "Proxies"),
+            events: TransactionalVec::new(),
+        }
+    }
+
+    fn deposit_event(&mut self, event: Event<T>) {
+        self.events.push(event);
+    }
+
+    pub fn take_events(&mut self) -> Vec<Event<T>> {
+        self.events.take()
+    }
+
+    /// The proxies of `delegator`, along with their type.
+    pub fn proxies(&self, delegator: &T::AccountId) -> Vec<ProxyOf<T>> {
+        self.proxies.get(delegator).unwrap_or_default()
+    }
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T>
+where
+    T: HasPallet<Pallet<T>>
+        + Dispatch<Origin = T::RuntimeOrigin, Call = <T as Config>::RuntimeCall>
+        + Transactional,
+{
+    /// Dispatch `call` on behalf of `real`, of which the caller must be a proxy with a type
+    /// allowing `call`. This succeeds whatever the result of `call`, reported in the
+    /// `Executed` event.
+    // The call has to be boxed, since `RuntimeCall` contains the calls of this pallet.
+    #[allow(clippy::boxed_local)]
+    #[weight(call.weight().saturating_add(PROXY_WEIGHT))]
+    pub fn proxy(
+        runtime: &mut T,
+        caller: T::AccountId,
+        real: T::AccountId,
+        call: Box<<T as Config>::RuntimeCall>,
+    ) -> DispatchResultWithPostInfo {
+        let pallet: &mut Self = runtime.pallet_mut();
+        let mut proxy_types = pallet
+            .proxies(&real)
+            .into_iter()
+            .filter(|(delegate, _)| *delegate == caller)
+            .map(|(_, proxy_type)| proxy_type)
+            .peekable();
+        if proxy_types.peek().is_none() {
+            return Err(Error::NotProxy.into())
+        }
+        if !proxy_types.any(|proxy_type| proxy_type.filter(&call)) {
+            return Err(Error::Unproxyable.into())
+        }
+
+        let weight = call.weight();
+        let result = crate::support::with_transaction(runtime, |runtime| {
+            runtime.dispatch(Origin::Signed(real).into(), *call)
+        });
+        let (weight, result) = match result {
+            Ok(post_info) => (post_info.calc_actual_weight(weight), Ok(())),
+            Err(error) => (weight, Err(error)),
+        };
+        let pallet: &mut Self = runtime.pallet_mut();
+        pallet.deposit_event(Event::Executed { result });
+        Ok(PostDispatchInfo { actual_weight: Some(weight.saturating_add(PROXY_WEIGHT)) })
+    }
+
+    /// Make `delegate` a proxy of the caller for the calls of `proxy_type`, reserving
+    /// `T::PROXY_DEPOSIT` from the caller.
+    #[weight(PROXY_WEIGHT)]
+    pub fn add_proxy(
+        runtime: &mut T,
+        caller: T::AccountId,
+        delegate: T::AccountId,
+        proxy_type: T::ProxyType,
+    ) -> DispatchResult {
+        if delegate == caller {
+            return Err(Error::NoSelfProxy.into())
+        }
+        let pallet: &mut Self = runtime.pallet_mut();
+        let mut proxies = pallet.proxies(&caller);
+        if proxies.contains(&(delegate.clone(), proxy_type)) {
+            return Err(Error::Duplicate.into())
+        }
+        if proxies.len() >= T::MAX_PROXIES as usize {
+            return Err(Error::TooMany.into())
+        }
+        T::Currency::reserve(runtime, &caller, T::PROXY_DEPOSIT)?;
+        proxies.push((delegate.clone(), proxy_type));
+        let pallet: &mut Self = runtime.pallet_mut();
+        pallet.proxies.insert(caller.clone(), proxies);
+        pallet.deposit_event(Event::Added { delegator: caller, delegate, proxy_type });
+        Ok(())
+    }
+
+    /// Remove `delegate` from the proxies of the caller for the calls of `proxy_type`, and
+    /// unreserve its deposit.
+    #[weight(PROXY_WEIGHT)]
+    pub fn remove_proxy(
+        runtime: &mut T,
+        caller: T::AccountId,
+        delegate: T::AccountId,
+        proxy_type: T::ProxyType,
+    ) -> DispatchResult {
+        let pallet: &mut Self = runtime.pallet_mut();
+        let mut proxies = pallet.proxies(&caller);
+        let len = proxies.len();
+        proxies.retain(|proxy| *proxy != (delegate.clone(), proxy_type));
+        if proxies.len() == len {
+            return Err(Error::NotFound.into())
+        }
+        if proxies.is_empty() {
+            pallet.proxies.remove(&caller);
+        } else {
+            pallet.proxies.insert(caller.clone(), proxies);
+        }
+        pallet.deposit_event(Event::Removed {
+            delegator: caller.clone(),
+            delegate,
+            proxy_type,
+        });
+        T::Currency::unreserve(runtime, &caller, T::PROXY_DEPOSIT);
+        Ok(())
+    }
+}
+
+impl<T: Config> Encode for Pallet<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.proxies.encode_to(dest);
+    }
+}
+
+/// The initial state of this pallet, which has nothing to configure.
+pub struct GenesisConfig<T: Config>(core::marker::PhantomData<T>);
+
+impl<T: Config> Default for GenesisConfig<T> {
+    fn default() -> Self {
+        Self(core::marker::PhantomData)
+    }
+}
+
+impl<T: Config> crate::support::BuildGenesisConfig<T> for GenesisConfig<T> {
+    fn build(self, _runtime: &mut T) -> DispatchResult {
+        Ok(())
+    }
+}
+
+impl<T: Config> crate::support::Hooks<T, T::BlockNumber> for Pallet<T> {}
+
+impl<T: Config> crate::support::PalletEvent for Pallet<T> {
+    type Event = Event<T>;
+}
+
+impl<T: Config> Transactional for Pallet<T> {
+    fn start_transaction(&mut self) {
+        self.events.start_transaction();
+    }
+
+    fn commit_transaction(&mut self) {
+        self.events.commit_transaction();
+    }
+
+    fn rollback_transaction(&mut self) {
+        self.events.rollback_transaction();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Pallet;
+    use crate::{
+        balances,
+        mock::{RuntimeCall, TestProxyType, TestRuntime},
+        support::DispatchError,
+        system,
+    };
+
+    fn transfer(to: &str, amount: u128) -> Box<RuntimeCall> {
+        Box::new(RuntimeCall::balances(balances::Call::transfer_allow_death {
+            to: to.to_string(),
+            amount,
+        }))
+    }
+
+    #[test]
+    fn add_and_remove_proxies() {
+        let mut runtime = TestRuntime::new();
+        let alice = "alice".to_string();
+        let bob = "bob".to_string();
+        runtime.balances.set_balance(&alice, 100);
+
+        assert!(Pallet::add_proxy(&mut runtime, alice.clone(), bob.clone(), TestProxyType::Any)
+            .is_ok());
+        assert!(Pallet::add_proxy(
+            &mut runtime,
+            alice.clone(),
+            bob.clone(),
+            TestProxyType::Transfer
+        )
+        .is_ok());
+        assert_eq!(
+            runtime.proxy.proxies(&alice),
+            vec![(bob.clone(), TestProxyType::Any), (bob.clone(), TestProxyType::Transfer)]
+        );
+        assert_eq!(runtime.balances.reserved_balance(&alice), 20);
+
+        assert_eq!(
+            Pallet::add_proxy(&mut runtime, alice.clone(), bob.clone(), TestProxyType::Any),
+            Err(super::Error::Duplicate.into())
+        );
+        assert_eq!(
+            Pallet::add_proxy(&mut runtime, alice.clone(), alice.clone(), TestProxyType::Any),
+            Err(super::Error::NoSelfProxy.into())
+        );
+        assert_eq!(
+            Pallet::add_proxy(
+                &mut runtime,
+                alice.clone(),
+                "charlie".to_string(),
+                TestProxyType::Any
+            ),
+            Err(super::Error::TooMany.into())
+        );
+
+        assert!(Pallet::remove_proxy(&mut runtime, alice.clone(), bob.clone(), TestProxyType::Any)
+            .is_ok());
+        assert_eq!(
+            Pallet::remove_proxy(&mut runtime, alice.clone(), bob.clone(), TestProxyType::Any),
+            Err(super::Error::NotFound.into())
+        );
+        assert_eq!(runtime.proxy.proxies(&alice), vec![(bob.clone(), TestProxyType::Transfer)]);
+        assert_eq!(runtime.balances.reserved_balance(&alice), 10);
+        assert_eq!(
+            runtime.proxy.take_events(),
+            vec![
+                super::Event::Added {
+                    delegator: alice.clone(),
+                    delegate: bob.clone(),
+                    proxy_type: TestProxyType::Any,
+                },
+                super::Event::Added {
+                    delegator: alice.clone(),
+                    delegate: bob.clone(),
+                    proxy_type: TestProxyType::Transfer,
+                },
+                super::Event::Removed {
+                    delegator: alice,
+                    delegate: bob,
+                    proxy_type: TestProxyType::Any,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn proxies_dispatch_filtered_calls_as_the_real_account() {
+        let mut runtime = TestRuntime::new();
+        let alice = "alice".to_string();
+        let bob = "bob".to_string();
+        runtime.balances.set_balance(&alice, 100);
+
+        assert_eq!(
+            Pallet::proxy(&mut runtime, bob.clone(), alice.clone(), transfer("charlie", 20)),
+            Err(super::Error::NotProxy.into())
+        );
+
+        Pallet::add_proxy(&mut runtime, alice.clone(), bob.clone(), TestProxyType::Transfer)
+            .unwrap();
+        let post_info =
+            Pallet::proxy(&mut runtime, bob.clone(), alice.clone(), transfer("charlie", 20))
+                .unwrap();
+        assert_eq!(post_info.actual_weight, Some(20_000));
+        assert_eq!(runtime.balances.balance(&alice), 70);
+        assert_eq!(runtime.balances.balance(&"charlie".to_string()), 20);
+
+        // A transfer proxy cannot make other calls.
+        let mint = Box::new(RuntimeCall::balances(balances::Call::mint {
+            who: bob.clone(),
+            amount: 50,
+        }));
+        assert_eq!(
+            Pallet::proxy(&mut runtime, bob.clone(), alice.clone(), mint.clone()),
+            Err(super::Error::Unproxyable.into())
+        );
+
+        // Any proxy can, but only with the origin of the real account.
+        Pallet::add_proxy(&mut runtime, alice.clone(), bob.clone(), TestProxyType::Any).unwrap();
+        runtime.proxy.take_events();
+        assert!(Pallet::proxy(&mut runtime, bob.clone(), alice, mint).is_ok());
+        assert_eq!(runtime.balances.balance(&bob), 0);
+        assert_eq!(
+            runtime.proxy.take_events(),
+            vec![super::Event::Executed {
+                result: Err(DispatchError::from(system::Error::BadOrigin).in_pallet(0)),
+            }]
+        );
+    }
+}
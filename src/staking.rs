@@ -0,0 +1,518 @@
+use core::fmt::Debug;
+use num::traits::{CheckedAdd, CheckedMul, Saturating, Zero};
+use crate::{
+    support::{
+        CodecError, Currency, Decode, DispatchResult, Encode, HasPallet, Hooks, Imbalance,
+        LockIdentifier, LockableCurrency, Storage, StorageMap, StorageValue, Transactional,
+        TransactionalVec,
+    },
+    system,
+};
+
+/// The type of the funds which are bonded.
+pub type BalanceOf<T> = <<T as Config>::Currency as Currency<
+    <T as crate::system::Config>::AccountId,
+    T,
+>>::Balance;
+
+/// The ledger of a stash of the runtime, in its currency and block numbers.
+pub type LedgerOf<T> = StakingLedger<BalanceOf<T>, <T as crate::system::Config>::BlockNumber>;
+
+/// The name of the lock which keeps bonded funds from being spent.
+const STAKING_ID: LockIdentifier = *b"staking ";
+
+pub trait Config: crate::system::Config + Sized {
+    /// The currency in which funds are bonded, by locking them.
+    type Currency: LockableCurrency<Self::AccountId, Self>;
+
+    /// The number of blocks in an era. Rewards are paid at the end of every era.
+    const BLOCKS_PER_ERA: Self::BlockNumber;
+
+    /// The number of blocks unbonded funds stay locked before they can be withdrawn.
+    const BONDING_DURATION: Self::BlockNumber;
+
+    /// The reward minted at the end of every era, and shared between the stashes in proportion to
+    /// their active bond.
+    const ERA_REWARD: BalanceOf<Self>;
+
+    /// The maximum number of unbonding chunks of a stash which have not been withdrawn yet.
+    const MAX_UNLOCKING_CHUNKS: u32;
+}
+
+/// Funds which were unbonded, and can be withdrawn from block `unlock_at` on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnlockChunk<Balance, BlockNumber> {
+    pub value: Balance,
+    pub unlock_at: BlockNumber,
+}
+
+/// The bonded funds of a stash. All of them are locked, whether they are active or unbonding.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StakingLedger<Balance, BlockNumber> {
+    /// The funds which earn rewards.
+    pub active: Balance,
+    /// The funds which were unbonded, and do not earn rewards anymore.
+    pub unlocking: Vec<UnlockChunk<Balance, BlockNumber>>,
+}
+
+impl<Balance: Zero + Saturating + Copy, BlockNumber> StakingLedger<Balance, BlockNumber> {
+    /// All the bonded funds, which are locked.
+    pub fn total(&self) -> Balance {
+        self.unlocking.iter().fold(self.active, |total, chunk| total.saturating_add(chunk.value))
+    }
+}
+
+impl<Balance: Encode, BlockNumber: Encode> Encode for StakingLedger<Balance, BlockNumber> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.active.encode_to(dest);
+        let unlocking: Vec<_> =
+            self.unlocking.iter().map(|chunk| (&chunk.value, &chunk.unlock_at)).collect();
+        unlocking.encode_to(dest);
+    }
+}
+
+impl<Balance: Decode, BlockNumber: Decode> Decode for StakingLedger<Balance, BlockNumber> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        let active = Balance::decode(input)?;
+        let unlocking = Vec::<(Balance, BlockNumber)>::decode(input)?
+            .into_iter()
+            .map(|(value, unlock_at)| UnlockChunk { value, unlock_at })
+            .collect();
+        Ok(Self { active, unlocking })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[macros::error]
+pub enum Error {
+    /// The account has no bonded funds.
+    NotBonded,
+    /// The free balance of the account is lower than the funds it would have bonded.
+    InsufficientBalance,
+    /// The amount to bond is zero.
+    InsufficientValue,
+    /// The account already has `Config::MAX_UNLOCKING_CHUNKS` unbonding chunks.
+    NoMoreChunks,
+}
+
+/// The events emitted by this pallet.
+#[derive(Debug)]
+pub enum Event<T: Config> {
+    /// `stash` bonded `amount`.
+    Bonded { stash: T::AccountId, amount: BalanceOf<T> },
+    /// `stash` unbonded `amount`, which it can withdraw after the bonding duration.
+    Unbonded { stash: T::AccountId, amount: BalanceOf<T> },
+    /// `stash` withdrew `amount` of unbonded funds, which are not locked anymore.
+    Withdrawn { stash: T::AccountId, amount: BalanceOf<T> },
+    /// `stash` was rewarded `amount` for its active bond.
+    Rewarded { stash: T::AccountId, amount: BalanceOf<T> },
+    /// Era `era` ended, and `total_reward` was paid out to the stashes.
+    EraPaid { era: u32, total_reward: BalanceOf<T> },
+}
+
+// Implemented by hand, since a derive would require `T` itself to implement `PartialEq`.
+impl<T: Config> PartialEq for Event<T> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (
+                Self::Bonded { stash: a0, amount: a1 },
+                Self::Bonded { stash: b0, amount: b1 },
+            ) => (a0, a1) == (b0, b1),
+            (
+                Self::Unbonded { stash: a0, amount: a1 },
+                Self::Unbonded { stash: b0, amount: b1 },
+            ) => (a0, a1) == (b0, b1),
+            (
+                Self::Withdrawn { stash: a0, amount: a1 },
+                Self::Withdrawn { stash: b0, amount: b1 },
+            ) => (a0, a1) == (b0, b1),
+            (
+                Self::Rewarded { stash: a0, amount: a1 },
+                Self::Rewarded { stash: b0, amount: b1 },
+            ) => (a0, a1) == (b0, b1),
+            (
+                Self::EraPaid { era: a0, total_reward: a1 },
+                Self::EraPaid { era: b0, total_reward: b1 },
+            ) => (a0, a1) == (b0, b1),
+            _ => false,
+        }
+    }
+}
+
+/// This is the Staking Module.
+/// It lets accounts bond part of their funds, which earns them a share of the reward minted at the
+/// end of every era.
+#[derive(Debug)]
+pub struct Pallet<T: Config> {
+    /// The bonded funds of each stash.
+    ledger: StorageMap<T::AccountId, LedgerOf<T>>,
+    /// The index of the current era.
+    current_era: StorageValue<u32>,
+    /// The block at the end of which the current era ends, if it is not the end of the first era.
+    era_end: StorageValue<T::BlockNumber>,
+    /// The events emitted by this pallet which have not been collected by the runtime yet.
+    events: TransactionalVec<Event<T>>,
+}
+
+impl<T: Config> Pallet<T> {
+    /// Create a new instance of the Staking Module.
+    pub fn new(storage: &Storage) -> Self {
+        Self {
+            ledger: StorageMap::new(storage, "Staking", "Ledger"),
+            current_era: StorageValue::new(storage, "Staking", "CurrentEra"),
+            era_end: StorageValue::new(storage, "Staking", "EraEnd"),
+            events: TransactionalVec::new(),
+        }
+    }
+
+    fn deposit_event(&mut self, event: Event<T>) {
+        self.events.push(event);
+    }
+
+    pub fn take_events(&mut self) -> Vec<Event<T>> {
+        self.events.take()
+    }
+
+    /// The bonded funds of `stash`, if it has any.
+    pub fn ledger(&self, stash: &T::AccountId) -> Option<LedgerOf<T>> {
+        self.ledger.get(stash)
+    }
+
+    /// The index of the current era.
+    pub fn current_era(&self) -> u32 {
+        self.current_era.get().unwrap_or(0)
+    }
+}
+
+impl<T: Config> Pallet<T>
+where
+    T: HasPallet<Pallet<T>> + HasPallet<system::Pallet<T>>,
+{
+    /// Store the ledger of `stash`, and lock all of its bonded funds. A ledger without funds is
+    /// removed along with the lock.
+    fn update_ledger(runtime: &mut T, stash: &T::AccountId, ledger: LedgerOf<T>) {
+        let total = ledger.total();
+        let pallet: &mut Self = runtime.pallet_mut();
+        if total.is_zero() {
+            pallet.ledger.remove(stash);
+            T::Currency::remove_lock(runtime, STAKING_ID, stash);
+        } else {
+            pallet.ledger.insert(stash.clone(), ledger);
+            T::Currency::set_lock(runtime, STAKING_ID, stash, total);
+        }
+    }
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T>
+where
+    T: HasPallet<Pallet<T>> + HasPallet<system::Pallet<T>>,
+{
+    /// Bond `value` of the free balance of the caller, on top of what it already bonded. Bonded
+    /// funds cannot be spent until they are unbonded and withdrawn.
+    #[weight(10_000)]
+    pub fn bond(runtime: &mut T, caller: T::AccountId, value: BalanceOf<T>) -> DispatchResult {
+        if value.is_zero() {
+            return Err(Error::InsufficientValue.into())
+        }
+        let pallet: &Self = runtime.pallet();
+        let mut ledger = pallet
+            .ledger(&caller)
+            .unwrap_or(StakingLedger { active: BalanceOf::<T>::zero(), unlocking: Vec::new() });
+        ledger.active = ledger.active.saturating_add(value);
+        if ledger.total() > T::Currency::free_balance(runtime, &caller) {
+            return Err(Error::InsufficientBalance.into())
+        }
+        Self::update_ledger(runtime, &caller, ledger);
+        let pallet: &mut Self = runtime.pallet_mut();
+        pallet.deposit_event(Event::Bonded { stash: caller, amount: value });
+        Ok(())
+    }
+
+    /// Unbond up to `value` of the active bond of the caller. The funds stay locked for
+    /// `Config::BONDING_DURATION` blocks, after which `withdraw_unbonded` unlocks them.
+    #[weight(10_000)]
+    pub fn unbond(runtime: &mut T, caller: T::AccountId, value: BalanceOf<T>) -> DispatchResult {
+        let pallet: &Self = runtime.pallet();
+        let mut ledger = pallet.ledger(&caller).ok_or(Error::NotBonded)?;
+        if ledger.unlocking.len() >= T::MAX_UNLOCKING_CHUNKS as usize {
+            return Err(Error::NoMoreChunks.into())
+        }
+        let value = value.min(ledger.active);
+        if value.is_zero() {
+            return Ok(())
+        }
+        let mut unlock_at = HasPallet::<system::Pallet<T>>::pallet(runtime).block_number();
+        unlock_at += T::BONDING_DURATION;
+        ledger.active = ledger.active.saturating_sub(value);
+        ledger.unlocking.push(UnlockChunk { value, unlock_at });
+        Self::update_ledger(runtime, &caller, ledger);
+        let pallet: &mut Self = runtime.pallet_mut();
+        pallet.deposit_event(Event::Unbonded { stash: caller, amount: value });
+        Ok(())
+    }
+
+    /// Unlock the unbonded funds of the caller whose bonding duration is over.
+    #[weight(10_000)]
+    pub fn withdraw_unbonded(runtime: &mut T, caller: T::AccountId) -> DispatchResult {
+        let now = HasPallet::<system::Pallet<T>>::pallet(runtime).block_number();
+        let pallet: &Self = runtime.pallet();
+        let mut ledger = pallet.ledger(&caller).ok_or(Error::NotBonded)?;
+        let total = ledger.total();
+        ledger.unlocking.retain(|chunk| chunk.unlock_at > now);
+        let amount = total.saturating_sub(ledger.total());
+        Self::update_ledger(runtime, &caller, ledger);
+        if !amount.is_zero() {
+            let pallet: &mut Self = runtime.pallet_mut();
+            pallet.deposit_event(Event::Withdrawn { stash: caller, amount });
+        }
+        Ok(())
+    }
+}
+
+impl<T: Config> Encode for Pallet<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.ledger.encode_to(dest);
+        self.current_era.encode_to(dest);
+        self.era_end.encode_to(dest);
+    }
+}
+
+/// The initial state of this pallet.
+pub struct GenesisConfig<T: Config> {
+    /// The initial stashes, along with the funds they bond.
+    pub stakers: Vec<(T::AccountId, BalanceOf<T>)>,
+}
+
+impl<T: Config> Default for GenesisConfig<T> {
+    fn default() -> Self {
+        Self { stakers: Vec::new() }
+    }
+}
+
+impl<T: Config> crate::support::BuildGenesisConfig<T> for GenesisConfig<T>
+where
+    T: HasPallet<Pallet<T>> + HasPallet<system::Pallet<T>>,
+{
+    fn build(self, runtime: &mut T) -> DispatchResult {
+        for (stash, value) in self.stakers {
+            Pallet::bond(runtime, stash, value)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Config> Hooks<T, T::BlockNumber> for Pallet<T>
+where
+    T: HasPallet<Pallet<T>> + HasPallet<system::Pallet<T>>,
+{
+    /// End the current era at the end of its last block, by paying out the era reward to the
+    /// stashes in proportion to their active bond. No reward is paid if the total issuance cannot
+    /// grow by the era reward.
+    fn on_finalize(runtime: &mut T, n: T::BlockNumber) {
+        let pallet: &Self = runtime.pallet();
+        if n < pallet.era_end.get().unwrap_or(T::BLOCKS_PER_ERA) {
+            return
+        }
+        let era = pallet.current_era();
+        let stakes: Vec<_> = pallet
+            .ledger
+            .iter()
+            .map(|(stash, ledger)| (stash, ledger.active))
+            .filter(|(_, active)| !active.is_zero())
+            .collect();
+        let total_stake = stakes
+            .iter()
+            .fold(BalanceOf::<T>::zero(), |total, (_, active)| total.saturating_add(*active));
+        // The rewards add up to at most the era reward, so they can all be minted.
+        let can_mint = T::Currency::total_issuance(runtime).checked_add(&T::ERA_REWARD).is_some();
+        let stakes = if can_mint { stakes } else { Vec::new() };
+
+        let mut total_reward = BalanceOf::<T>::zero();
+        for (stash, active) in stakes {
+            // Only overflows for stakes far beyond the total issuance of any sensible currency.
+            let reward = T::ERA_REWARD
+                .checked_mul(&active)
+                .map(|product| product / total_stake)
+                .unwrap_or_else(BalanceOf::<T>::zero);
+            if reward.is_zero() {
+                continue
+            }
+            // A reward too small to be deposited is not paid.
+            let Ok(deposited) = T::Currency::deposit(runtime, &stash, reward) else { continue };
+            T::Currency::settle(runtime, deposited, Imbalance::zero());
+            total_reward = total_reward.saturating_add(reward);
+            let pallet: &mut Self = runtime.pallet_mut();
+            pallet.deposit_event(Event::Rewarded { stash, amount: reward });
+        }
+
+        let pallet: &mut Self = runtime.pallet_mut();
+        pallet.deposit_event(Event::EraPaid { era, total_reward });
+        pallet.current_era.put(era + 1);
+        let mut era_end = n;
+        era_end += T::BLOCKS_PER_ERA;
+        pallet.era_end.put(era_end);
+    }
+}
+
+impl<T: Config> crate::support::PalletEvent for Pallet<T> {
+    type Event = Event<T>;
+}
+
+impl<T: Config> Transactional for Pallet<T> {
+    fn start_transaction(&mut self) {
+        self.events.start_transaction();
+    }
+
+    fn commit_transaction(&mut self) {
+        self.events.commit_transaction();
+    }
+
+    fn rollback_transaction(&mut self) {
+        self.events.rollback_transaction();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Pallet, StakingLedger, UnlockChunk};
+    use crate::{balances, mock::TestRuntime, support::Hooks};
+
+    fn run_to_block(runtime: &mut TestRuntime, block_number: u32) {
+        while runtime.system.block_number() < block_number {
+            runtime.system.inc_block_number();
+        }
+    }
+
+    #[test]
+    fn bond_unbond_and_withdraw() {
+        let mut runtime = TestRuntime::new();
+        let alice = "alice".to_string();
+        let bob = "bob".to_string();
+        runtime.balances.set_balance(&alice, 100);
+
+        assert_eq!(
+            Pallet::bond(&mut runtime, alice.clone(), 0),
+            Err(super::Error::InsufficientValue.into())
+        );
+        assert_eq!(
+            Pallet::bond(&mut runtime, alice.clone(), 101),
+            Err(super::Error::InsufficientBalance.into())
+        );
+        assert_eq!(Pallet::bond(&mut runtime, alice.clone(), 60), Ok(()));
+        assert_eq!(
+            balances::Pallet::transfer_allow_death(&mut runtime, alice.clone(), bob.clone(), 41),
+            Err(balances::Error::LiquidityRestrictions.into())
+        );
+        assert_eq!(Pallet::bond(&mut runtime, alice.clone(), 30), Ok(()));
+        assert_eq!(runtime.balances.locked_balance(&alice), 90);
+        assert_eq!(
+            Pallet::unbond(&mut runtime, bob.clone(), 10),
+            Err(super::Error::NotBonded.into())
+        );
+
+        // Unbonded funds stay locked for the bonding duration.
+        run_to_block(&mut runtime, 1);
+        assert_eq!(Pallet::unbond(&mut runtime, alice.clone(), 50), Ok(()));
+        assert_eq!(Pallet::unbond(&mut runtime, alice.clone(), 10), Ok(()));
+        assert_eq!(
+            Pallet::unbond(&mut runtime, alice.clone(), 10),
+            Err(super::Error::NoMoreChunks.into())
+        );
+        assert_eq!(
+            runtime.staking.ledger(&alice),
+            Some(StakingLedger {
+                active: 30,
+                unlocking: vec![
+                    UnlockChunk { value: 50, unlock_at: 6 },
+                    UnlockChunk { value: 10, unlock_at: 6 },
+                ],
+            })
+        );
+        assert_eq!(Pallet::withdraw_unbonded(&mut runtime, alice.clone()), Ok(()));
+        assert_eq!(runtime.balances.locked_balance(&alice), 90);
+
+        run_to_block(&mut runtime, 6);
+        assert_eq!(Pallet::withdraw_unbonded(&mut runtime, alice.clone()), Ok(()));
+        assert_eq!(runtime.balances.locked_balance(&alice), 30);
+
+        // Unbonding more than the active bond unbonds all of it.
+        assert_eq!(Pallet::unbond(&mut runtime, alice.clone(), 100), Ok(()));
+        run_to_block(&mut runtime, 11);
+        assert_eq!(Pallet::withdraw_unbonded(&mut runtime, alice.clone()), Ok(()));
+        assert_eq!(runtime.staking.ledger(&alice), None);
+        assert_eq!(runtime.balances.locked_balance(&alice), 0);
+        assert_eq!(
+            runtime.staking.take_events(),
+            vec![
+                super::Event::Bonded { stash: alice.clone(), amount: 60 },
+                super::Event::Bonded { stash: alice.clone(), amount: 30 },
+                super::Event::Unbonded { stash: alice.clone(), amount: 50 },
+                super::Event::Unbonded { stash: alice.clone(), amount: 10 },
+                super::Event::Withdrawn { stash: alice.clone(), amount: 60 },
+                super::Event::Unbonded { stash: alice.clone(), amount: 30 },
+                super::Event::Withdrawn { stash: alice, amount: 30 },
+            ]
+        );
+    }
+
+    #[test]
+    fn era_rewards_are_proportional_to_stake() {
+        let mut runtime = TestRuntime::new();
+        let alice = "alice".to_string();
+        let bob = "bob".to_string();
+        runtime.balances.set_balance(&alice, 100);
+        runtime.balances.set_balance(&bob, 100);
+        runtime.balances.set_balance(&"charlie".to_string(), 100);
+        Pallet::bond(&mut runtime, alice.clone(), 60).unwrap();
+        Pallet::bond(&mut runtime, bob.clone(), 30).unwrap();
+        runtime.staking.take_events();
+
+        Pallet::on_finalize(&mut runtime, 9);
+        assert_eq!(runtime.staking.take_events(), vec![]);
+        Pallet::on_finalize(&mut runtime, 10);
+        assert_eq!(runtime.balances.balance(&alice), 160);
+        assert_eq!(runtime.balances.balance(&bob), 130);
+        assert_eq!(runtime.balances.total_issuance(), 390);
+        assert_eq!(runtime.staking.current_era(), 1);
+        assert_eq!(
+            runtime.staking.take_events(),
+            // Stashes are paid in the order of their encoded account ids.
+            vec![
+                super::Event::Rewarded { stash: bob.clone(), amount: 30 },
+                super::Event::Rewarded { stash: alice.clone(), amount: 60 },
+                super::Event::EraPaid { era: 0, total_reward: 90 },
+            ]
+        );
+
+        // Unbonding funds do not earn rewards.
+        runtime.system.inc_block_number();
+        Pallet::unbond(&mut runtime, alice.clone(), 60).unwrap();
+        Pallet::on_finalize(&mut runtime, 19);
+        assert_eq!(runtime.staking.current_era(), 1);
+        Pallet::on_finalize(&mut runtime, 20);
+        assert_eq!(runtime.balances.balance(&alice), 160);
+        assert_eq!(runtime.balances.balance(&bob), 220);
+        assert_eq!(runtime.staking.current_era(), 2);
+    }
+
+    #[test]
+    fn era_reward_is_not_paid_past_the_maximum_issuance() {
+        let mut runtime = TestRuntime::new();
+        let alice = "alice".to_string();
+        runtime.balances.set_balance(&alice, 100);
+        Pallet::bond(&mut runtime, alice.clone(), 60).unwrap();
+        runtime.balances.set_balance(&"bob".to_string(), u128::MAX - 150);
+        runtime.staking.take_events();
+
+        // The era still ends, without any reward.
+        Pallet::on_finalize(&mut runtime, 10);
+        assert_eq!(runtime.balances.balance(&alice), 100);
+        assert_eq!(runtime.balances.total_issuance(), u128::MAX - 50);
+        assert_eq!(runtime.staking.current_era(), 1);
+        assert_eq!(
+            runtime.staking.take_events(),
+            vec![super::Event::EraPaid { era: 0, total_reward: 0 }]
+        );
+    }
+}
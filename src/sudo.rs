@@ -0,0 +1,314 @@
+use core::fmt::Debug;
+use crate::support::{
+    Decode, Dispatch, DispatchResult, DispatchResultWithPostInfo, Encode, GetWeight, HasPallet,
+    Origin, PostDispatchInfo, Storage, StorageValue, Transactional, TransactionalVec, Weight,
+};
+
+pub trait Config: crate::system::Config {
+    /// The aggregated call type of the runtime, generated by `#[macros::runtime]`, which the sudo
+    /// key can dispatch with root privileges.
+    type RuntimeCall: GetWeight + Clone + Debug + PartialEq + Encode + Decode;
+}
+
+/// The weight of the calls of this pallet, on top of the weight of the call they dispatch.
+const SUDO_WEIGHT: Weight = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[macros::error]
+pub enum Error {
+    /// The caller is not the sudo key.
+    RequireSudo,
+}
+
+/// The events emitted by this pallet.
+#[derive(Debug)]
+pub enum Event<T: Config> {
+    /// The sudo key dispatched a call with root privileges, with this result.
+    Sudid { sudo_result: DispatchResult },
+    /// The sudo key changed from `old` to `new`.
+    KeyChanged { old: Option<T::AccountId>, new: T::AccountId },
+    /// The sudo key dispatched a call on behalf of another account, with this result.
+    SudoAsDone { sudo_result: DispatchResult },
+}
+
+// Implemented by hand, since a derive would require `T` itself to implement `PartialEq`.
+impl<T: Config> PartialEq for Event<T> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Sudid { sudo_result: a }, Self::Sudid { sudo_result: b }) => a == b,
+            (
+                Self::KeyChanged { old: a0, new: a1 },
+                Self::KeyChanged { old: b0, new: b1 },
+            ) => (a0, a1) == (b0, b1),
+            (Self::SudoAsDone { sudo_result: a }, Self::SudoAsDone { sudo_result: b }) => a == b,
+            _ => false,
+        }
+    }
+}
+
+/// This is the Sudo Module.
+/// It lets a single account, the sudo key, dispatch any call with root privileges.
+#[derive(Debug)]
+pub struct Pallet<T: Config> {
+    /// The account allowed to make sudo calls, if any.
+    key: StorageValue<T::AccountId>,
+    /// The events emitted by this pallet which have not been collected by the runtime yet.
+    events: TransactionalVec<Event<T>>,
+}
+
+impl<T: Config> Pallet<T> {
+    /// Create a new instance of the Sudo Module, without any key.
+    pub fn new(storage: &Storage) -> Self {
+        Self { key: StorageValue::new(storage, "Sudo", "Key"), events: TransactionalVec::new() }
+    }
+
+    fn deposit_event(&mut self, event: Event<T>) {
+        self.events.push(event);
+    }
+
+    pub fn take_events(&mut self) -> Vec<Event<T>> {
+        self.events.take()
+    }
+
+    /// The account allowed to make sudo calls, if any.
+    pub fn key(&self) -> Option<T::AccountId> {
+        self.key.get()
+    }
+
+    /// Set the sudo key without any check, as when the chain is started.
+    pub fn initialize_key(&mut self, key: T::AccountId) {
+        self.key.put(key);
+    }
+
+    /// Check that `who` is the sudo key.
+    fn ensure_sudo(&self, who: &T::AccountId) -> DispatchResult {
+        match self.key() {
+            Some(key) if key == *who => Ok(()),
+            _ => Err(Error::RequireSudo.into()),
+        }
+    }
+}
+
+impl<T: Config> Pallet<T>
+where
+    T: HasPallet<Pallet<T>>
+        + Dispatch<Origin = T::RuntimeOrigin, Call = <T as Config>::RuntimeCall>
+        + Transactional,
+{
+    /// Dispatch `call` from `origin` in its own transaction, so a failed call leaves no trace, and
+    /// return its result along with the weight it used.
+    fn dispatch_in_transaction(
+        runtime: &mut T,
+        origin: Origin<T::AccountId>,
+        call: <T as Config>::RuntimeCall,
+    ) -> (DispatchResult, Weight) {
+        let weight = call.weight();
+        let result = crate::support::with_transaction(runtime, |runtime| {
+            runtime.dispatch(origin.into(), call)
+        });
+        match result {
+            Ok(post_info) => (Ok(()), post_info.calc_actual_weight(weight)),
+            Err(error) => (Err(error), weight),
+        }
+    }
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T>
+where
+    T: HasPallet<Pallet<T>>
+        + Dispatch<Origin = T::RuntimeOrigin, Call = <T as Config>::RuntimeCall>
+        + Transactional,
+{
+    /// Dispatch `call` with root privileges. Only the sudo key can make this call, which succeeds
+    /// whatever the result of `call`, reported in the `Sudid` event.
+    // The call has to be boxed, since `RuntimeCall` contains the calls of this pallet.
+    #[allow(clippy::boxed_local)]
+    #[weight(call.weight().saturating_add(SUDO_WEIGHT))]
+    pub fn sudo(
+        runtime: &mut T,
+        caller: T::AccountId,
+        call: Box<<T as Config>::RuntimeCall>,
+    ) -> DispatchResultWithPostInfo {
+        let sudo: &mut Self = runtime.pallet_mut();
+        sudo.ensure_sudo(&caller)?;
+        let (sudo_result, weight) = Self::dispatch_in_transaction(runtime, Origin::Root, *call);
+        let sudo: &mut Self = runtime.pallet_mut();
+        sudo.deposit_event(Event::Sudid { sudo_result });
+        Ok(PostDispatchInfo { actual_weight: Some(weight.saturating_add(SUDO_WEIGHT)) })
+    }
+
+    /// Make `new` the sudo key. Only the current sudo key can make this call.
+    #[weight(SUDO_WEIGHT)]
+    pub fn set_key(runtime: &mut T, caller: T::AccountId, new: T::AccountId) -> DispatchResult {
+        let sudo: &mut Self = runtime.pallet_mut();
+        sudo.ensure_sudo(&caller)?;
+        sudo.key.put(new.clone());
+        sudo.deposit_event(Event::KeyChanged { old: Some(caller), new });
+        Ok(())
+    }
+
+    /// Dispatch `call` from the signed origin of `who`. Only the sudo key can make this call,
+    /// which succeeds whatever the result of `call`, reported in the `SudoAsDone` event.
+    #[allow(clippy::boxed_local)]
+    #[weight(call.weight().saturating_add(SUDO_WEIGHT))]
+    pub fn sudo_as(
+        runtime: &mut T,
+        caller: T::AccountId,
+        who: T::AccountId,
+        call: Box<<T as Config>::RuntimeCall>,
+    ) -> DispatchResultWithPostInfo {
+        let sudo: &mut Self = runtime.pallet_mut();
+        sudo.ensure_sudo(&caller)?;
+        let (sudo_result, weight) =
+            Self::dispatch_in_transaction(runtime, Origin::Signed(who), *call);
+        let sudo: &mut Self = runtime.pallet_mut();
+        sudo.deposit_event(Event::SudoAsDone { sudo_result });
+        Ok(PostDispatchInfo { actual_weight: Some(weight.saturating_add(SUDO_WEIGHT)) })
+    }
+}
+
+impl<T: Config> Encode for Pallet<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.key.encode_to(dest);
+    }
+}
+
+/// The initial state of this pallet.
+pub struct GenesisConfig<T: Config> {
+    /// The initial sudo key. Without one, no account can make calls as root.
+    pub key: Option<T::AccountId>,
+}
+
+impl<T: Config> Default for GenesisConfig<T> {
+    fn default() -> Self {
+        Self { key: None }
+    }
+}
+
+impl<T: Config> crate::support::BuildGenesisConfig<T> for GenesisConfig<T>
+where
+    T: HasPallet<Pallet<T>>,
+{
+    fn build(self, runtime: &mut T) -> DispatchResult {
+        if let Some(key) = self.key {
+            let sudo: &mut Pallet<T> = runtime.pallet_mut();
+            sudo.initialize_key(key);
+        }
+        Ok(())
+    }
+}
+
+impl<T: Config> crate::support::Hooks<T, T::BlockNumber> for Pallet<T> {}
+
+impl<T: Config> crate::support::PalletEvent for Pallet<T> {
+    type Event = Event<T>;
+}
+
+impl<T: Config> Transactional for Pallet<T> {
+    fn start_transaction(&mut self) {
+        self.events.start_transaction();
+    }
+
+    fn commit_transaction(&mut self) {
+        self.events.commit_transaction();
+    }
+
+    fn rollback_transaction(&mut self) {
+        self.events.rollback_transaction();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Pallet;
+    use crate::{
+        balances,
+        mock::{RuntimeCall, TestRuntime},
+        support::{Dispatch, Origin},
+    };
+
+    fn force_set_balance(who: &str, free: u128) -> Box<RuntimeCall> {
+        Box::new(RuntimeCall::balances(balances::Call::force_set_balance {
+            who: who.to_string(),
+            free,
+        }))
+    }
+
+    #[test]
+    fn sudo_dispatches_as_root() {
+        let mut runtime = TestRuntime::new();
+        let alice = "alice".to_string();
+        runtime.sudo.initialize_key(alice.clone());
+
+        // Only the key can use sudo, and root calls fail when made directly.
+        assert_eq!(
+            Pallet::sudo(&mut runtime, "bob".to_string(), force_set_balance("bob", 100)),
+            Err(super::Error::RequireSudo.into())
+        );
+        let call = *force_set_balance("bob", 100);
+        assert!(runtime.dispatch(Origin::Signed(alice.clone()), call).is_err());
+
+        let post_info =
+            Pallet::sudo(&mut runtime, alice.clone(), force_set_balance("bob", 100)).unwrap();
+        assert_eq!(post_info.actual_weight, Some(20_000));
+        assert_eq!(runtime.balances.balance(&"bob".to_string()), 100);
+
+        // A failed call is rolled back, but the sudo call itself succeeds.
+        let call = Box::new(RuntimeCall::balances(balances::Call::force_transfer {
+            from: "bob".to_string(),
+            to: "charlie".to_string(),
+            amount: 200,
+        }));
+        assert!(Pallet::sudo(&mut runtime, alice.clone(), call).is_ok());
+        assert_eq!(runtime.balances.balance(&"bob".to_string()), 100);
+        assert_eq!(
+            runtime.sudo.take_events(),
+            vec![
+                super::Event::Sudid { sudo_result: Ok(()) },
+                super::Event::Sudid {
+                    sudo_result: Err(crate::support::DispatchError::from(
+                        balances::Error::InsufficientBalance
+                    )
+                    .in_pallet(1))
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn sudo_as_and_set_key() {
+        let mut runtime = TestRuntime::new();
+        let alice = "alice".to_string();
+        let bob = "bob".to_string();
+        runtime.sudo.initialize_key(alice.clone());
+        runtime.balances.set_balance(&bob, 100);
+
+        // The call is made on behalf of `bob`, who does not need to have signed it.
+        let call = Box::new(RuntimeCall::balances(balances::Call::transfer_allow_death {
+            to: "charlie".to_string(),
+            amount: 30,
+        }));
+        assert!(Pallet::sudo_as(&mut runtime, alice.clone(), bob.clone(), call.clone()).is_ok());
+        assert_eq!(runtime.balances.balance(&bob), 70);
+        assert_eq!(runtime.balances.balance(&"charlie".to_string()), 30);
+
+        assert_eq!(
+            Pallet::set_key(&mut runtime, bob.clone(), bob.clone()),
+            Err(super::Error::RequireSudo.into())
+        );
+        assert_eq!(Pallet::set_key(&mut runtime, alice.clone(), bob.clone()), Ok(()));
+        assert_eq!(runtime.sudo.key(), Some(bob.clone()));
+        assert_eq!(
+            Pallet::sudo_as(&mut runtime, alice.clone(), bob.clone(), call),
+            Err(super::Error::RequireSudo.into())
+        );
+        assert_eq!(
+            runtime.sudo.take_events(),
+            vec![
+                super::Event::SudoAsDone { sudo_result: Ok(()) },
+                super::Event::KeyChanged { old: Some(alice), new: bob },
+            ]
+        );
+    }
+}
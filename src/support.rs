@@ -1,4 +1,21 @@
+use core::fmt::Debug;
+use num::traits::{CheckedAdd, CheckedMul, CheckedSub, Saturating, Unsigned};
+
+mod codec;
+pub mod ed25519;
+mod hashing;
+pub mod storage;
+mod transactional;
+
+pub use codec::{CodecError, Decode, Encode};
+pub use hashing::{BlakeTwo256, Hashing, H256};
+pub use storage::{Storage, StorageDoubleMap, StorageMap, StorageValue};
+pub use transactional::{
+    with_transaction, Transactional, TransactionalMap, TransactionalValue, TransactionalVec,
+};
+
 /// The most primitive representation of a Blockchain block.
+#[derive(Debug, Clone)]
 pub struct Block<Header, Extrinsic> {
     /// The block header contains metadata about the block.
     pub header: Header,
@@ -6,37 +23,482 @@ pub struct Block<Header, Extrinsic> {
     pub extrinsics: Vec<Extrinsic>,
 }
 
-/// We are using an extremely simplified header which only contains the current block number.
-/// On a real blockchain, you would expect to also find:
-/// - parent block hash
-/// - state root
-/// - extrinsics root
-/// - etc...
-pub struct Header<BlockNumber> {
+/// The header of a block, which links it to its parent and commits to its content.
+///
+/// The hash of a block is the hash of the encoding of its header.
+#[derive(Debug, Clone)]
+pub struct Header<BlockNumber, Hash> {
+    /// The hash of the header of the previous block.
+    pub parent_hash: Hash,
     pub block_number: BlockNumber,
+    /// The merkle root of the state of all pallets after executing the block.
+    pub state_root: Hash,
+    /// The merkle root of the extrinsics of the block.
+    pub extrinsics_root: Hash,
 }
 
 /// This is an "extrinsic": literally an external message from outside of the blockchain.
 /// This simplified version of an extrinsic tells us who is making the call, and which call they are
 /// making.
-pub struct Extrinsic<Caller, Call> {
+///
+/// The caller proves that it made the call by signing the payload returned by `signing_payload`.
+/// The nonce must be the current nonce of the caller, so the same extrinsic cannot be replayed.
+#[derive(Debug, Clone)]
+pub struct Extrinsic<Caller, Call, Nonce, Signature> {
     pub caller: Caller,
+    pub nonce: Nonce,
+    pub signature: Signature,
     pub call: Call,
 }
 
+/// The message the caller of an extrinsic has to sign: the encoding of the call, the nonce of the
+/// caller and the genesis hash of the chain, so a signature is not valid on another chain.
+pub fn signing_payload<Call: Encode, Nonce: Encode, Hash: Encode>(
+    call: &Call,
+    nonce: &Nonce,
+    genesis_hash: &Hash,
+) -> Vec<u8> {
+    (call, nonce, genesis_hash).encode()
+}
+
+/// A signature which can be checked against the account which is supposed to have produced it.
+///
+/// This is implemented by `ed25519::Signature`, but any other scheme where the account is the
+/// public key, such as sr25519, fits in.
+pub trait Verify {
+    /// The type of the account which produces the signature.
+    type Signer;
+
+    /// Whether this is a valid signature of `message` by `signer`.
+    fn verify(&self, message: &[u8], signer: &Self::Signer) -> bool;
+}
+
+/// A signature for the tests of the pallets, where accounts are plain strings. It is valid if it
+/// names the signer and the message.
+#[cfg(test)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestSignature(pub String, pub Vec<u8>);
+
+#[cfg(test)]
+impl Verify for TestSignature {
+    type Signer = String;
+
+    fn verify(&self, message: &[u8], signer: &String) -> bool {
+        &self.0 == signer && self.1 == message
+    }
+}
+
+#[cfg(test)]
+impl Encode for TestSignature {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.0.encode_to(dest);
+        self.1.encode_to(dest);
+    }
+}
+
+#[cfg(test)]
+impl Decode for TestSignature {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        Ok(TestSignature(Decode::decode(input)?, Decode::decode(input)?))
+    }
+}
+
+impl<Header: Encode, Extrinsic: Encode> Encode for Block<Header, Extrinsic> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.header.encode_to(dest);
+        self.extrinsics.encode_to(dest);
+    }
+}
+
+impl<Header: Decode, Extrinsic: Decode> Decode for Block<Header, Extrinsic> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        Ok(Block { header: Decode::decode(input)?, extrinsics: Decode::decode(input)? })
+    }
+}
+
+impl<BlockNumber: Encode, Hash: Encode> Encode for Header<BlockNumber, Hash> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.parent_hash.encode_to(dest);
+        self.block_number.encode_to(dest);
+        self.state_root.encode_to(dest);
+        self.extrinsics_root.encode_to(dest);
+    }
+}
+
+impl<BlockNumber: Decode, Hash: Decode> Decode for Header<BlockNumber, Hash> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        Ok(Header {
+            parent_hash: Decode::decode(input)?,
+            block_number: Decode::decode(input)?,
+            state_root: Decode::decode(input)?,
+            extrinsics_root: Decode::decode(input)?,
+        })
+    }
+}
+
+impl<Caller: Encode, Call: Encode, Nonce: Encode, Signature: Encode> Encode
+    for Extrinsic<Caller, Call, Nonce, Signature>
+{
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.caller.encode_to(dest);
+        self.nonce.encode_to(dest);
+        self.signature.encode_to(dest);
+        self.call.encode_to(dest);
+    }
+}
+
+impl<Caller: Decode, Call: Decode, Nonce: Decode, Signature: Decode> Decode
+    for Extrinsic<Caller, Call, Nonce, Signature>
+{
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        Ok(Extrinsic {
+            caller: Decode::decode(input)?,
+            nonce: Decode::decode(input)?,
+            signature: Decode::decode(input)?,
+            call: Decode::decode(input)?,
+        })
+    }
+}
+
+/// The outcome of executing a block, with one receipt for each of its extrinsics, in order.
+#[derive(Debug)]
+pub struct BlockReceipt<BlockNumber, Caller, Nonce> {
+    pub block_number: BlockNumber,
+    pub extrinsics: Vec<ExtrinsicReceipt<Caller, Nonce>>,
+}
+
+/// The outcome of applying a single extrinsic.
+#[derive(Debug)]
+pub struct ExtrinsicReceipt<Caller, Nonce> {
+    /// The account which made the call.
+    pub caller: Caller,
+    /// The name of the pallet which was called.
+    pub pallet: &'static str,
+    /// The name of the function which was called in that pallet.
+    pub call: &'static str,
+    /// The nonce of the caller when the extrinsic was applied.
+    pub nonce: Nonce,
+    /// The weight actually used by the call, which the caller paid for.
+    pub weight: Weight,
+    /// Whether the call succeeded, or the error it failed with.
+    pub result: DispatchResult,
+}
+
+/// The amount of computation a call needs. Every call declares the weight it uses at most with
+/// `#[weight(..)]`, and blocks have a limited weight.
+pub type Weight = u64;
+
 /// The Result type for our runtime. When everything completes successfully, we return `Ok(())`,
-/// otherwise return a static error message.
-pub type DispatchResult = Result<(), &'static str>;
+/// otherwise return a `DispatchError` describing what went wrong.
+pub type DispatchResult = Result<(), DispatchError>;
+
+/// The Result type of a call which can report the weight it actually used. Calls returning a
+/// `DispatchResult` are converted into this, using their declared weight.
+pub type DispatchResultWithPostInfo = Result<PostDispatchInfo, DispatchError>;
+
+/// Information returned by a call once it has been dispatched.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PostDispatchInfo {
+    /// The weight actually used by the call, if it is lower than its declared weight. The unused
+    /// weight is refunded to the caller.
+    pub actual_weight: Option<Weight>,
+}
+
+impl PostDispatchInfo {
+    /// The weight to charge for a call which declared `weight`. A call cannot use more than it
+    /// declared.
+    pub fn calc_actual_weight(&self, weight: Weight) -> Weight {
+        self.actual_weight.map_or(weight, |actual| actual.min(weight))
+    }
+}
+
+impl From<()> for PostDispatchInfo {
+    fn from(_: ()) -> Self {
+        PostDispatchInfo { actual_weight: None }
+    }
+}
+
+/// The reason why a call could not be dispatched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DispatchError {
+    /// An error which does not belong to any pallet, described by a static message.
+    Other(&'static str),
+    /// An error declared by a pallet with `#[macros::error]`.
+    Module(ModuleError),
+}
+
+/// An error declared by a pallet, identified by the pallet index and the error index rather than
+/// by its wording.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModuleError {
+    /// The index of the pallet in the `Runtime` struct, `system` being `0`. This is `None` until
+    /// the runtime fills it in while dispatching the call.
+    pub index: Option<u8>,
+    /// The index of the variant in the `Error` enum of the pallet.
+    pub error: u8,
+    /// The name of the variant in the `Error` enum of the pallet.
+    pub message: &'static str,
+}
+
+impl DispatchError {
+    /// Set the index of the pallet which returned this error, unless it is already known because
+    /// the error comes from a nested call to another pallet.
+    pub fn in_pallet(self, index: u8) -> Self {
+        match self {
+            DispatchError::Module(ModuleError { index: None, error, message }) =>
+                DispatchError::Module(ModuleError { index: Some(index), error, message }),
+            other => other,
+        }
+    }
+}
+
+impl From<&'static str> for DispatchError {
+    fn from(message: &'static str) -> Self {
+        DispatchError::Other(message)
+    }
+}
+
+impl core::fmt::Display for DispatchError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            DispatchError::Other(message) => write!(f, "{message}"),
+            DispatchError::Module(ModuleError { index: Some(index), error, message }) =>
+                write!(f, "{message} (pallet {index}, error {error})"),
+            DispatchError::Module(ModuleError { index: None, error, message }) =>
+                write!(f, "{message} (error {error})"),
+        }
+    }
+}
+
+/// A call which declares its weight, so it can be charged for before it is dispatched.
+pub trait GetWeight {
+    /// The weight of the call, which is an upper bound on what dispatching it costs.
+    fn weight(&self) -> Weight;
+}
+
+/// A filter on calls, such as the calls which each type of proxy can make.
+pub trait InstanceFilter<Call> {
+    /// Whether `call` passes the filter.
+    fn filter(&self, call: &Call) -> bool;
+}
+
+/// The origin of a call, which tells on whose behalf it is made.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Origin<AccountId> {
+    /// The call is made by an account, such as the caller of a signed extrinsic.
+    Signed(AccountId),
+    /// The call is made with root privileges, which no extrinsic has on its own.
+    Root,
+    /// The call is made on behalf of no one.
+    None,
+}
 
 /// A trait which allows us to dispatch an incoming extrinsic to the appropriate state transition
 /// function call.
 pub trait Dispatch {
-    /// The type used to identify the caller of the function.
-    type Caller;
-    /// The state transition function call the caller is trying to access.
+    /// The type used to identify the origin of the function call.
+    type Origin;
+    /// The state transition function call the origin is trying to access.
     type Call;
 
-    /// This function takes a `caller` and the `call` they want to make, and returns a `Result`
+    /// This function takes an `origin` and the `call` it wants to make, and returns a `Result`
     /// based on the outcome of that function call.
-    fn dispatch(&mut self, caller: Self::Caller, call: Self::Call) -> DispatchResult;
+    fn dispatch(&mut self, origin: Self::Origin, call: Self::Call) -> DispatchResultWithPostInfo;
+}
+
+/// Access to one of the pallets of the runtime. `#[macros::runtime]` implements it on the runtime
+/// for every pallet, including system.
+///
+/// A pallet which needs other pallets takes `runtime: &mut T` rather than `self`, and requires
+/// `T: HasPallet<..>` for each of the pallets it uses, including itself.
+pub trait HasPallet<Pallet> {
+    /// The index of the pallet in the runtime, `system` being `0`. Errors returned to another
+    /// pallet are tagged with it, so they are not taken for errors of the calling pallet.
+    const INDEX: u8;
+
+    fn pallet(&self) -> &Pallet;
+    fn pallet_mut(&mut self) -> &mut Pallet;
+}
+
+/// The events of a pallet, which `#[macros::runtime]` aggregates into the `RuntimeEvent` of the
+/// runtime. Events only need to depend on the runtime when they hold some of its types.
+pub trait PalletEvent {
+    type Event;
+}
+
+/// Whether an operation taking funds out of an account may reap it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExistenceRequirement {
+    /// The operation fails rather than letting the account fall below the existential deposit.
+    KeepAlive,
+    /// The account is reaped if it falls below the existential deposit.
+    AllowDeath,
+}
+
+/// An amount of a currency which was added to or taken out of accounts, but which the total
+/// issuance does not account for yet. Imbalances are settled with `Currency::settle`.
+pub trait Imbalance<Balance>: Sized {
+    /// An imbalance of nothing.
+    fn zero() -> Self;
+    /// The amount of the imbalance.
+    fn peek(&self) -> Balance;
+    /// Merge two imbalances of the same sign into one.
+    fn merge(self, other: Self) -> Self;
+}
+
+/// A currency held by accounts, such as the one of `balances`, which lets other pallets move
+/// funds without knowing how they are stored.
+///
+/// Like calls needing other pallets, the functions take the runtime holding the currency. Errors
+/// are tagged with the index of the pallet of the currency, so they are not taken for errors of
+/// the calling pallet.
+pub trait Currency<AccountId, Runtime> {
+    /// The type used to represent an amount of the currency.
+    type Balance: Unsigned
+        + CheckedAdd
+        + CheckedSub
+        + CheckedMul
+        + Saturating
+        + Copy
+        + Ord
+        + Debug
+        + Encode
+        + Decode;
+    /// Funds added to an account, which the total issuance must grow by.
+    type PositiveImbalance: Imbalance<Self::Balance>;
+    /// Funds taken out of an account, which the total issuance must shrink by.
+    type NegativeImbalance: Imbalance<Self::Balance>;
+
+    /// The amount of the funds of `who` which are not reserved.
+    fn free_balance(runtime: &Runtime, who: &AccountId) -> Self::Balance;
+
+    /// The sum of the funds of all the accounts.
+    fn total_issuance(runtime: &Runtime) -> Self::Balance;
+
+    /// Transfer `amount` from the free balance of `from` to `to`.
+    fn transfer(
+        runtime: &mut Runtime,
+        from: &AccountId,
+        to: &AccountId,
+        amount: Self::Balance,
+        existence: ExistenceRequirement,
+    ) -> DispatchResult;
+
+    /// Take `amount` out of the free balance of `who`.
+    fn withdraw(
+        runtime: &mut Runtime,
+        who: &AccountId,
+        amount: Self::Balance,
+        existence: ExistenceRequirement,
+    ) -> Result<Self::NegativeImbalance, DispatchError>;
+
+    /// Add `amount` to the free balance of `who`, which may create its account. Fails if the total
+    /// issuance cannot grow by `amount`, so settling each deposit before the next one cannot
+    /// overflow it.
+    fn deposit(
+        runtime: &mut Runtime,
+        who: &AccountId,
+        amount: Self::Balance,
+    ) -> Result<Self::PositiveImbalance, DispatchError>;
+
+    /// Update the total issuance for the funds which were deposited and withdrawn. What was
+    /// withdrawn beyond what was deposited is burned, and what was deposited beyond what was
+    /// withdrawn is minted.
+    fn settle(
+        runtime: &mut Runtime,
+        deposited: Self::PositiveImbalance,
+        withdrawn: Self::NegativeImbalance,
+    );
+}
+
+/// A currency where part of the funds of an account can be reserved, for example as a deposit.
+/// Reserved funds still belong to the account, but cannot be used until they are unreserved, and
+/// they can be slashed.
+pub trait ReservableCurrency<AccountId, Runtime>: Currency<AccountId, Runtime> {
+    /// The amount of the funds of `who` which are reserved.
+    fn reserved_balance(runtime: &Runtime, who: &AccountId) -> Self::Balance;
+
+    /// Move `amount` from the free balance of `who` to its reserved balance.
+    fn reserve(runtime: &mut Runtime, who: &AccountId, amount: Self::Balance) -> DispatchResult;
+
+    /// Move up to `amount` from the reserved balance of `who` back to its free balance. Returns
+    /// the part of `amount` which could not be unreserved.
+    fn unreserve(runtime: &mut Runtime, who: &AccountId, amount: Self::Balance) -> Self::Balance;
+
+    /// Destroy up to `amount` from the reserved balance of `who`. Returns the part of `amount`
+    /// which could not be slashed.
+    fn slash_reserved(
+        runtime: &mut Runtime,
+        who: &AccountId,
+        amount: Self::Balance,
+    ) -> Self::Balance;
+}
+
+/// The name of a lock on the funds of an account, so each pallet can manage its own locks.
+pub type LockIdentifier = [u8; 8];
+
+/// A currency where part of the free balance of an account can be locked. Locked funds stay in
+/// the free balance, but cannot be withdrawn. Locks overlap: only the largest one applies.
+pub trait LockableCurrency<AccountId, Runtime>: Currency<AccountId, Runtime> {
+    /// Lock `amount` of the free balance of `who`, replacing the lock named `id` if there is one.
+    fn set_lock(runtime: &mut Runtime, id: LockIdentifier, who: &AccountId, amount: Self::Balance);
+
+    /// Remove the lock named `id` from the funds of `who`.
+    fn remove_lock(runtime: &mut Runtime, id: LockIdentifier, who: &AccountId);
+}
+
+/// The initial state of a pallet, which `#[macros::runtime]` builds before the first block.
+///
+/// Every pallet declares a `GenesisConfig` implementing this trait, even when it has nothing to
+/// configure.
+pub trait BuildGenesisConfig<Runtime> {
+    /// Check the configuration, and write the initial state of the pallet into `runtime`.
+    fn build(self, runtime: &mut Runtime) -> DispatchResult;
+}
+
+/// The logic a pallet runs at every block, outside of any extrinsic.
+///
+/// `#[macros::runtime]` calls the hooks of every pallet, in the order of the `Runtime` struct, so
+/// every pallet implements this trait. Each hook does nothing by default.
+pub trait Hooks<Runtime, BlockNumber> {
+    /// Called at the start of block `n`, before its extrinsics. Returns the weight it used, which
+    /// is taken from the weight available to the extrinsics.
+    fn on_initialize(_runtime: &mut Runtime, _n: BlockNumber) -> Weight {
+        0
+    }
+
+    /// Called after the extrinsics of block `n`, with the weight they left unused. Returns the
+    /// weight it used, out of `remaining_weight`.
+    fn on_idle(_runtime: &mut Runtime, _n: BlockNumber, _remaining_weight: Weight) -> Weight {
+        0
+    }
+
+    /// Called at the end of block `n`, once every pallet ran `on_idle`.
+    fn on_finalize(_runtime: &mut Runtime, _n: BlockNumber) {}
+}
+
+/// The hook the runtime uses to make the caller of an extrinsic pay for the weight of its call.
+///
+/// The runtime implements it by choosing which pallet holds the funds, usually `balances`.
+pub trait ChargeTransaction {
+    /// The type used to identify the caller of an extrinsic.
+    type Caller;
+    /// The fee which was withdrawn from the caller.
+    type Fee;
+
+    /// Withdraw the fee for a call of the given weight from `who`, before it is dispatched.
+    fn withdraw_fee(
+        &mut self,
+        who: &Self::Caller,
+        weight: Weight,
+    ) -> Result<Self::Fee, DispatchError>;
+
+    /// Once the call has been dispatched, refund the part of `fee` which pays for more than
+    /// `actual_weight`, and hand out the rest.
+    fn correct_and_deposit_fee(
+        &mut self,
+        who: &Self::Caller,
+        fee: Self::Fee,
+        actual_weight: Weight,
+    );
 }
@@ -0,0 +1,391 @@
+use std::collections::BTreeMap;
+
+/// A type which can be turned into bytes, in a deterministic way.
+///
+/// The format is inspired by SCALE: integers are little endian, collections are prefixed by their
+/// length as a `Compact` integer, and enums are prefixed by the index of their variant as a `u8`.
+pub trait Encode {
+    /// Append the encoding of `self` to `dest`.
+    fn encode_to(&self, dest: &mut Vec<u8>);
+
+    fn encode(&self) -> Vec<u8> {
+        let mut dest = Vec::new();
+        self.encode_to(&mut dest);
+        dest
+    }
+}
+
+/// A type which can be read back from the bytes produced by `Encode`.
+pub trait Decode: Sized {
+    /// Decode a value from the start of `input`, advancing `input` past the bytes which were read.
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError>;
+
+    /// Decode a value which must span the whole of `data`.
+    fn decode_all(mut data: &[u8]) -> Result<Self, CodecError> {
+        let value = Self::decode(&mut data)?;
+        if !data.is_empty() {
+            return Err(CodecError("input has trailing bytes"))
+        }
+        Ok(value)
+    }
+}
+
+/// The reason why some bytes could not be decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CodecError(pub &'static str);
+
+impl core::fmt::Display for CodecError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Read the next `len` bytes of `input`.
+fn read_bytes<'a>(input: &mut &'a [u8], len: usize) -> Result<&'a [u8], CodecError> {
+    if input.len() < len {
+        return Err(CodecError("unexpected end of input"))
+    }
+    let (bytes, rest) = input.split_at(len);
+    *input = rest;
+    Ok(bytes)
+}
+
+macro_rules! impl_codec_for_int {
+    ($( $int:ty ),*) => {$(
+        impl Encode for $int {
+            fn encode_to(&self, dest: &mut Vec<u8>) {
+                dest.extend_from_slice(&self.to_le_bytes());
+            }
+        }
+
+        impl Decode for $int {
+            fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+                let bytes = read_bytes(input, core::mem::size_of::<$int>())?;
+                Ok(<$int>::from_le_bytes(bytes.try_into().expect("read the size of the integer")))
+            }
+        }
+    )*};
+}
+
+impl_codec_for_int!(u8, u16, u32, u64, u128);
+
+/// An unsigned integer encoded in as few bytes as possible, as in SCALE:
+/// - `0..=63` takes a single byte, with `0b00` in its two lowest bits.
+/// - `64..=16_383` takes two bytes, with `0b01` in the two lowest bits.
+/// - `16_384..=1_073_741_823` takes four bytes, with `0b10` in the two lowest bits.
+/// - anything bigger takes a prefix byte with `0b11` in its two lowest bits and the number of
+///   following bytes minus four in the upper bits, followed by the value in little endian.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Compact<T>(pub T);
+
+impl Encode for Compact<u128> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        let value = self.0;
+        match value {
+            0..=0x3f => dest.push((value as u8) << 2),
+            0x40..=0x3fff => dest.extend_from_slice(&(((value as u16) << 2) | 0b01).to_le_bytes()),
+            0x4000..=0x3fff_ffff =>
+                dest.extend_from_slice(&(((value as u32) << 2) | 0b10).to_le_bytes()),
+            _ => {
+                let bytes = value.to_le_bytes();
+                let len = bytes.iter().rposition(|byte| *byte != 0).expect("value is not zero") + 1;
+                dest.push((((len - 4) as u8) << 2) | 0b11);
+                dest.extend_from_slice(&bytes[..len]);
+            },
+        }
+    }
+}
+
+impl Decode for Compact<u128> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        let prefix = read_bytes(input, 1)?[0];
+        let value = match prefix & 0b11 {
+            0b00 => (prefix >> 2) as u128,
+            0b01 => {
+                let value = u16::from_le_bytes([prefix, read_bytes(input, 1)?[0]]) >> 2;
+                if value <= 0x3f {
+                    return Err(CodecError("compact integer is not in canonical form"))
+                }
+                value as u128
+            },
+            0b10 => {
+                let rest = read_bytes(input, 3)?;
+                let value = u32::from_le_bytes([prefix, rest[0], rest[1], rest[2]]) >> 2;
+                if value <= 0x3fff {
+                    return Err(CodecError("compact integer is not in canonical form"))
+                }
+                value as u128
+            },
+            _ => {
+                let len = (prefix >> 2) as usize + 4;
+                if len > 16 {
+                    return Err(CodecError("compact integer is too big"))
+                }
+                let mut bytes = [0u8; 16];
+                bytes[..len].copy_from_slice(read_bytes(input, len)?);
+                let value = u128::from_le_bytes(bytes);
+                if value <= 0x3fff_ffff || bytes[len - 1] == 0 {
+                    return Err(CodecError("compact integer is not in canonical form"))
+                }
+                value
+            },
+        };
+        Ok(Compact(value))
+    }
+}
+
+macro_rules! impl_compact_for_int {
+    ($( $int:ty ),*) => {$(
+        impl Encode for Compact<$int> {
+            fn encode_to(&self, dest: &mut Vec<u8>) {
+                Compact(self.0 as u128).encode_to(dest);
+            }
+        }
+
+        impl Decode for Compact<$int> {
+            fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+                let Compact(value) = Compact::<u128>::decode(input)?;
+                let value = <$int>::try_from(value)
+                    .map_err(|_| CodecError("compact integer is out of range"))?;
+                Ok(Compact(value))
+            }
+        }
+    )*};
+}
+
+impl_compact_for_int!(u8, u16, u32, u64);
+
+/// Encode the length of a collection.
+fn encode_len(len: usize, dest: &mut Vec<u8>) {
+    Compact(len as u64).encode_to(dest);
+}
+
+/// Decode the length of a collection.
+fn decode_len(input: &mut &[u8]) -> Result<usize, CodecError> {
+    let Compact(len) = Compact::<u64>::decode(input)?;
+    usize::try_from(len).map_err(|_| CodecError("length is out of range"))
+}
+
+impl Encode for bool {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        dest.push(*self as u8);
+    }
+}
+
+impl Decode for bool {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        match u8::decode(input)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(CodecError("invalid boolean")),
+        }
+    }
+}
+
+impl Encode for () {
+    fn encode_to(&self, _dest: &mut Vec<u8>) {}
+}
+
+impl Decode for () {
+    fn decode(_input: &mut &[u8]) -> Result<Self, CodecError> {
+        Ok(())
+    }
+}
+
+impl<const N: usize> Encode for [u8; N] {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        dest.extend_from_slice(self);
+    }
+}
+
+impl<const N: usize> Decode for [u8; N] {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        Ok(read_bytes(input, N)?.try_into().expect("read exactly N bytes"))
+    }
+}
+
+impl Encode for str {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        encode_len(self.len(), dest);
+        dest.extend_from_slice(self.as_bytes());
+    }
+}
+
+impl Encode for String {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.as_str().encode_to(dest);
+    }
+}
+
+impl Decode for String {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        let len = decode_len(input)?;
+        let bytes = read_bytes(input, len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| CodecError("invalid utf-8 string"))
+    }
+}
+
+impl<T: Encode> Encode for [T] {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        encode_len(self.len(), dest);
+        for item in self {
+            item.encode_to(dest);
+        }
+    }
+}
+
+impl<T: Encode> Encode for Vec<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.as_slice().encode_to(dest);
+    }
+}
+
+impl<T: Decode> Decode for Vec<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        let len = decode_len(input)?;
+        // Every item takes at least one byte, except zero sized ones. We cap the allocation to
+        // the size of the input, so a bogus length can not make us allocate a huge vector.
+        let mut items = Vec::with_capacity(len.min(input.len()));
+        for _ in 0..len {
+            items.push(T::decode(input)?);
+        }
+        Ok(items)
+    }
+}
+
+impl<K: Encode, V: Encode> Encode for BTreeMap<K, V> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        encode_len(self.len(), dest);
+        for entry in self {
+            entry.encode_to(dest);
+        }
+    }
+}
+
+impl<K: Decode + Ord, V: Decode> Decode for BTreeMap<K, V> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        Ok(Vec::<(K, V)>::decode(input)?.into_iter().collect())
+    }
+}
+
+impl<T: Encode> Encode for Option<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        match self {
+            None => dest.push(0),
+            Some(value) => {
+                dest.push(1);
+                value.encode_to(dest);
+            },
+        }
+    }
+}
+
+impl<T: Decode> Decode for Option<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        match u8::decode(input)? {
+            0 => Ok(None),
+            1 => Ok(Some(T::decode(input)?)),
+            _ => Err(CodecError("invalid option variant")),
+        }
+    }
+}
+
+impl<T: Encode + ?Sized> Encode for Box<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.as_ref().encode_to(dest);
+    }
+}
+
+impl<T: Decode> Decode for Box<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        Ok(Box::new(T::decode(input)?))
+    }
+}
+
+impl<T: Encode + ?Sized> Encode for &T {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        (**self).encode_to(dest);
+    }
+}
+
+macro_rules! impl_codec_for_tuple {
+    ($( ($( $name:ident ),+) ),*) => {$(
+        impl<$( $name: Encode ),+> Encode for ($( $name, )+) {
+            #[allow(non_snake_case)]
+            fn encode_to(&self, dest: &mut Vec<u8>) {
+                let ($( $name, )+) = self;
+                $( $name.encode_to(dest); )+
+            }
+        }
+
+        impl<$( $name: Decode ),+> Decode for ($( $name, )+) {
+            fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+                Ok(($( $name::decode(input)?, )+))
+            }
+        }
+    )*};
+}
+
+impl_codec_for_tuple!((A), (A, B), (A, B, C), (A, B, C, D));
+
+#[cfg(test)]
+mod tests {
+    use super::{CodecError, Compact, Decode, Encode};
+    use std::collections::BTreeMap;
+
+    fn round_trip<T: Encode + Decode + PartialEq + core::fmt::Debug>(value: T, encoded: &[u8]) {
+        assert_eq!(value.encode(), encoded);
+        assert_eq!(T::decode_all(encoded), Ok(value));
+    }
+
+    #[test]
+    fn integers() {
+        round_trip(42u8, &[42]);
+        round_trip(0x0102u16, &[2, 1]);
+        round_trip(0x01020304u32, &[4, 3, 2, 1]);
+        round_trip(1u64, &[1, 0, 0, 0, 0, 0, 0, 0]);
+        round_trip(u128::MAX, &[0xff; 16]);
+        round_trip(true, &[1]);
+        assert_eq!(bool::decode_all(&[2]), Err(CodecError("invalid boolean")));
+        assert_eq!(u32::decode_all(&[1, 2]), Err(CodecError("unexpected end of input")));
+    }
+
+    #[test]
+    fn compact_integers() {
+        round_trip(Compact(0u32), &[0x00]);
+        round_trip(Compact(1u32), &[0x04]);
+        round_trip(Compact(63u32), &[0xfc]);
+        round_trip(Compact(64u32), &[0x01, 0x01]);
+        round_trip(Compact(16_383u32), &[0xfd, 0xff]);
+        round_trip(Compact(16_384u32), &[0x02, 0x00, 0x01, 0x00]);
+        round_trip(Compact(1_073_741_823u64), &[0xfe, 0xff, 0xff, 0xff]);
+        round_trip(Compact(1_073_741_824u64), &[0x03, 0x00, 0x00, 0x00, 0x40]);
+        round_trip(Compact(u64::MAX), &[0x13, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]);
+        round_trip(Compact(u128::MAX), &[[0x33].as_slice(), &[0xff; 16]].concat());
+
+        assert_eq!(
+            Compact::<u8>::decode_all(&[0x01, 0x04]),
+            Err(CodecError("compact integer is out of range"))
+        );
+        assert_eq!(
+            Compact::<u32>::decode_all(&[0x01, 0x00]),
+            Err(CodecError("compact integer is not in canonical form"))
+        );
+    }
+
+    #[test]
+    fn collections() {
+        round_trip("hola".to_string(), &[0x10, b'h', b'o', b'l', b'a']);
+        round_trip(vec![1u16, 2], &[0x08, 1, 0, 2, 0]);
+        round_trip(Some(3u8), &[1, 3]);
+        round_trip(None::<u8>, &[0]);
+        round_trip(Box::new(7u8), &[7]);
+        round_trip((1u8, "a".to_string()), &[1, 0x04, b'a']);
+        round_trip([9u8; 3], &[9, 9, 9]);
+        round_trip(BTreeMap::from([(2u8, 20u8), (1, 10)]), &[0x08, 1, 10, 2, 20]);
+
+        assert_eq!(String::decode_all(&[0x04, 0xff]), Err(CodecError("invalid utf-8 string")));
+        assert_eq!(Vec::<u8>::decode_all(&[0x08, 1]), Err(CodecError("unexpected end of input")));
+        assert_eq!(u8::decode_all(&[1, 2]), Err(CodecError("input has trailing bytes")));
+    }
+}
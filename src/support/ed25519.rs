@@ -0,0 +1,102 @@
+use super::{CodecError, Decode, Encode, Verify};
+use ed25519_dalek::{Signer, SigningKey, VerifyingKey};
+
+/// An ed25519 public key, which can be used as an `AccountId`.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Public(pub [u8; 32]);
+
+/// An ed25519 signature.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Signature(pub [u8; 64]);
+
+/// An ed25519 key pair, used to sign extrinsics.
+pub struct Pair(SigningKey);
+
+impl Pair {
+    /// Create a key pair from a 32 byte secret seed.
+    pub fn from_seed(seed: &[u8; 32]) -> Self {
+        Pair(SigningKey::from_bytes(seed))
+    }
+
+    pub fn public(&self) -> Public {
+        Public(self.0.verifying_key().to_bytes())
+    }
+
+    pub fn sign(&self, message: &[u8]) -> Signature {
+        Signature(self.0.sign(message).to_bytes())
+    }
+}
+
+impl Verify for Signature {
+    type Signer = Public;
+
+    fn verify(&self, message: &[u8], signer: &Public) -> bool {
+        let signature = ed25519_dalek::Signature::from_bytes(&self.0);
+        VerifyingKey::from_bytes(&signer.0)
+            .map(|key| key.verify_strict(message, &signature).is_ok())
+            .unwrap_or(false)
+    }
+}
+
+impl core::fmt::Debug for Public {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "0x")?;
+        for byte in self.0 {
+            write!(f, "{byte:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+impl core::fmt::Debug for Signature {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "0x")?;
+        for byte in self.0 {
+            write!(f, "{byte:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Encode for Public {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.0.encode_to(dest);
+    }
+}
+
+impl Decode for Public {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        Ok(Public(Decode::decode(input)?))
+    }
+}
+
+impl Encode for Signature {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.0.encode_to(dest);
+    }
+}
+
+impl Decode for Signature {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        Ok(Signature(Decode::decode(input)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Pair, Public, Signature};
+    use crate::support::Verify;
+
+    #[test]
+    fn sign_and_verify() {
+        let alice = Pair::from_seed(&[1; 32]);
+        let bob = Pair::from_seed(&[2; 32]);
+        let signature = alice.sign(b"hola");
+
+        assert!(signature.verify(b"hola", &alice.public()));
+        assert!(!signature.verify(b"chau", &alice.public()));
+        assert!(!signature.verify(b"hola", &bob.public()));
+        assert!(!Signature([0; 64]).verify(b"hola", &alice.public()));
+        assert!(!signature.verify(b"hola", &Public([0xff; 32])));
+    }
+}
@@ -0,0 +1,111 @@
+use super::{CodecError, Decode, Encode};
+use blake2::{digest::consts::U32, Blake2b, Digest};
+use core::fmt::Debug;
+
+/// A hashing algorithm, used by the runtime to compute block hashes and storage roots.
+pub trait Hashing {
+    /// The type of the hashes produced by this algorithm.
+    type Output: Copy + Default + Eq + Ord + Debug + Encode + Decode + AsRef<[u8]>;
+
+    /// Hash some raw bytes.
+    fn hash(data: &[u8]) -> Self::Output;
+
+    /// Hash the encoding of any value.
+    fn hash_of<S: Encode + ?Sized>(value: &S) -> Self::Output {
+        Self::hash(&value.encode())
+    }
+
+    /// Compute the root of a binary merkle tree over `leaves`.
+    ///
+    /// Each level hashes pairs of nodes together, and an odd node at the end of a level is moved up
+    /// as is. The root of an empty tree is the hash of no data.
+    fn merkle_root<I: IntoIterator<Item = Self::Output>>(leaves: I) -> Self::Output {
+        let mut level = leaves.into_iter().collect::<Vec<_>>();
+        if level.is_empty() {
+            return Self::hash(&[])
+        }
+        while level.len() > 1 {
+            level = level
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => Self::hash(&[left.as_ref(), right.as_ref()].concat()),
+                    [single] => *single,
+                    _ => unreachable!("chunks have one or two items"),
+                })
+                .collect();
+        }
+        level[0]
+    }
+}
+
+/// A 256 bit hash.
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct H256(pub [u8; 32]);
+
+impl AsRef<[u8]> for H256 {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl Encode for H256 {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.0.encode_to(dest);
+    }
+}
+
+impl Decode for H256 {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        Ok(H256(Decode::decode(input)?))
+    }
+}
+
+impl Debug for H256 {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "0x")?;
+        for byte in self.0 {
+            write!(f, "{byte:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The BLAKE2b hashing algorithm, with a 256 bit output.
+#[derive(Debug)]
+pub struct BlakeTwo256;
+
+impl Hashing for BlakeTwo256 {
+    type Output = H256;
+
+    fn hash(data: &[u8]) -> H256 {
+        H256(Blake2b::<U32>::digest(data).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BlakeTwo256, Hashing};
+
+    #[test]
+    fn blake_two_256() {
+        assert_eq!(
+            format!("{:?}", BlakeTwo256::hash(b"")),
+            "0x0e5751c026e543b2e8ab2eb06099daa1d1e5df47778f7787faab45cdf12fe3a8"
+        );
+        assert_eq!(BlakeTwo256::hash_of(&(1u8, 2u8)), BlakeTwo256::hash(&[1, 2]));
+    }
+
+    #[test]
+    fn merkle_root() {
+        let [a, b, c] = [b"a", b"b", b"c"].map(|leaf| BlakeTwo256::hash(leaf));
+        let ab = BlakeTwo256::hash(&[a.0, b.0].concat());
+
+        assert_eq!(BlakeTwo256::merkle_root([]), BlakeTwo256::hash(&[]));
+        assert_eq!(BlakeTwo256::merkle_root([a]), a);
+        assert_eq!(BlakeTwo256::merkle_root([a, b]), ab);
+        assert_eq!(
+            BlakeTwo256::merkle_root([a, b, c]),
+            BlakeTwo256::hash(&[ab.0, c.0].concat())
+        );
+    }
+}
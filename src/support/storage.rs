@@ -0,0 +1,633 @@
+use super::{BlakeTwo256, CodecError, Decode, Encode, Hashing, Transactional};
+use core::{cell::RefCell, fmt::Debug, marker::PhantomData};
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+    rc::Rc,
+};
+
+/// A key-value store holding the encoded state of the pallets.
+///
+/// A backend only ever sees committed writes: `Storage` keeps the writes of open transactions to
+/// itself until the outermost transaction is committed.
+pub trait Backend {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>>;
+    fn insert(&mut self, key: Vec<u8>, value: Vec<u8>);
+    fn remove(&mut self, key: &[u8]);
+    /// Every entry whose key starts with `prefix`, in key order.
+    fn iter_prefix(&self, prefix: &[u8]) -> Vec<(Vec<u8>, Vec<u8>)>;
+    /// Make the writes so far durable. A backend which does not outlive the process has nothing
+    /// to do.
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The entries of `entries` whose key starts with `prefix`, in key order.
+fn entries_with_prefix(
+    entries: &BTreeMap<Vec<u8>, Vec<u8>>,
+    prefix: &[u8],
+) -> Vec<(Vec<u8>, Vec<u8>)> {
+    entries
+        .range(prefix.to_vec()..)
+        .take_while(|(key, _)| key.starts_with(prefix))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect()
+}
+
+/// A backend which keeps its entries in memory, so they are lost when it is dropped.
+#[derive(Debug, Default)]
+pub struct InMemoryBackend {
+    entries: BTreeMap<Vec<u8>, Vec<u8>>,
+}
+
+impl Backend for InMemoryBackend {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.entries.get(key).cloned()
+    }
+
+    fn insert(&mut self, key: Vec<u8>, value: Vec<u8>) {
+        self.entries.insert(key, value);
+    }
+
+    fn remove(&mut self, key: &[u8]) {
+        self.entries.remove(key);
+    }
+
+    fn iter_prefix(&self, prefix: &[u8]) -> Vec<(Vec<u8>, Vec<u8>)> {
+        entries_with_prefix(&self.entries, prefix)
+    }
+}
+
+/// The length of the checksum at the end of the file of a `FileBackend`.
+const CHECKSUM_LEN: usize = 32;
+
+/// A backend which keeps its entries in memory, and writes all of them to a file when flushed.
+///
+/// The file is replaced by renaming a temporary file over it, so a crash in the middle of a flush
+/// leaves either the old or the new entries behind, never a mix of both. The encoded entries are
+/// followed by their `BlakeTwo256` hash, so a file damaged on disk is detected when opened.
+#[derive(Debug)]
+pub struct FileBackend {
+    path: PathBuf,
+    entries: BTreeMap<Vec<u8>, Vec<u8>>,
+}
+
+impl FileBackend {
+    /// Open the backend stored at `path`, which starts empty if the file does not exist yet.
+    pub fn open(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let bytes = match fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == io::ErrorKind::NotFound =>
+                return Ok(Self { path, entries: BTreeMap::new() }),
+            Err(e) => return Err(e),
+        };
+        let invalid = |reason: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid storage file {}: {reason}", path.display()),
+            )
+        };
+        let Some(split) = bytes.len().checked_sub(CHECKSUM_LEN) else {
+            return Err(invalid("missing checksum"))
+        };
+        let (encoded, checksum) = bytes.split_at(split);
+        if BlakeTwo256::hash(encoded).as_ref() != checksum {
+            return Err(invalid("checksum mismatch"))
+        }
+        let entries = BTreeMap::decode_all(encoded).map_err(|e| invalid(e.0))?;
+        Ok(Self { path, entries })
+    }
+}
+
+impl Backend for FileBackend {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.entries.get(key).cloned()
+    }
+
+    fn insert(&mut self, key: Vec<u8>, value: Vec<u8>) {
+        self.entries.insert(key, value);
+    }
+
+    fn remove(&mut self, key: &[u8]) {
+        self.entries.remove(key);
+    }
+
+    fn iter_prefix(&self, prefix: &[u8]) -> Vec<(Vec<u8>, Vec<u8>)> {
+        entries_with_prefix(&self.entries, prefix)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let encoded = self.entries.encode();
+        let temporary = self.path.with_extension("tmp");
+        let mut file = fs::File::create(&temporary)?;
+        file.write_all(&encoded)?;
+        file.write_all(BlakeTwo256::hash(&encoded).as_ref())?;
+        file.sync_all()?;
+        fs::rename(&temporary, &self.path)
+    }
+}
+
+/// The storage of a runtime, shared by the storage items of all its pallets.
+///
+/// Cloning a `Storage` gives another handle on the same state. Outside of a transaction all writes
+/// go straight to the backend. Inside of a transaction writes are kept in an overlay, where `None`
+/// marks a removed key, until the outermost transaction is committed.
+///
+/// The storage items of a pallet make up its state: a pallet encodes them in its `Encode` impl,
+/// and the runtime hashes the encoding of each pallet into the state root of a block.
+#[derive(Clone)]
+pub struct Storage(Rc<RefCell<Layers>>);
+
+struct Layers {
+    backend: Box<dyn Backend>,
+    /// One overlay for each open transaction, the innermost transaction being the last one.
+    overlays: Vec<BTreeMap<Vec<u8>, Option<Vec<u8>>>>,
+    /// The prefix of every storage item created over this storage, along with a check that an
+    /// entry, whose key is given without the prefix, decodes as the types of the item.
+    items: BTreeMap<Vec<u8>, CheckEntry>,
+}
+
+type CheckEntry = fn(&[u8], &[u8]) -> Result<(), CodecError>;
+
+impl Storage {
+    pub fn new(backend: impl Backend + 'static) -> Self {
+        Self(Rc::new(RefCell::new(Layers {
+            backend: Box::new(backend),
+            overlays: Vec::new(),
+            items: BTreeMap::new(),
+        })))
+    }
+
+    pub fn in_memory() -> Self {
+        Self::new(InMemoryBackend::default())
+    }
+
+    /// Get the value stored at `key`, looking through the open transactions first.
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        let layers = self.0.borrow();
+        for overlay in layers.overlays.iter().rev() {
+            if let Some(value) = overlay.get(key) {
+                return value.clone()
+            }
+        }
+        layers.backend.get(key)
+    }
+
+    /// Write `value` at `key`, or remove `key` if `value` is `None`.
+    fn write(&self, key: Vec<u8>, value: Option<Vec<u8>>) {
+        let mut layers = self.0.borrow_mut();
+        match layers.overlays.last_mut() {
+            Some(overlay) => {
+                overlay.insert(key, value);
+            },
+            None => match value {
+                Some(value) => layers.backend.insert(key, value),
+                None => layers.backend.remove(&key),
+            },
+        }
+    }
+
+    /// Every entry whose key starts with `prefix`, including the writes of open transactions, in
+    /// key order.
+    fn iter_prefix(&self, prefix: &[u8]) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let layers = self.0.borrow();
+        let mut view: BTreeMap<Vec<u8>, Option<Vec<u8>>> = layers
+            .backend
+            .iter_prefix(prefix)
+            .into_iter()
+            .map(|(key, value)| (key, Some(value)))
+            .collect();
+        for overlay in &layers.overlays {
+            for (key, value) in overlay.range(prefix.to_vec()..) {
+                if !key.starts_with(prefix) {
+                    break
+                }
+                view.insert(key.clone(), value.clone());
+            }
+        }
+        view.into_iter().filter_map(|(key, value)| value.map(|value| (key, value))).collect()
+    }
+
+    /// Record the storage item at `prefix`, so that `check` knows how to decode its entries.
+    fn register(&self, prefix: &[u8], check: CheckEntry) {
+        self.0.borrow_mut().items.insert(prefix.to_vec(), check);
+    }
+
+    /// Check that every committed entry belongs to one of the storage items created so far, and
+    /// decodes as its types. The storage items expect to only read what they wrote, so a state
+    /// loaded from a backend, once all the pallets are created over it, should be checked before
+    /// it is read. Any other entry is reported as `InvalidData`.
+    pub fn check(&self) -> io::Result<()> {
+        let layers = self.0.borrow();
+        for (key, value) in layers.backend.iter_prefix(&[]) {
+            let invalid = |reason: &str| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid storage entry {key:?}: {reason}"),
+                )
+            };
+            // The prefixes of two items never start one another, so the only item which can hold
+            // `key` is the one with the greatest prefix up to `key`.
+            let item = layers.items.range(..=key.clone()).next_back();
+            let Some((prefix, check)) = item.filter(|(prefix, _)| key.starts_with(prefix)) else {
+                return Err(invalid("unknown storage item"))
+            };
+            check(&key[prefix.len()..], &value).map_err(|e| invalid(e.0))?;
+        }
+        Ok(())
+    }
+
+    /// Make the committed state durable. The writes of open transactions have not reached the
+    /// backend yet, so they are not flushed.
+    pub fn flush(&self) -> io::Result<()> {
+        self.0.borrow_mut().backend.flush()
+    }
+}
+
+impl Transactional for Storage {
+    fn start_transaction(&mut self) {
+        self.0.borrow_mut().overlays.push(BTreeMap::new());
+    }
+
+    fn commit_transaction(&mut self) {
+        let mut layers = self.0.borrow_mut();
+        let overlay = layers.overlays.pop().expect("no transaction to commit");
+        match layers.overlays.last_mut() {
+            Some(parent) => parent.extend(overlay),
+            None =>
+                for (key, value) in overlay {
+                    match value {
+                        Some(value) => layers.backend.insert(key, value),
+                        None => layers.backend.remove(&key),
+                    }
+                },
+        }
+    }
+
+    fn rollback_transaction(&mut self) {
+        self.0.borrow_mut().overlays.pop().expect("no transaction to roll back");
+    }
+}
+
+impl Debug for Storage {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Storage").finish_non_exhaustive()
+    }
+}
+
+/// The prefix of the keys of a storage item, made of the names of its pallet and of the item.
+/// Both names are length prefixed, so the prefix of an item never starts the prefix of another.
+fn item_prefix(pallet: &str, item: &str) -> Vec<u8> {
+    (pallet, item).encode()
+}
+
+/// Decode a value read from storage, which was encoded by the storage item reading it, or checked
+/// by `Storage::check` when it was loaded.
+fn decode<T: Decode>(mut bytes: &[u8]) -> T {
+    T::decode(&mut bytes).expect("storage items only read what they wrote")
+}
+
+/// Check the entry of a `StorageValue`, whose key is the prefix of the item.
+fn check_value<V: Decode>(key: &[u8], value: &[u8]) -> Result<(), CodecError> {
+    if !key.is_empty() {
+        return Err(CodecError("value has a key"))
+    }
+    V::decode_all(value).map(|_| ())
+}
+
+/// Check an entry of a `StorageMap`, or of a `StorageDoubleMap` whose key `K` is a pair.
+fn check_map<K: Decode, V: Decode>(key: &[u8], value: &[u8]) -> Result<(), CodecError> {
+    K::decode_all(key)?;
+    V::decode_all(value).map(|_| ())
+}
+
+/// Encode the entries under `prefix`, with the prefix stripped from their keys, so that the same
+/// entries always produce the same bytes no matter which backend holds them.
+fn encode_entries(storage: &Storage, prefix: &[u8], dest: &mut Vec<u8>) {
+    storage
+        .iter_prefix(prefix)
+        .into_iter()
+        .map(|(key, value)| (key[prefix.len()..].to_vec(), value))
+        .collect::<Vec<_>>()
+        .encode_to(dest);
+}
+
+/// A single value of a pallet, which is empty until a value is put in it.
+pub struct StorageValue<V> {
+    storage: Storage,
+    key: Vec<u8>,
+    _value: PhantomData<V>,
+}
+
+impl<V: Encode + Decode> StorageValue<V> {
+    pub fn new(storage: &Storage, pallet: &str, item: &str) -> Self {
+        let key = item_prefix(pallet, item);
+        storage.register(&key, check_value::<V>);
+        Self { storage: storage.clone(), key, _value: PhantomData }
+    }
+
+    pub fn get(&self) -> Option<V> {
+        self.storage.get(&self.key).map(|value| decode(&value))
+    }
+
+    pub fn put(&mut self, value: V) {
+        self.storage.write(self.key.clone(), Some(value.encode()));
+    }
+}
+
+impl<V: Decode + Debug> Debug for StorageValue<V> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.storage.get(&self.key).map(|value| decode::<V>(&value)).fmt(f)
+    }
+}
+
+impl<V> Encode for StorageValue<V> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.storage.get(&self.key).encode_to(dest);
+    }
+}
+
+/// A map of a pallet, whose entries are stored at the encoding of their key after the prefix of
+/// the map.
+pub struct StorageMap<K, V> {
+    storage: Storage,
+    prefix: Vec<u8>,
+    _entry: PhantomData<(K, V)>,
+}
+
+impl<K: Encode + Decode, V: Encode + Decode> StorageMap<K, V> {
+    pub fn new(storage: &Storage, pallet: &str, item: &str) -> Self {
+        let prefix = item_prefix(pallet, item);
+        storage.register(&prefix, check_map::<K, V>);
+        Self { storage: storage.clone(), prefix, _entry: PhantomData }
+    }
+
+    fn key(&self, key: &K) -> Vec<u8> {
+        let mut full_key = self.prefix.clone();
+        key.encode_to(&mut full_key);
+        full_key
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.storage.get(&self.key(key)).map(|value| decode(&value))
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.storage.get(&self.key(key)).is_some()
+    }
+
+    pub fn insert(&mut self, key: K, value: V) {
+        self.storage.write(self.key(&key), Some(value.encode()));
+    }
+
+    pub fn remove(&mut self, key: &K) {
+        self.storage.write(self.key(key), None);
+    }
+}
+
+impl<K: Decode, V: Decode> StorageMap<K, V> {
+    /// Iterate over the entries of the map, in the order of their encoded keys.
+    pub fn iter(&self) -> impl Iterator<Item = (K, V)> {
+        let prefix_len = self.prefix.len();
+        self.storage
+            .iter_prefix(&self.prefix)
+            .into_iter()
+            .map(move |(key, value)| (decode(&key[prefix_len..]), decode(&value)))
+    }
+}
+
+impl<K: Decode + Debug, V: Decode + Debug> Debug for StorageMap<K, V> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K, V> Encode for StorageMap<K, V> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        encode_entries(&self.storage, &self.prefix, dest);
+    }
+}
+
+/// A map of a pallet with two keys, whose entries are stored at the encoding of both keys after
+/// the prefix of the map. The entries sharing a first key can be iterated over on their own.
+pub struct StorageDoubleMap<K1, K2, V> {
+    storage: Storage,
+    prefix: Vec<u8>,
+    _entry: PhantomData<(K1, K2, V)>,
+}
+
+impl<K1: Encode, K2, V> StorageDoubleMap<K1, K2, V> {
+    /// The prefix of the keys of the entries whose first key is `key1`.
+    fn first_key_prefix(&self, key1: &K1) -> Vec<u8> {
+        let mut prefix = self.prefix.clone();
+        key1.encode_to(&mut prefix);
+        prefix
+    }
+
+    /// Remove every entry whose first key is `key1`.
+    pub fn remove_prefix(&mut self, key1: &K1) {
+        for (key, _) in self.storage.iter_prefix(&self.first_key_prefix(key1)) {
+            self.storage.write(key, None);
+        }
+    }
+}
+
+impl<K1: Encode + Decode, K2: Encode + Decode, V: Encode + Decode> StorageDoubleMap<K1, K2, V> {
+    pub fn new(storage: &Storage, pallet: &str, item: &str) -> Self {
+        let prefix = item_prefix(pallet, item);
+        storage.register(&prefix, check_map::<(K1, K2), V>);
+        Self { storage: storage.clone(), prefix, _entry: PhantomData }
+    }
+
+    fn key(&self, key1: &K1, key2: &K2) -> Vec<u8> {
+        let mut full_key = self.first_key_prefix(key1);
+        key2.encode_to(&mut full_key);
+        full_key
+    }
+
+    pub fn get(&self, key1: &K1, key2: &K2) -> Option<V> {
+        self.storage.get(&self.key(key1, key2)).map(|value| decode(&value))
+    }
+
+    pub fn insert(&mut self, key1: K1, key2: K2, value: V) {
+        self.storage.write(self.key(&key1, &key2), Some(value.encode()));
+    }
+
+    pub fn remove(&mut self, key1: &K1, key2: &K2) {
+        self.storage.write(self.key(key1, key2), None);
+    }
+}
+
+impl<K1: Encode, K2: Decode, V: Decode> StorageDoubleMap<K1, K2, V> {
+    /// Iterate over the entries whose first key is `key1`, in the order of their encoded second
+    /// keys.
+    pub fn iter_prefix(&self, key1: &K1) -> impl Iterator<Item = (K2, V)> {
+        let prefix = self.first_key_prefix(key1);
+        let prefix_len = prefix.len();
+        self.storage
+            .iter_prefix(&prefix)
+            .into_iter()
+            .map(move |(key, value)| (decode(&key[prefix_len..]), decode(&value)))
+    }
+}
+
+impl<K1: Decode, K2: Decode, V: Decode> StorageDoubleMap<K1, K2, V> {
+    /// Iterate over all the entries of the map, in the order of their encoded keys.
+    pub fn iter(&self) -> impl Iterator<Item = ((K1, K2), V)> {
+        let prefix_len = self.prefix.len();
+        self.storage
+            .iter_prefix(&self.prefix)
+            .into_iter()
+            .map(move |(key, value)| (decode(&key[prefix_len..]), decode(&value)))
+    }
+}
+
+impl<K1: Decode + Debug, K2: Decode + Debug, V: Decode + Debug> Debug
+    for StorageDoubleMap<K1, K2, V>
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K1, K2, V> Encode for StorageDoubleMap<K1, K2, V> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        encode_entries(&self.storage, &self.prefix, dest);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FileBackend, Storage, StorageDoubleMap, StorageMap, StorageValue};
+    use crate::support::Transactional;
+
+    #[test]
+    fn items_are_kept_apart_by_prefix() {
+        let storage = Storage::in_memory();
+        let mut value = StorageValue::<u32>::new(&storage, "Pallet", "Value");
+        let mut map = StorageMap::<String, u32>::new(&storage, "Pallet", "Map");
+        let other = StorageMap::<String, u32>::new(&storage, "Other", "Map");
+
+        assert_eq!(value.get(), None);
+        value.put(1);
+        assert_eq!(value.get(), Some(1));
+
+        map.insert("bob".to_string(), 2);
+        map.insert("alice".to_string(), 1);
+        assert_eq!(map.get(&"alice".to_string()), Some(1));
+        assert!(map.contains_key(&"bob".to_string()));
+        assert!(!other.contains_key(&"bob".to_string()));
+        assert_eq!(
+            map.iter().collect::<Vec<_>>(),
+            vec![("bob".to_string(), 2), ("alice".to_string(), 1)]
+        );
+
+        map.remove(&"bob".to_string());
+        assert_eq!(map.get(&"bob".to_string()), None);
+        assert_eq!(other.iter().count(), 0);
+    }
+
+    #[test]
+    fn double_map_iterates_by_first_key() {
+        let storage = Storage::in_memory();
+        let mut map = StorageDoubleMap::<String, u8, u32>::new(&storage, "Pallet", "DoubleMap");
+        map.insert("alice".to_string(), 2, 20);
+        map.insert("alice".to_string(), 1, 10);
+        map.insert("bob".to_string(), 1, 30);
+
+        assert_eq!(map.get(&"alice".to_string(), &2), Some(20));
+        let alice_entries = |map: &StorageDoubleMap<String, u8, u32>| {
+            map.iter_prefix(&"alice".to_string()).collect::<Vec<_>>()
+        };
+        assert_eq!(alice_entries(&map), vec![(1, 10), (2, 20)]);
+        assert_eq!(map.iter().count(), 3);
+
+        map.remove(&"alice".to_string(), &1);
+        assert_eq!(alice_entries(&map), vec![(2, 20)]);
+        map.remove_prefix(&"alice".to_string());
+        assert_eq!(map.iter().collect::<Vec<_>>(), vec![(("bob".to_string(), 1), 30)]);
+    }
+
+    #[test]
+    fn nested_transactions() {
+        let mut storage = Storage::in_memory();
+        let mut map = StorageMap::<String, u32>::new(&storage, "Pallet", "Map");
+        let alice = "alice".to_string();
+        map.insert(alice.clone(), 1);
+
+        storage.start_transaction();
+        map.insert(alice.clone(), 2);
+        map.insert("bob".to_string(), 2);
+        storage.start_transaction();
+        map.remove(&alice);
+        assert_eq!(map.get(&alice), None);
+        assert_eq!(map.iter().count(), 1);
+
+        // The inner transaction is rolled back, the outer one is committed.
+        storage.rollback_transaction();
+        assert_eq!(map.get(&alice), Some(2));
+        storage.commit_transaction();
+        assert_eq!(map.iter().count(), 2);
+
+        storage.start_transaction();
+        map.remove(&alice);
+        storage.rollback_transaction();
+        assert_eq!(map.get(&alice), Some(2));
+    }
+
+    #[test]
+    fn file_backend_keeps_flushed_state() {
+        let path = std::env::temp_dir()
+            .join(format!("rust-state-machine-{}-storage", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let storage = Storage::new(FileBackend::open(&path).expect("no file yet"));
+        let mut value = StorageValue::<u32>::new(&storage, "Pallet", "Value");
+        value.put(1);
+        storage.flush().expect("file can be written");
+        value.put(2);
+
+        // Only the flushed writes are found when opening the file again.
+        let reopened = Storage::new(FileBackend::open(&path).expect("valid file"));
+        assert_eq!(StorageValue::<u32>::new(&reopened, "Pallet", "Value").get(), Some(1));
+
+        // A file damaged on disk fails its checksum.
+        let mut bytes = std::fs::read(&path).expect("file exists");
+        bytes[0] ^= 1;
+        std::fs::write(&path, bytes).expect("file can be written");
+        let error = FileBackend::open(&path).map(|_| ()).expect_err("damaged file");
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        assert!(error.to_string().ends_with("checksum mismatch"));
+        std::fs::remove_file(&path).expect("file exists");
+    }
+
+    #[test]
+    fn check_rejects_entries_of_other_items() {
+        let path = std::env::temp_dir()
+            .join(format!("rust-state-machine-{}-storage-check", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let storage = Storage::new(FileBackend::open(&path).expect("no file yet"));
+        StorageValue::<u32>::new(&storage, "Pallet", "Value").put(1);
+        StorageMap::<String, u32>::new(&storage, "Pallet", "Map").insert("alice".to_string(), 2);
+        assert!(storage.check().is_ok());
+        storage.flush().expect("file can be written");
+
+        // The entries of the file must belong to the items created over it, with the same types.
+        let reopened = Storage::new(FileBackend::open(&path).expect("valid file"));
+        StorageValue::<u32>::new(&reopened, "Pallet", "Value");
+        let error = reopened.check().expect_err("unknown map");
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        assert!(error.to_string().ends_with("unknown storage item"));
+        StorageMap::<String, u64>::new(&reopened, "Pallet", "Map");
+        let error = reopened.check().expect_err("map of another type");
+        assert!(error.to_string().ends_with("unexpected end of input"));
+        StorageMap::<String, u32>::new(&reopened, "Pallet", "Map");
+        assert!(reopened.check().is_ok());
+        std::fs::remove_file(&path).expect("file exists");
+    }
+}
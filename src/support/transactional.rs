@@ -0,0 +1,377 @@
+use super::Encode;
+use core::{borrow::Borrow, fmt::Debug};
+use std::collections::BTreeMap;
+
+/// A trait for storage which can open nested transactions over its state.
+///
+/// Every write made after `start_transaction` is kept apart from the rest of the state until the
+/// matching `commit_transaction` or `rollback_transaction`. Transactions can be nested, in which
+/// case committing only merges the writes into the enclosing transaction.
+pub trait Transactional {
+    /// Open a new transaction layer on top of the current state.
+    fn start_transaction(&mut self);
+    /// Merge the writes of the innermost transaction into the layer below it.
+    fn commit_transaction(&mut self);
+    /// Throw away the writes of the innermost transaction.
+    fn rollback_transaction(&mut self);
+}
+
+/// Run `f` inside of a new transaction on `storage`.
+///
+/// The writes made by `f` are committed if it returns `Ok`, and rolled back if it returns `Err`.
+pub fn with_transaction<S, R, E>(
+    storage: &mut S,
+    f: impl FnOnce(&mut S) -> Result<R, E>,
+) -> Result<R, E>
+where
+    S: Transactional + ?Sized,
+{
+    storage.start_transaction();
+    let result = f(storage);
+    match result {
+        Ok(_) => storage.commit_transaction(),
+        Err(_) => storage.rollback_transaction(),
+    }
+    result
+}
+
+/// A `BTreeMap` with support for nested transactions.
+///
+/// Outside of a transaction all writes go straight to the underlying map. Inside of a transaction
+/// writes are kept in an overlay, where `None` marks a removed key, until the transaction ends.
+pub struct TransactionalMap<K, V> {
+    /// The committed state of the map.
+    committed: BTreeMap<K, V>,
+    /// One overlay for each open transaction, the innermost transaction being the last one.
+    overlays: Vec<BTreeMap<K, Option<V>>>,
+}
+
+impl<K: Ord + Clone, V> TransactionalMap<K, V> {
+    pub fn new() -> Self {
+        Self { committed: BTreeMap::new(), overlays: Vec::new() }
+    }
+
+    /// Get the value stored at `key`, looking through the open transactions first.
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        for overlay in self.overlays.iter().rev() {
+            if let Some(value) = overlay.get(key) {
+                return value.as_ref()
+            }
+        }
+        self.committed.get(key)
+    }
+
+    pub fn insert(&mut self, key: K, value: V) {
+        match self.overlays.last_mut() {
+            Some(overlay) => {
+                overlay.insert(key, Some(value));
+            },
+            None => {
+                self.committed.insert(key, value);
+            },
+        }
+    }
+
+    pub fn remove(&mut self, key: &K) {
+        match self.overlays.last_mut() {
+            Some(overlay) => {
+                overlay.insert(key.clone(), None);
+            },
+            None => {
+                self.committed.remove(key);
+            },
+        }
+    }
+
+    /// Iterate over the current view of the map, including the writes of open transactions, in key
+    /// order.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        let mut view: BTreeMap<&K, Option<&V>> =
+            self.committed.iter().map(|(key, value)| (key, Some(value))).collect();
+        for overlay in &self.overlays {
+            for (key, value) in overlay {
+                view.insert(key, value.as_ref());
+            }
+        }
+        view.into_iter().filter_map(|(key, value)| value.map(|value| (key, value)))
+    }
+}
+
+impl<K: Ord + Clone, V> Transactional for TransactionalMap<K, V> {
+    fn start_transaction(&mut self) {
+        self.overlays.push(BTreeMap::new());
+    }
+
+    fn commit_transaction(&mut self) {
+        let overlay = self.overlays.pop().expect("no transaction to commit");
+        match self.overlays.last_mut() {
+            Some(parent) => parent.extend(overlay),
+            None =>
+                for (key, value) in overlay {
+                    match value {
+                        Some(value) => self.committed.insert(key, value),
+                        None => self.committed.remove(&key),
+                    };
+                },
+        }
+    }
+
+    fn rollback_transaction(&mut self) {
+        self.overlays.pop().expect("no transaction to roll back");
+    }
+}
+
+impl<K: Ord + Clone + Debug, V: Debug> Debug for TransactionalMap<K, V> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+/// Encodes the current view of the map like a `BTreeMap`, so that the same entries always produce
+/// the same bytes no matter how many transactions they went through.
+impl<K: Ord + Clone + Encode, V: Encode> Encode for TransactionalMap<K, V> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.iter().collect::<Vec<_>>().encode_to(dest);
+    }
+}
+
+/// A single value with support for nested transactions.
+pub struct TransactionalValue<V> {
+    /// The committed value.
+    committed: V,
+    /// One overlay for each open transaction, holding the value if it was written in it.
+    overlays: Vec<Option<V>>,
+}
+
+impl<V> TransactionalValue<V> {
+    pub fn new(value: V) -> Self {
+        Self { committed: value, overlays: Vec::new() }
+    }
+
+    /// Get the current value, looking through the open transactions first.
+    pub fn get(&self) -> &V {
+        self.overlays.iter().rev().find_map(Option::as_ref).unwrap_or(&self.committed)
+    }
+
+    pub fn put(&mut self, value: V) {
+        match self.overlays.last_mut() {
+            Some(overlay) => *overlay = Some(value),
+            None => self.committed = value,
+        }
+    }
+}
+
+impl<V> Transactional for TransactionalValue<V> {
+    fn start_transaction(&mut self) {
+        self.overlays.push(None);
+    }
+
+    fn commit_transaction(&mut self) {
+        let overlay = self.overlays.pop().expect("no transaction to commit");
+        if let Some(value) = overlay {
+            self.put(value);
+        }
+    }
+
+    fn rollback_transaction(&mut self) {
+        self.overlays.pop().expect("no transaction to roll back");
+    }
+}
+
+impl<V: Debug> Debug for TransactionalValue<V> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.get().fmt(f)
+    }
+}
+
+impl<V: Encode> Encode for TransactionalValue<V> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.get().encode_to(dest);
+    }
+}
+
+/// An append-only list with support for nested transactions, such as a list of pending events.
+///
+/// Rolling back a transaction drops every item pushed since that transaction was started.
+pub struct TransactionalVec<T> {
+    items: Vec<T>,
+    /// The length of `items` when each of the open transactions was started.
+    checkpoints: Vec<usize>,
+}
+
+impl<T> TransactionalVec<T> {
+    pub fn new() -> Self {
+        Self { items: Vec::new(), checkpoints: Vec::new() }
+    }
+
+    pub fn push(&mut self, item: T) {
+        self.items.push(item);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.items.iter()
+    }
+
+    /// Take all the items out of the list.
+    ///
+    /// The items are handed over to the caller for good: rolling back a transaction which was
+    /// started before this call does not bring them back.
+    pub fn take(&mut self) -> Vec<T> {
+        self.checkpoints.iter_mut().for_each(|len| *len = 0);
+        core::mem::take(&mut self.items)
+    }
+}
+
+impl<T> Transactional for TransactionalVec<T> {
+    fn start_transaction(&mut self) {
+        self.checkpoints.push(self.items.len());
+    }
+
+    fn commit_transaction(&mut self) {
+        self.checkpoints.pop().expect("no transaction to commit");
+    }
+
+    fn rollback_transaction(&mut self) {
+        let len = self.checkpoints.pop().expect("no transaction to roll back");
+        self.items.truncate(len);
+    }
+}
+
+impl<T: Debug> Debug for TransactionalVec<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        with_transaction, Transactional, TransactionalMap, TransactionalValue, TransactionalVec,
+    };
+
+    #[test]
+    fn writes_outside_transaction_are_committed() {
+        let mut map = TransactionalMap::<&str, u32>::new();
+        map.insert("alice", 1);
+        map.insert("bob", 2);
+        map.remove(&"bob");
+        assert_eq!(map.get("alice"), Some(&1));
+        assert_eq!(map.get("bob"), None);
+    }
+
+    #[test]
+    fn rollback_restores_previous_state() {
+        let mut map = TransactionalMap::<&str, u32>::new();
+        map.insert("alice", 1);
+
+        map.start_transaction();
+        map.insert("alice", 2);
+        map.insert("bob", 3);
+        assert_eq!(map.get("alice"), Some(&2));
+        map.remove(&"alice");
+        assert_eq!(map.get("alice"), None);
+        map.rollback_transaction();
+
+        assert_eq!(map.get("alice"), Some(&1));
+        assert_eq!(map.get("bob"), None);
+    }
+
+    #[test]
+    fn nested_transactions() {
+        let mut map = TransactionalMap::<&str, u32>::new();
+        map.insert("alice", 1);
+
+        map.start_transaction();
+        map.insert("bob", 2);
+
+        // The inner transaction is rolled back, but the outer one keeps its writes.
+        map.start_transaction();
+        map.insert("alice", 10);
+        map.remove(&"bob");
+        map.rollback_transaction();
+        assert_eq!(map.get("alice"), Some(&1));
+        assert_eq!(map.get("bob"), Some(&2));
+
+        // The inner transaction is committed into the outer one...
+        map.start_transaction();
+        map.remove(&"alice");
+        map.commit_transaction();
+        assert_eq!(map.get("alice"), None);
+
+        // ...and only reaches the committed state once the outer one is committed too.
+        map.commit_transaction();
+        assert_eq!(map.iter().collect::<Vec<_>>(), vec![(&"bob", &2)]);
+    }
+
+    #[test]
+    fn with_transaction_commits_on_ok_only() {
+        let mut map = TransactionalMap::<&str, u32>::new();
+
+        let result: Result<(), &str> = with_transaction(&mut map, |map| {
+            map.insert("alice", 1);
+            Err("failed")
+        });
+        assert_eq!(result, Err("failed"));
+        assert_eq!(map.get("alice"), None);
+
+        let result: Result<(), &str> = with_transaction(&mut map, |map| {
+            map.insert("alice", 1);
+            Ok(())
+        });
+        assert_eq!(result, Ok(()));
+        assert_eq!(map.get("alice"), Some(&1));
+    }
+
+    #[test]
+    fn nested_transactions_on_value() {
+        let mut value = TransactionalValue::new(1);
+
+        value.start_transaction();
+        value.put(2);
+        value.start_transaction();
+        assert_eq!(value.get(), &2);
+        value.put(3);
+        value.rollback_transaction();
+        assert_eq!(value.get(), &2);
+        value.commit_transaction();
+        assert_eq!(value.get(), &2);
+
+        value.start_transaction();
+        value.put(4);
+        value.rollback_transaction();
+        assert_eq!(value.get(), &2);
+    }
+
+    #[test]
+    fn rollback_truncates_vec() {
+        let mut vec = TransactionalVec::<u32>::new();
+        vec.push(1);
+
+        vec.start_transaction();
+        vec.push(2);
+        vec.start_transaction();
+        vec.push(3);
+        vec.rollback_transaction();
+        vec.commit_transaction();
+        assert_eq!(vec.iter().collect::<Vec<_>>(), vec![&1, &2]);
+
+        vec.start_transaction();
+        vec.push(4);
+        vec.rollback_transaction();
+        assert_eq!(vec.take(), vec![1, 2]);
+        assert_eq!(vec.take(), Vec::<u32>::new());
+
+        // Items taken inside of a transaction are not restored by rolling it back.
+        vec.push(5);
+        vec.start_transaction();
+        vec.push(6);
+        assert_eq!(vec.take(), vec![5, 6]);
+        vec.push(7);
+        vec.rollback_transaction();
+        assert_eq!(vec.take(), Vec::<u32>::new());
+    }
+}
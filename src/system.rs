@@ -1,61 +1,308 @@
-use std::collections::BTreeMap;
 use num::traits::{One, Zero};
-use core::ops::AddAssign;
+use core::{fmt::Debug, ops::AddAssign};
+use crate::support::{
+    Decode, DispatchError, DispatchResult, Encode, Hashing, Origin, Storage, StorageMap,
+    StorageValue, Transactional, TransactionalMap, TransactionalValue, Verify, Weight,
+};
 
 pub trait Config {
-    type AccountId: Ord + Clone;
-    type BlockNumber: Zero + One + AddAssign + Copy;
-    type Nonce: Zero + One + Copy;
+    type AccountId: Ord + Clone + Debug + Encode + Decode;
+    type BlockNumber: Zero + One + AddAssign + Copy + Ord + Debug + Encode + Decode;
+    type Nonce: Zero + One + Copy + PartialEq + PartialOrd + Encode + Decode;
+    /// The aggregated event type of the runtime, generated by `#[macros::runtime]`.
+    type RuntimeEvent;
+    /// The origin of the calls of the runtime, which converts from and into a `support::Origin`.
+    type RuntimeOrigin: Clone
+        + Debug
+        + From<Origin<Self::AccountId>>
+        + Into<Origin<Self::AccountId>>;
+    /// The hashing algorithm used for block hashes and merkle roots.
+    type Hashing: Hashing;
+    /// The signature of an extrinsic, which must be produced by its caller.
+    type Signature: Verify<Signer = Self::AccountId>;
+    /// The maximum total weight of the extrinsics of a block.
+    const MAX_BLOCK_WEIGHT: Weight;
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[macros::error]
+pub enum Error {
+    /// The signature of the extrinsic was not produced by its caller.
+    BadSignature,
+    /// The nonce of the extrinsic was already used by its caller.
+    StaleNonce,
+    /// The nonce of the extrinsic is ahead of the current nonce of its caller, so an extrinsic of
+    /// the caller is missing before it.
+    FutureNonce,
+    /// The extrinsic does not fit in what remains of the weight of the block.
+    ExhaustsResources,
+    /// The caller cannot pay the fee of the extrinsic.
+    Payment,
+    /// The origin is not allowed to make this call.
+    BadOrigin,
+}
+
+/// Ensure that `origin` is signed, and return the account which signed it. The error is reported
+/// as coming from `system`, whichever pallet the call belongs to.
+pub fn ensure_signed<T: Config>(origin: T::RuntimeOrigin) -> Result<T::AccountId, DispatchError> {
+    match origin.into() {
+        Origin::Signed(who) => Ok(who),
+        _ => Err(DispatchError::from(Error::BadOrigin).in_pallet(0)),
+    }
+}
+
+/// Ensure that `origin` is root, as required by calls reserved to root. The error is reported as
+/// coming from `system`, whichever pallet the call belongs to.
+pub fn ensure_root<T: Config>(origin: T::RuntimeOrigin) -> DispatchResult {
+    match origin.into() {
+        Origin::Root => Ok(()),
+        _ => Err(DispatchError::from(Error::BadOrigin).in_pallet(0)),
+    }
+}
+
+/// The hash type produced by the `Hashing` algorithm of the runtime.
+pub type HashOf<T> = <<T as Config>::Hashing as Hashing>::Output;
+
+/// The part of the block execution during which an event was emitted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// The event was emitted by the `on_initialize` hook of a pallet, before the extrinsics.
+    Initialization,
+    /// The event was emitted while applying the extrinsic at this index of the block.
+    ApplyExtrinsic(u32),
+    /// The event was emitted by the `on_idle` or `on_finalize` hook of a pallet, after the
+    /// extrinsics.
+    Finalization,
+}
+
+/// An event emitted during the execution of the current block.
+#[derive(Debug)]
+pub struct EventRecord<E> {
+    pub phase: Phase,
+    pub event: E,
+}
 
 #[derive(Debug)]
 pub struct Pallet<T: Config> {
-    block_number: T::BlockNumber,
-    nonce: BTreeMap<T::AccountId, T::Nonce>,
+    /// The storage shared by all the pallets, whose transactions are opened by the runtime.
+    storage: Storage,
+    block_number: StorageValue<T::BlockNumber>,
+    nonce: StorageMap<T::AccountId, T::Nonce>,
+    /// The hash of every block executed so far, starting with the genesis block, which the
+    /// runtime records as block `0` once it has built the genesis state.
+    block_hash: StorageMap<T::BlockNumber, HashOf<T>>,
+    /// The events emitted by all the pallets during the current block, by order of deposit.
+    events: TransactionalMap<u32, EventRecord<T::RuntimeEvent>>,
+    event_count: TransactionalValue<u32>,
+    /// The weight used by the extrinsics of the current block so far.
+    block_weight: TransactionalValue<Weight>,
 }
 
 impl<T: Config> Pallet<T>{
-    pub fn new() -> Self {
-        Self { block_number : T::BlockNumber::zero(), nonce: BTreeMap::new()}
+    pub fn new(storage: &Storage) -> Self {
+        Self {
+            storage: storage.clone(),
+            block_number: StorageValue::new(storage, "System", "BlockNumber"),
+            nonce: StorageMap::new(storage, "System", "Nonce"),
+            block_hash: StorageMap::new(storage, "System", "BlockHash"),
+            events: TransactionalMap::new(),
+            event_count: TransactionalValue::new(0),
+            block_weight: TransactionalValue::new(0),
+        }
+    }
+
+    /// The storage shared by all the pallets, for example to flush it once a block is executed.
+    pub fn storage(&self) -> &Storage {
+        &self.storage
     }
 
     pub fn block_number(&self) -> T::BlockNumber {
-        self.block_number
+        self.block_number.get().unwrap_or_else(T::BlockNumber::zero)
     }
 
     pub fn inc_block_number(&mut self) {
-        self.block_number += T::BlockNumber::one();
+        let mut block_number = self.block_number();
+        block_number += T::BlockNumber::one();
+        self.block_number.put(block_number);
+    }
+
+    /// The hash of the block with the given number, if it was executed.
+    pub fn block_hash(&self, block_number: &T::BlockNumber) -> Option<HashOf<T>> {
+        self.block_hash.get(block_number)
+    }
+
+    /// The hash of the last executed block, which the next block must reference as its parent.
+    pub fn parent_hash(&self) -> HashOf<T> {
+        self.block_hash(&self.block_number()).unwrap_or_default()
+    }
+
+    /// The hash of the genesis block, which extrinsics sign so they are only valid on this chain.
+    pub fn genesis_hash(&self) -> HashOf<T> {
+        self.block_hash(&T::BlockNumber::zero()).unwrap_or_default()
+    }
+
+    /// Record the hash of the current block, once it has been fully executed.
+    pub fn finalize_block(&mut self, hash: HashOf<T>) {
+        self.block_hash.insert(self.block_number(), hash);
+    }
+
+    /// The nonce `who` must use for its next extrinsic, which is the number of extrinsics it has
+    /// made so far.
+    pub fn account_nonce(&self, who: &T::AccountId) -> T::Nonce {
+        self.nonce.get(who).unwrap_or_else(T::Nonce::zero)
     }
 
-    pub fn inc_nonce(&mut self, who: &T::AccountId) {
-        let actual_nonce: T::Nonce = *self.nonce.get(who).unwrap_or(&T::Nonce::zero());
+    /// Forget everything about `who`, once the account has been removed from the pallets holding
+    /// its funds. Its nonce starts again from zero if it is ever used again.
+    pub fn kill_account(&mut self, who: &T::AccountId) {
+        self.nonce.remove(who);
+    }
+
+    /// Increment the nonce of `who`, returning the nonce it had before.
+    pub fn inc_nonce(&mut self, who: &T::AccountId) -> T::Nonce {
+        let actual_nonce = self.account_nonce(who);
         let new_nonce = actual_nonce + T::Nonce::one();
         self.nonce.insert(who.clone(), new_nonce);
+        actual_nonce
+    }
+
+    /// Increment the nonce of `who`, if `expected` is its current nonce. Otherwise the nonce is
+    /// left untouched, and the error tells whether `expected` was already used or is too far ahead.
+    pub fn check_and_inc_nonce(
+        &mut self,
+        who: &T::AccountId,
+        expected: T::Nonce,
+    ) -> DispatchResult {
+        let actual_nonce = self.account_nonce(who);
+        if expected < actual_nonce {
+            return Err(Error::StaleNonce.into())
+        }
+        if expected > actual_nonce {
+            return Err(Error::FutureNonce.into())
+        }
+        self.inc_nonce(who);
+        Ok(())
+    }
+
+    /// Record an event in the log of the current block.
+    pub fn deposit_event(&mut self, phase: Phase, event: T::RuntimeEvent) {
+        let index = *self.event_count.get();
+        self.events.insert(index, EventRecord { phase, event });
+        self.event_count.put(index + 1);
+    }
+
+    /// The events emitted so far during the current block, in the order they were deposited.
+    pub fn events(&self) -> impl Iterator<Item = &EventRecord<T::RuntimeEvent>> {
+        (0..*self.event_count.get()).filter_map(|index| self.events.get(&index))
+    }
+
+    /// The weight used by the hooks and extrinsics of the current block so far.
+    pub fn block_weight(&self) -> Weight {
+        *self.block_weight.get()
+    }
+
+    /// The weight which can still be used by the extrinsics of the current block.
+    pub fn remaining_weight(&self) -> Weight {
+        T::MAX_BLOCK_WEIGHT.saturating_sub(self.block_weight())
+    }
+
+    /// Account for the weight used by an extrinsic or a hook of the current block.
+    pub fn register_weight(&mut self, weight: Weight) {
+        self.block_weight.put(self.block_weight().saturating_add(weight));
+    }
+
+    /// Forget the weight used by the previous block, at the start of a new block.
+    pub fn reset_block_weight(&mut self) {
+        self.block_weight.put(0);
+    }
+
+    /// Clear the event log, at the start of a new block.
+    pub fn reset_events(&mut self) {
+        for index in 0..*self.event_count.get() {
+            self.events.remove(&index);
+        }
+        self.event_count.put(0);
+    }
+}
+
+/// The event log and the block weight only describe the execution of the current block, so they
+/// are left out of the state of the pallet.
+impl<T: Config> Encode for Pallet<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.block_number.encode_to(dest);
+        self.nonce.encode_to(dest);
+        self.block_hash.encode_to(dest);
+    }
+}
+
+/// The initial state of this pallet, which has nothing to configure.
+pub struct GenesisConfig<T: Config>(core::marker::PhantomData<T>);
+
+impl<T: Config> Default for GenesisConfig<T> {
+    fn default() -> Self {
+        Self(core::marker::PhantomData)
+    }
+}
+
+impl<T: Config> crate::support::BuildGenesisConfig<T> for GenesisConfig<T> {
+    fn build(self, _runtime: &mut T) -> DispatchResult {
+        Ok(())
+    }
+}
+
+impl<T: Config> crate::support::Hooks<T, T::BlockNumber> for Pallet<T> {}
+
+impl<T: Config> Transactional for Pallet<T> {
+    fn start_transaction(&mut self) {
+        self.events.start_transaction();
+        self.event_count.start_transaction();
+        self.block_weight.start_transaction();
+    }
+
+    fn commit_transaction(&mut self) {
+        self.events.commit_transaction();
+        self.event_count.commit_transaction();
+        self.block_weight.commit_transaction();
+    }
+
+    fn rollback_transaction(&mut self) {
+        self.events.rollback_transaction();
+        self.event_count.rollback_transaction();
+        self.block_weight.rollback_transaction();
     }
 }
 
 #[cfg(test)]
 mod test {
+    use crate::support::Storage;
+
     struct TestConfig;
     impl super::Config for TestConfig {
         type AccountId = String;
         type BlockNumber = u32;
         type Nonce = u32;
+        type RuntimeEvent = &'static str;
+        type RuntimeOrigin = crate::support::Origin<String>;
+        type Hashing = crate::support::BlakeTwo256;
+        type Signature = crate::support::TestSignature;
+        const MAX_BLOCK_WEIGHT: crate::support::Weight = 100;
     }
 
 
     #[test]
     fn init_system() {
-        let mut system = super::Pallet::<TestConfig>::new();
+        let mut system = super::Pallet::<TestConfig>::new(&Storage::in_memory());
         let alice = "alice".to_string();
 //        let bob = "bob".to_string();
 
         system.inc_block_number();
-        system.inc_nonce(&alice);
+        assert_eq!(system.inc_nonce(&alice), 0);
         assert_eq!(system.block_number(), 1);
-        assert_eq!(system.nonce.get("alice"), Some(&1));
-        assert_eq!(system.nonce.get("bob"), None);
+        assert_eq!(system.account_nonce(&alice), 1);
+        assert_eq!(system.account_nonce(&"bob".to_string()), 0);
+
+        system.kill_account(&alice);
+        assert_eq!(system.account_nonce(&alice), 0);
         /* TODO: Create a test which checks the following:
             - Increment the current block number.
             - Increment the nonce of `alice`.
@@ -65,5 +312,78 @@ mod test {
             - Check the nonce of `bob` is what we expect.
         */
     }
-}
 
+    #[test]
+    fn check_nonces() {
+        let mut system = super::Pallet::<TestConfig>::new(&Storage::in_memory());
+        let alice = "alice".to_string();
+
+        assert_eq!(system.check_and_inc_nonce(&alice, 1), Err(super::Error::FutureNonce.into()));
+        assert_eq!(system.check_and_inc_nonce(&alice, 0), Ok(()));
+        assert_eq!(system.check_and_inc_nonce(&alice, 1), Ok(()));
+        assert_eq!(system.check_and_inc_nonce(&alice, 1), Err(super::Error::StaleNonce.into()));
+        assert_eq!(system.check_and_inc_nonce(&alice, 0), Err(super::Error::StaleNonce.into()));
+        assert_eq!(system.account_nonce(&alice), 2);
+    }
+
+    #[test]
+    fn origins() {
+        use crate::support::{DispatchError, ModuleError, Origin};
+        let bad_origin =
+            DispatchError::Module(ModuleError { index: Some(0), error: 5, message: "BadOrigin" });
+        let alice = Origin::Signed("alice".to_string());
+
+        assert_eq!(super::ensure_signed::<TestConfig>(alice.clone()), Ok("alice".to_string()));
+        assert_eq!(super::ensure_signed::<TestConfig>(Origin::Root), Err(bad_origin));
+        assert_eq!(super::ensure_signed::<TestConfig>(Origin::None), Err(bad_origin));
+        assert_eq!(super::ensure_root::<TestConfig>(Origin::Root), Ok(()));
+        assert_eq!(super::ensure_root::<TestConfig>(alice), Err(bad_origin));
+        assert_eq!(super::ensure_root::<TestConfig>(Origin::None), Err(bad_origin));
+    }
+
+    #[test]
+    fn block_weight() {
+        let mut system = super::Pallet::<TestConfig>::new(&Storage::in_memory());
+        assert_eq!(system.remaining_weight(), 100);
+
+        system.register_weight(30);
+        system.register_weight(50);
+        assert_eq!(system.block_weight(), 80);
+        assert_eq!(system.remaining_weight(), 20);
+        system.register_weight(50);
+        assert_eq!(system.remaining_weight(), 0);
+
+        system.reset_block_weight();
+        assert_eq!(system.remaining_weight(), 100);
+    }
+
+    #[test]
+    fn deposit_events() {
+        let mut system = super::Pallet::<TestConfig>::new(&Storage::in_memory());
+        assert_eq!(system.events().count(), 0);
+
+        system.deposit_event(super::Phase::ApplyExtrinsic(0), "first");
+        system.deposit_event(super::Phase::ApplyExtrinsic(1), "second");
+        let events = system.events().map(|record| (record.phase, record.event)).collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            vec![(super::Phase::ApplyExtrinsic(0), "first"), (super::Phase::ApplyExtrinsic(1), "second")]
+        );
+
+        system.reset_events();
+        assert_eq!(system.events().count(), 0);
+    }
+
+    #[test]
+    fn block_hashes() {
+        use crate::support::H256;
+        let mut system = super::Pallet::<TestConfig>::new(&Storage::in_memory());
+        assert_eq!(system.parent_hash(), H256::default());
+
+        system.inc_block_number();
+        system.finalize_block(H256([1; 32]));
+        assert_eq!(system.parent_hash(), H256([1; 32]));
+        assert_eq!(system.block_hash(&1), Some(H256([1; 32])));
+        assert_eq!(system.block_hash(&2), None);
+    }
+}
@@ -0,0 +1,313 @@
+use core::fmt::Debug;
+use crate::support::{
+    Decode, Dispatch, DispatchError, DispatchResultWithPostInfo, Encode, GetWeight, HasPallet,
+    PostDispatchInfo, Storage, Transactional, TransactionalVec, Weight,
+};
+
+pub trait Config: crate::system::Config {
+    /// The aggregated call type of the runtime, generated by `#[macros::runtime]`, which can be
+    /// batched.
+    type RuntimeCall: GetWeight + Clone + Debug + PartialEq + Encode + Decode;
+}
+
+/// The weight of dispatching each call of a batch, on top of the weight of the call itself.
+const ITEM_WEIGHT: Weight = 1_000;
+
+/// The weight of a batch of `calls`, as declared by its calls.
+fn batch_weight<T: Config>(calls: &[T::RuntimeCall]) -> Weight {
+    let weights = calls.iter().map(|call| call.weight().saturating_add(ITEM_WEIGHT));
+    weights.fold(0, Weight::saturating_add)
+}
+
+/// The events emitted by this pallet.
+#[derive(Debug, PartialEq)]
+pub enum Event {
+    /// A batch was interrupted by the call at `index`, which failed with `error`.
+    BatchInterrupted { index: u32, error: DispatchError },
+    /// All the calls of a batch were dispatched successfully.
+    BatchCompleted,
+    /// All the calls of a batch were dispatched, but some of them failed.
+    BatchCompletedWithErrors,
+    /// A call of a batch was dispatched successfully.
+    ItemCompleted,
+    /// A call of a batch failed with `error`, which did not stop the batch.
+    ItemFailed { error: DispatchError },
+}
+
+/// This is the Utility Module.
+/// It lets an origin dispatch a batch of calls in a single extrinsic.
+#[derive(Debug)]
+pub struct Pallet<T: Config> {
+    /// The events emitted by this pallet which have not been collected by the runtime yet.
+    events: TransactionalVec<Event>,
+    _runtime: core::marker::PhantomData<T>,
+}
+
+impl<T: Config> Pallet<T> {
+    /// Create a new instance of the Utility Module. It keeps no state, so it has nothing to put in
+    /// `storage`.
+    pub fn new(_storage: &Storage) -> Self {
+        Self { events: TransactionalVec::new(), _runtime: core::marker::PhantomData }
+    }
+
+    fn deposit_event(&mut self, event: Event) {
+        self.events.push(event);
+    }
+
+    pub fn take_events(&mut self) -> Vec<Event> {
+        self.events.take()
+    }
+}
+
+impl<T: Config> Pallet<T>
+where
+    T: HasPallet<Pallet<T>>
+        + Dispatch<Origin = T::RuntimeOrigin, Call = <T as Config>::RuntimeCall>
+        + Transactional,
+{
+    /// Dispatch `call` from `origin` in its own transaction, so a failed call leaves no trace.
+    /// Returns the weight it used along with its result.
+    fn dispatch_item(
+        runtime: &mut T,
+        origin: T::RuntimeOrigin,
+        call: <T as Config>::RuntimeCall,
+    ) -> (Weight, Result<(), DispatchError>) {
+        let weight = call.weight().saturating_add(ITEM_WEIGHT);
+        let result =
+            crate::support::with_transaction(runtime, |runtime| runtime.dispatch(origin, call));
+        match result {
+            Ok(post_info) => (post_info.calc_actual_weight(weight), Ok(())),
+            Err(error) => (weight, Err(error)),
+        }
+    }
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T>
+where
+    T: HasPallet<Pallet<T>>
+        + Dispatch<Origin = T::RuntimeOrigin, Call = <T as Config>::RuntimeCall>
+        + Transactional,
+{
+    /// Dispatch `calls` in order from the origin of this call, stopping at the first one which
+    /// fails. The calls before it stay dispatched, and the batch itself succeeds.
+    #[weight(batch_weight::<T>(calls))]
+    pub fn batch(
+        runtime: &mut T,
+        origin: T::RuntimeOrigin,
+        calls: Vec<<T as Config>::RuntimeCall>,
+    ) -> DispatchResultWithPostInfo {
+        let mut weight: Weight = 0;
+        for (index, call) in calls.into_iter().enumerate() {
+            let (item_weight, result) = Self::dispatch_item(runtime, origin.clone(), call);
+            weight = weight.saturating_add(item_weight);
+            let utility: &mut Self = runtime.pallet_mut();
+            match result {
+                Ok(()) => utility.deposit_event(Event::ItemCompleted),
+                Err(error) => {
+                    utility.deposit_event(Event::BatchInterrupted { index: index as u32, error });
+                    return Ok(PostDispatchInfo { actual_weight: Some(weight) })
+                },
+            }
+        }
+        let utility: &mut Self = runtime.pallet_mut();
+        utility.deposit_event(Event::BatchCompleted);
+        Ok(PostDispatchInfo { actual_weight: Some(weight) })
+    }
+
+    /// Dispatch `calls` in order from the origin of this call, all or nothing: if one of them
+    /// fails, the state is restored to what it was before the batch, which fails with its error.
+    #[weight(batch_weight::<T>(calls))]
+    pub fn batch_all(
+        runtime: &mut T,
+        origin: T::RuntimeOrigin,
+        calls: Vec<<T as Config>::RuntimeCall>,
+    ) -> DispatchResultWithPostInfo {
+        crate::support::with_transaction(runtime, |runtime| {
+            let mut weight: Weight = 0;
+            for call in calls {
+                let (item_weight, result) = Self::dispatch_item(runtime, origin.clone(), call);
+                weight = weight.saturating_add(item_weight);
+                result?;
+                let utility: &mut Self = runtime.pallet_mut();
+                utility.deposit_event(Event::ItemCompleted);
+            }
+            let utility: &mut Self = runtime.pallet_mut();
+            utility.deposit_event(Event::BatchCompleted);
+            Ok(PostDispatchInfo { actual_weight: Some(weight) })
+        })
+    }
+
+    /// Dispatch all of `calls` in order from the origin of this call, even when some of them
+    /// fail. The batch itself always succeeds.
+    #[weight(batch_weight::<T>(calls))]
+    pub fn force_batch(
+        runtime: &mut T,
+        origin: T::RuntimeOrigin,
+        calls: Vec<<T as Config>::RuntimeCall>,
+    ) -> DispatchResultWithPostInfo {
+        let mut weight: Weight = 0;
+        let mut failed = false;
+        for call in calls {
+            let (item_weight, result) = Self::dispatch_item(runtime, origin.clone(), call);
+            weight = weight.saturating_add(item_weight);
+            let utility: &mut Self = runtime.pallet_mut();
+            match result {
+                Ok(()) => utility.deposit_event(Event::ItemCompleted),
+                Err(error) => {
+                    failed = true;
+                    utility.deposit_event(Event::ItemFailed { error });
+                },
+            }
+        }
+        let utility: &mut Self = runtime.pallet_mut();
+        if failed {
+            utility.deposit_event(Event::BatchCompletedWithErrors);
+        } else {
+            utility.deposit_event(Event::BatchCompleted);
+        }
+        Ok(PostDispatchInfo { actual_weight: Some(weight) })
+    }
+}
+
+/// This pallet has no state of its own.
+impl<T: Config> Encode for Pallet<T> {
+    fn encode_to(&self, _dest: &mut Vec<u8>) {}
+}
+
+/// The initial state of this pallet, which has nothing to configure.
+pub struct GenesisConfig<T: Config>(core::marker::PhantomData<T>);
+
+impl<T: Config> Default for GenesisConfig<T> {
+    fn default() -> Self {
+        Self(core::marker::PhantomData)
+    }
+}
+
+impl<T: Config> crate::support::BuildGenesisConfig<T> for GenesisConfig<T> {
+    fn build(self, _runtime: &mut T) -> crate::support::DispatchResult {
+        Ok(())
+    }
+}
+
+impl<T: Config> crate::support::Hooks<T, T::BlockNumber> for Pallet<T> {}
+
+impl<T: Config> crate::support::PalletEvent for Pallet<T> {
+    type Event = Event;
+}
+
+impl<T: Config> Transactional for Pallet<T> {
+    fn start_transaction(&mut self) {
+        self.events.start_transaction();
+    }
+
+    fn commit_transaction(&mut self) {
+        self.events.commit_transaction();
+    }
+
+    fn rollback_transaction(&mut self) {
+        self.events.rollback_transaction();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Pallet;
+    use crate::{
+        balances,
+        mock::{RuntimeCall, TestRuntime},
+        support::{DispatchError, GetWeight, Origin},
+    };
+
+    fn transfer(to: &str, amount: u128) -> RuntimeCall {
+        RuntimeCall::balances(balances::Call::transfer_allow_death { to: to.to_string(), amount })
+    }
+
+    #[test]
+    fn batch_stops_at_the_first_error() {
+        let mut runtime = TestRuntime::new();
+        let alice = Origin::Signed("alice".to_string());
+        runtime.balances.set_balance(&"alice".to_string(), 100);
+
+        let calls = vec![transfer("bob", 20), transfer("charlie", 200), transfer("dave", 20)];
+        let call = RuntimeCall::utility(super::Call::batch { calls: calls.clone() });
+        assert_eq!(call.weight(), 33_000);
+        let post_info = Pallet::batch(&mut runtime, alice.clone(), calls).unwrap();
+        assert_eq!(post_info.actual_weight, Some(22_000));
+        assert_eq!(runtime.balances.balance(&"alice".to_string()), 80);
+        assert_eq!(runtime.balances.balance(&"dave".to_string()), 0);
+        assert_eq!(
+            runtime.utility.take_events(),
+            vec![
+                super::Event::ItemCompleted,
+                super::Event::BatchInterrupted {
+                    index: 1,
+                    error: DispatchError::from(balances::Error::InsufficientBalance).in_pallet(1),
+                },
+            ]
+        );
+
+        // Calls are made from the origin of the batch.
+        let calls = vec![RuntimeCall::balances(balances::Call::mint {
+            who: "bob".to_string(),
+            amount: 50,
+        })];
+        assert!(Pallet::batch(&mut runtime, alice, calls.clone()).is_ok());
+        assert_eq!(runtime.balances.balance(&"bob".to_string()), 20);
+        assert!(Pallet::batch(&mut runtime, Origin::Root, calls).is_ok());
+        assert_eq!(runtime.balances.balance(&"bob".to_string()), 70);
+    }
+
+    #[test]
+    fn batch_all_is_atomic() {
+        let mut runtime = TestRuntime::new();
+        let alice = Origin::Signed("alice".to_string());
+        runtime.balances.set_balance(&"alice".to_string(), 100);
+
+        let calls = vec![transfer("bob", 20), transfer("charlie", 200)];
+        assert_eq!(
+            Pallet::batch_all(&mut runtime, alice.clone(), calls),
+            Err(DispatchError::from(balances::Error::InsufficientBalance).in_pallet(1))
+        );
+        assert_eq!(runtime.balances.balance(&"alice".to_string()), 100);
+        assert_eq!(runtime.balances.balance(&"bob".to_string()), 0);
+        assert_eq!(runtime.balances.take_events(), vec![]);
+        assert_eq!(runtime.utility.take_events(), vec![]);
+
+        let calls = vec![transfer("bob", 20), transfer("charlie", 20)];
+        assert!(Pallet::batch_all(&mut runtime, alice, calls).is_ok());
+        assert_eq!(runtime.balances.balance(&"alice".to_string()), 60);
+        assert_eq!(
+            runtime.utility.take_events(),
+            vec![
+                super::Event::ItemCompleted,
+                super::Event::ItemCompleted,
+                super::Event::BatchCompleted,
+            ]
+        );
+    }
+
+    #[test]
+    fn force_batch_continues_past_errors() {
+        let mut runtime = TestRuntime::new();
+        let alice = Origin::Signed("alice".to_string());
+        runtime.balances.set_balance(&"alice".to_string(), 100);
+
+        let calls = vec![transfer("bob", 20), transfer("charlie", 200), transfer("dave", 20)];
+        let post_info = Pallet::force_batch(&mut runtime, alice, calls).unwrap();
+        assert_eq!(post_info.actual_weight, Some(33_000));
+        assert_eq!(runtime.balances.balance(&"alice".to_string()), 60);
+        assert_eq!(runtime.balances.balance(&"dave".to_string()), 20);
+        assert_eq!(
+            runtime.utility.take_events(),
+            vec![
+                super::Event::ItemCompleted,
+                super::Event::ItemFailed {
+                    error: DispatchError::from(balances::Error::InsufficientBalance).in_pallet(1),
+                },
+                super::Event::ItemCompleted,
+                super::Event::BatchCompletedWithErrors,
+            ]
+        );
+    }
+}
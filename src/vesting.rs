@@ -0,0 +1,480 @@
+use core::fmt::Debug;
+use num::traits::{CheckedMul, One, Saturating, Zero};
+use crate::{
+    support::{
+        CodecError, Currency, Decode, DispatchResult, Encode, ExistenceRequirement, HasPallet,
+        LockIdentifier, LockableCurrency, Storage, StorageMap, Transactional, TransactionalVec,
+    },
+    system,
+};
+
+/// The type of the funds which vest.
+pub type BalanceOf<T> = <<T as Config>::Currency as Currency<
+    <T as crate::system::Config>::AccountId,
+    T,
+>>::Balance;
+
+/// The vesting schedule of the runtime, in its currency and block numbers.
+pub type VestingInfoOf<T> = VestingInfo<BalanceOf<T>, <T as crate::system::Config>::BlockNumber>;
+
+/// The name of the lock which keeps the funds of an account from being spent until they vest.
+const VESTING_ID: LockIdentifier = *b"vesting ";
+
+pub trait Config: crate::system::Config + Sized {
+    /// The currency in which funds vest, by locking the part which has not vested yet.
+    type Currency: LockableCurrency<Self::AccountId, Self>;
+
+    /// The smallest amount which can be transferred with `vested_transfer`.
+    const MIN_VESTED_TRANSFER: BalanceOf<Self>;
+
+    /// The maximum number of vesting schedules of an account.
+    const MAX_VESTING_SCHEDULES: u32;
+
+    /// Convert a block number into a balance, to count the funds vested over a number of blocks.
+    fn block_number_to_balance(block_number: Self::BlockNumber) -> BalanceOf<Self>;
+}
+
+/// A linear vesting schedule: `locked` funds, of which `per_block` are unlocked at every block
+/// after `starting_block`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VestingInfo<Balance, BlockNumber> {
+    /// The funds locked by the schedule when it starts.
+    pub locked: Balance,
+    /// The funds unlocked at every block.
+    pub per_block: Balance,
+    /// The block after which funds start to be unlocked.
+    pub starting_block: BlockNumber,
+}
+
+impl<Balance: Encode, BlockNumber: Encode> Encode for VestingInfo<Balance, BlockNumber> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.locked.encode_to(dest);
+        self.per_block.encode_to(dest);
+        self.starting_block.encode_to(dest);
+    }
+}
+
+impl<Balance: Decode, BlockNumber: Decode> Decode for VestingInfo<Balance, BlockNumber> {
+    fn decode(input: &mut &[u8]) -> Result<Self, CodecError> {
+        Ok(VestingInfo {
+            locked: Decode::decode(input)?,
+            per_block: Decode::decode(input)?,
+            starting_block: Decode::decode(input)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[macros::error]
+pub enum Error {
+    /// The account has no vesting schedule.
+    NotVesting,
+    /// The account already has `Config::MAX_VESTING_SCHEDULES` vesting schedules.
+    AtMaxVestingSchedules,
+    /// The amount transferred is below `Config::MIN_VESTED_TRANSFER`.
+    AmountLow,
+    /// The account has no vesting schedule at this index.
+    ScheduleIndexOutOfBounds,
+    /// The schedule locks no funds, or never unlocks them.
+    InvalidScheduleParams,
+}
+
+/// The events emitted by this pallet.
+#[derive(Debug)]
+pub enum Event<T: Config> {
+    /// The funds of `account` which have not vested yet changed to `unvested`.
+    VestingUpdated { account: T::AccountId, unvested: BalanceOf<T> },
+    /// All the funds of `account` have vested.
+    VestingCompleted { account: T::AccountId },
+}
+
+// Implemented by hand, since a derive would require `T` itself to implement `PartialEq`.
+impl<T: Config> PartialEq for Event<T> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (
+                Self::VestingUpdated { account: a0, unvested: a1 },
+                Self::VestingUpdated { account: b0, unvested: b1 },
+            ) => (a0, a1) == (b0, b1),
+            (
+                Self::VestingCompleted { account: a },
+                Self::VestingCompleted { account: b },
+            ) => a == b,
+            _ => false,
+        }
+    }
+}
+
+/// This is the Vesting Module.
+/// It locks funds of an account, and unlocks them linearly as blocks go by.
+#[derive(Debug)]
+pub struct Pallet<T: Config> {
+    /// The vesting schedules of each account which still has funds vesting.
+    vesting: StorageMap<T::AccountId, Vec<VestingInfoOf<T>>>,
+    /// The events emitted by this pallet which have not been collected by the runtime yet.
+    events: TransactionalVec<Event<T>>,
+}
+
+impl<T: Config> Pallet<T> {
+    /// Create a new instance of the Vesting Module.
+    pub fn new(storage: &Storage) -> Self {
+        Self {
+            vesting: StorageMap::new(storage, "Vesting", "Vesting"),
+            events: TransactionalVec::new(),
+        }
+    }
+
+    fn deposit_event(&mut self, event: Event<T>) {
+        self.events.push(event);
+    }
+
+    pub fn take_events(&mut self) -> Vec<Event<T>> {
+        self.events.take()
+    }
+
+    /// The vesting schedules of `who`.
+    pub fn vesting(&self, who: &T::AccountId) -> Vec<VestingInfoOf<T>> {
+        self.vesting.get(who).unwrap_or_default()
+    }
+
+    /// The funds of `schedule` which are still locked at block `now`.
+    fn locked_at(schedule: &VestingInfoOf<T>, now: T::BlockNumber) -> BalanceOf<T> {
+        if now <= schedule.starting_block {
+            return schedule.locked
+        }
+        let elapsed = T::block_number_to_balance(now)
+            .saturating_sub(T::block_number_to_balance(schedule.starting_block));
+        match schedule.per_block.checked_mul(&elapsed) {
+            Some(vested) => schedule.locked.saturating_sub(vested),
+            None => BalanceOf::<T>::zero(),
+        }
+    }
+
+    /// The block, as a balance, at which `schedule` has unlocked all of its funds.
+    fn ending_block(schedule: &VestingInfoOf<T>) -> BalanceOf<T> {
+        let mut duration = schedule.locked / schedule.per_block;
+        if !(schedule.locked % schedule.per_block).is_zero() {
+            duration = duration.saturating_add(BalanceOf::<T>::one());
+        }
+        T::block_number_to_balance(schedule.starting_block).saturating_add(duration)
+    }
+
+    /// Merge two schedules into one, which unlocks what they still lock at block `now` until the
+    /// later of their ends. There is nothing left to merge when both have ended.
+    fn merge_vesting_info(
+        now: T::BlockNumber,
+        schedule1: &VestingInfoOf<T>,
+        schedule2: &VestingInfoOf<T>,
+    ) -> Option<VestingInfoOf<T>> {
+        let locked =
+            Self::locked_at(schedule1, now).saturating_add(Self::locked_at(schedule2, now));
+        if locked.is_zero() {
+            return None
+        }
+        let ending_block = Self::ending_block(schedule1).max(Self::ending_block(schedule2));
+        let starting_block = now.max(schedule1.starting_block).max(schedule2.starting_block);
+        let duration = ending_block.saturating_sub(T::block_number_to_balance(starting_block));
+        let per_block = if duration.is_zero() {
+            locked
+        } else {
+            (locked / duration).max(BalanceOf::<T>::one())
+        };
+        Some(VestingInfo { locked, per_block, starting_block })
+    }
+}
+
+impl<T: Config> Pallet<T>
+where
+    T: HasPallet<Pallet<T>> + HasPallet<system::Pallet<T>>,
+{
+    /// The funds of `who` which have not vested yet, if it has any vesting schedule.
+    pub fn vesting_balance(runtime: &T, who: &T::AccountId) -> Option<BalanceOf<T>> {
+        let now = HasPallet::<system::Pallet<T>>::pallet(runtime).block_number();
+        let pallet: &Self = runtime.pallet();
+        let schedules = pallet.vesting.get(who)?;
+        let locked = schedules.iter().map(|schedule| Self::locked_at(schedule, now));
+        Some(locked.fold(BalanceOf::<T>::zero(), |sum, locked| sum.saturating_add(locked)))
+    }
+
+    /// Add a vesting schedule to `who`, which locks funds it already has. This is how funds are
+    /// given out at genesis, and how `vested_transfer` locks the funds it transfers.
+    pub fn add_vesting_schedule(
+        runtime: &mut T,
+        who: &T::AccountId,
+        schedule: VestingInfoOf<T>,
+    ) -> DispatchResult {
+        Self::ensure_can_add_schedule(runtime, who, &schedule)?;
+        let pallet: &Self = runtime.pallet();
+        let mut schedules = pallet.vesting(who);
+        schedules.push(schedule);
+        Self::write_vesting(runtime, who, schedules);
+        Ok(())
+    }
+
+    fn ensure_can_add_schedule(
+        runtime: &T,
+        who: &T::AccountId,
+        schedule: &VestingInfoOf<T>,
+    ) -> DispatchResult {
+        if schedule.locked.is_zero() || schedule.per_block.is_zero() {
+            return Err(Error::InvalidScheduleParams.into())
+        }
+        let pallet: &Self = runtime.pallet();
+        if pallet.vesting(who).len() >= T::MAX_VESTING_SCHEDULES as usize {
+            return Err(Error::AtMaxVestingSchedules.into())
+        }
+        Ok(())
+    }
+
+    /// Store the schedules of `who` which have not ended yet, and lock the funds they still lock.
+    fn write_vesting(runtime: &mut T, who: &T::AccountId, mut schedules: Vec<VestingInfoOf<T>>) {
+        let now = HasPallet::<system::Pallet<T>>::pallet(runtime).block_number();
+        schedules.retain(|schedule| !Self::locked_at(schedule, now).is_zero());
+        let unvested = schedules
+            .iter()
+            .map(|schedule| Self::locked_at(schedule, now))
+            .fold(BalanceOf::<T>::zero(), |sum, locked| sum.saturating_add(locked));
+
+        let pallet: &mut Self = runtime.pallet_mut();
+        if schedules.is_empty() {
+            pallet.vesting.remove(who);
+            pallet.deposit_event(Event::VestingCompleted { account: who.clone() });
+            T::Currency::remove_lock(runtime, VESTING_ID, who);
+        } else {
+            pallet.vesting.insert(who.clone(), schedules);
+            pallet.deposit_event(Event::VestingUpdated { account: who.clone(), unvested });
+            T::Currency::set_lock(runtime, VESTING_ID, who, unvested);
+        }
+    }
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T>
+where
+    T: HasPallet<Pallet<T>> + HasPallet<system::Pallet<T>>,
+{
+    /// Unlock the funds of the caller which have vested so far.
+    #[weight(10_000)]
+    pub fn vest(runtime: &mut T, caller: T::AccountId) -> DispatchResult {
+        let pallet: &Self = runtime.pallet();
+        let schedules = pallet.vesting.get(&caller).ok_or(Error::NotVesting)?;
+        Self::write_vesting(runtime, &caller, schedules);
+        Ok(())
+    }
+
+    /// Transfer `schedule.locked` from the caller to `target`, where it vests following
+    /// `schedule`.
+    #[weight(10_000)]
+    pub fn vested_transfer(
+        runtime: &mut T,
+        caller: T::AccountId,
+        target: T::AccountId,
+        schedule: VestingInfoOf<T>,
+    ) -> DispatchResult {
+        if schedule.locked < T::MIN_VESTED_TRANSFER {
+            return Err(Error::AmountLow.into())
+        }
+        Self::ensure_can_add_schedule(runtime, &target, &schedule)?;
+        T::Currency::transfer(
+            runtime,
+            &caller,
+            &target,
+            schedule.locked,
+            ExistenceRequirement::AllowDeath,
+        )?;
+        Self::add_vesting_schedule(runtime, &target, schedule)
+    }
+
+    /// Merge two vesting schedules of the caller into one, which unlocks what they still lock
+    /// until the later of their ends.
+    #[weight(10_000)]
+    pub fn merge_schedules(
+        runtime: &mut T,
+        caller: T::AccountId,
+        schedule1_index: u32,
+        schedule2_index: u32,
+    ) -> DispatchResult {
+        if schedule1_index == schedule2_index {
+            return Ok(())
+        }
+        let pallet: &Self = runtime.pallet();
+        let mut schedules = pallet.vesting.get(&caller).ok_or(Error::NotVesting)?;
+        let (first, second) = if schedule1_index < schedule2_index {
+            (schedule1_index as usize, schedule2_index as usize)
+        } else {
+            (schedule2_index as usize, schedule1_index as usize)
+        };
+        if second >= schedules.len() {
+            return Err(Error::ScheduleIndexOutOfBounds.into())
+        }
+
+        let schedule2 = schedules.remove(second);
+        let schedule1 = schedules.remove(first);
+        let now = HasPallet::<system::Pallet<T>>::pallet(runtime).block_number();
+        schedules.extend(Self::merge_vesting_info(now, &schedule1, &schedule2));
+        Self::write_vesting(runtime, &caller, schedules);
+        Ok(())
+    }
+}
+
+impl<T: Config> Encode for Pallet<T> {
+    fn encode_to(&self, dest: &mut Vec<u8>) {
+        self.vesting.encode_to(dest);
+    }
+}
+
+/// The initial state of this pallet.
+pub struct GenesisConfig<T: Config> {
+    /// The initial vesting schedules, which lock funds the accounts already have.
+    pub vesting: Vec<(T::AccountId, VestingInfoOf<T>)>,
+}
+
+impl<T: Config> Default for GenesisConfig<T> {
+    fn default() -> Self {
+        Self { vesting: Vec::new() }
+    }
+}
+
+impl<T: Config> crate::support::BuildGenesisConfig<T> for GenesisConfig<T>
+where
+    T: HasPallet<Pallet<T>> + HasPallet<system::Pallet<T>>,
+{
+    fn build(self, runtime: &mut T) -> DispatchResult {
+        for (who, schedule) in self.vesting {
+            Pallet::add_vesting_schedule(runtime, &who, schedule)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Config> crate::support::Hooks<T, T::BlockNumber> for Pallet<T> {}
+
+impl<T: Config> crate::support::PalletEvent for Pallet<T> {
+    type Event = Event<T>;
+}
+
+impl<T: Config> Transactional for Pallet<T> {
+    fn start_transaction(&mut self) {
+        self.events.start_transaction();
+    }
+
+    fn commit_transaction(&mut self) {
+        self.events.commit_transaction();
+    }
+
+    fn rollback_transaction(&mut self) {
+        self.events.rollback_transaction();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Pallet, VestingInfo};
+    use crate::{balances, mock::TestRuntime};
+
+    fn run_to_block(runtime: &mut TestRuntime, block_number: u32) {
+        while runtime.system.block_number() < block_number {
+            runtime.system.inc_block_number();
+        }
+    }
+
+    #[test]
+    fn vesting_locks_unvested_funds() {
+        let mut runtime = TestRuntime::new();
+        let alice = "alice".to_string();
+        let bob = "bob".to_string();
+        runtime.balances.set_balance(&alice, 1_000);
+
+        let schedule = VestingInfo { locked: 500, per_block: 10, starting_block: 0 };
+        assert_eq!(Pallet::add_vesting_schedule(&mut runtime, &alice, schedule), Ok(()));
+        assert_eq!(
+            Pallet::add_vesting_schedule(
+                &mut runtime,
+                &alice,
+                VestingInfo { locked: 500, per_block: 0, starting_block: 0 }
+            ),
+            Err(super::Error::InvalidScheduleParams.into())
+        );
+        assert_eq!(runtime.balances.locked_balance(&alice), 500);
+        assert_eq!(
+            balances::Pallet::transfer_allow_death(&mut runtime, alice.clone(), bob.clone(), 501),
+            Err(balances::Error::LiquidityRestrictions.into())
+        );
+
+        // Funds vest with every block, but stay locked until the account calls `vest`.
+        run_to_block(&mut runtime, 10);
+        assert_eq!(Pallet::vesting_balance(&runtime, &alice), Some(400));
+        assert_eq!(runtime.balances.locked_balance(&alice), 500);
+        assert_eq!(Pallet::vest(&mut runtime, alice.clone()), Ok(()));
+        assert_eq!(runtime.balances.locked_balance(&alice), 400);
+        assert_eq!(
+            balances::Pallet::transfer_allow_death(&mut runtime, alice.clone(), bob.clone(), 600),
+            Ok(())
+        );
+
+        run_to_block(&mut runtime, 60);
+        assert_eq!(Pallet::vest(&mut runtime, alice.clone()), Ok(()));
+        assert_eq!(runtime.balances.locked_balance(&alice), 0);
+        assert_eq!(runtime.vesting.vesting(&alice), []);
+        assert_eq!(Pallet::vest(&mut runtime, alice.clone()), Err(super::Error::NotVesting.into()));
+        assert_eq!(
+            runtime.vesting.take_events(),
+            vec![
+                super::Event::VestingUpdated { account: alice.clone(), unvested: 500 },
+                super::Event::VestingUpdated { account: alice.clone(), unvested: 400 },
+                super::Event::VestingCompleted { account: alice },
+            ]
+        );
+    }
+
+    #[test]
+    fn vested_transfer_and_merge_schedules() {
+        let mut runtime = TestRuntime::new();
+        let alice = "alice".to_string();
+        let bob = "bob".to_string();
+        runtime.balances.set_balance(&alice, 1_000);
+
+        let schedule1 = VestingInfo { locked: 100, per_block: 10, starting_block: 0 };
+        let schedule2 = VestingInfo { locked: 200, per_block: 20, starting_block: 5 };
+        assert_eq!(
+            Pallet::vested_transfer(
+                &mut runtime,
+                alice.clone(),
+                bob.clone(),
+                VestingInfo { locked: 40, per_block: 10, starting_block: 0 }
+            ),
+            Err(super::Error::AmountLow.into())
+        );
+        assert_eq!(
+            Pallet::vested_transfer(&mut runtime, alice.clone(), bob.clone(), schedule1),
+            Ok(())
+        );
+        assert_eq!(
+            Pallet::vested_transfer(&mut runtime, alice.clone(), bob.clone(), schedule2),
+            Ok(())
+        );
+        assert_eq!(
+            Pallet::vested_transfer(&mut runtime, alice.clone(), bob.clone(), schedule1),
+            Err(super::Error::AtMaxVestingSchedules.into())
+        );
+        assert_eq!(runtime.balances.balance(&alice), 700);
+        assert_eq!(runtime.balances.balance(&bob), 300);
+        assert_eq!(runtime.balances.locked_balance(&bob), 300);
+
+        // At block 5, the schedules still lock 50 until block 10 and 200 until block 15.
+        run_to_block(&mut runtime, 5);
+        assert_eq!(
+            Pallet::merge_schedules(&mut runtime, bob.clone(), 0, 2),
+            Err(super::Error::ScheduleIndexOutOfBounds.into())
+        );
+        assert_eq!(Pallet::merge_schedules(&mut runtime, bob.clone(), 1, 0), Ok(()));
+        assert_eq!(
+            runtime.vesting.vesting(&bob),
+            [VestingInfo { locked: 250, per_block: 25, starting_block: 5 }]
+        );
+        assert_eq!(runtime.balances.locked_balance(&bob), 250);
+
+        run_to_block(&mut runtime, 15);
+        assert_eq!(Pallet::vesting_balance(&runtime, &bob), Some(0));
+    }
+}